@@ -0,0 +1,287 @@
+/*
+ * File: async_link.rs
+ * Project: src
+ * Created Date: 15/10/2022
+ * Author: Shun Suzuki
+ * -----
+ * Last Modified: 15/10/2022
+ * Modified By: Shun Suzuki (suzuki@hapis.k.u-tokyo.ac.jp)
+ * -----
+ * Copyright (c) 2022 Shun Suzuki. All rights reserved.
+ *
+ */
+
+use std::future::Future;
+
+use anyhow::Result;
+use autd3_driver::{RxDatagram, TxDatagram};
+
+use crate::{
+    error::AUTDInternalError,
+    geometry::{Geometry, GeometryBuilder, LegacyTransducer, Transducer, UnitQuaternion},
+    link::Link,
+};
+
+/// Asynchronous version of [Link]
+pub trait AsyncLink: Send {
+    fn open<T: Transducer>(
+        &mut self,
+        geometry: &Geometry<T>,
+    ) -> impl Future<Output = Result<()>> + Send;
+    fn close(&mut self) -> impl Future<Output = Result<()>> + Send;
+    fn send(&mut self, tx: &TxDatagram) -> impl Future<Output = Result<bool>> + Send;
+    fn receive(&mut self, rx: &mut RxDatagram) -> impl Future<Output = Result<bool>> + Send;
+    fn is_open(&self) -> bool;
+    /// Whether [AsyncLink::receive] returns the data from the devices.
+    fn supports_read(&self) -> bool {
+        true
+    }
+}
+
+/// [AsyncLink] running a blocking [Link] on the blocking thread pool of tokio
+///
+/// Since the transducers cannot be moved to another thread, [open](AsyncLink::open) passes the link a copy of the geometry with the same arrangement of the devices, i.e., their origins, rotations, labels, and phase offsets, made of [LegacyTransducer].
+pub struct BlockingLink<L: Link + 'static> {
+    link: Option<L>,
+}
+
+impl<L: Link + 'static> BlockingLink<L> {
+    pub fn new(link: L) -> Self {
+        Self { link: Some(link) }
+    }
+
+    /// Take the wrapped link back. Returns `None` if the link was lost by a panic in it.
+    pub fn into_inner(self) -> Option<L> {
+        self.link
+    }
+
+    async fn run_blocking<R, F>(&mut self, f: F) -> Result<R>
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut L) -> Result<R> + Send + 'static,
+    {
+        let mut link = self.link.take().ok_or(AUTDInternalError::LinkClosed)?;
+        let (link, res) = tokio::task::spawn_blocking(move || {
+            let res = f(&mut link);
+            (link, res)
+        })
+        .await?;
+        self.link = Some(link);
+        res
+    }
+}
+
+/// Copy of the arrangement of the devices in `geometry`, which can be moved to another thread
+fn detached_geometry<T: Transducer>(geometry: &Geometry<T>) -> Geometry<LegacyTransducer> {
+    let mut detached = GeometryBuilder::new()
+        .attenuation(geometry.attenuation)
+        .sound_speed(geometry.sound_speed())
+        .legacy_mode()
+        .build();
+    geometry.devices().iter().enumerate().for_each(|(i, dev)| {
+        let origin = &dev.transducers()[0];
+        let rotation = UnitQuaternion::from_basis_unchecked(&[
+            *origin.x_direction(),
+            *origin.y_direction(),
+            *origin.z_direction(),
+        ]);
+        detached.add_device_quaternion(*origin.position(), rotation);
+        detached.set_device_label(i, dev.label().to_owned());
+        detached.set_device_phase_offset(i, dev.phase_offset());
+    });
+    detached
+}
+
+impl<L: Link + 'static> AsyncLink for BlockingLink<L> {
+    fn open<T: Transducer>(
+        &mut self,
+        geometry: &Geometry<T>,
+    ) -> impl Future<Output = Result<()>> + Send {
+        let geometry = detached_geometry(geometry);
+        self.run_blocking(move |link| link.open(&geometry))
+    }
+
+    async fn close(&mut self) -> Result<()> {
+        self.run_blocking(|link| link.close()).await
+    }
+
+    async fn send(&mut self, tx: &TxDatagram) -> Result<bool> {
+        let tx = tx.clone();
+        self.run_blocking(move |link| link.send(&tx)).await
+    }
+
+    async fn receive(&mut self, rx: &mut RxDatagram) -> Result<bool> {
+        let mut buf = RxDatagram::new(rx.messages().len());
+        let (buf, res) = self
+            .run_blocking(move |link| {
+                let res = link.receive(&mut buf)?;
+                Ok((buf, res))
+            })
+            .await?;
+        rx.copy_from(&buf);
+        Ok(res)
+    }
+
+    fn is_open(&self) -> bool {
+        self.link.as_ref().is_some_and(|link| link.is_open())
+    }
+
+    fn supports_read(&self) -> bool {
+        self.link.as_ref().is_some_and(|link| link.supports_read())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex},
+        thread::{self, ThreadId},
+    };
+
+    use super::*;
+    use crate::geometry::Vector3;
+
+    #[derive(Default)]
+    struct Record {
+        open_thread: Option<ThreadId>,
+        positions: Vec<Vector3>,
+        labels: Vec<String>,
+        phase_offsets: Vec<f64>,
+        sound_speed: f64,
+        sent: Vec<u8>,
+    }
+
+    struct RecordLink {
+        record: Arc<Mutex<Record>>,
+        is_open: bool,
+    }
+
+    impl Link for RecordLink {
+        fn open<T: Transducer>(&mut self, geometry: &Geometry<T>) -> Result<()> {
+            let mut record = self.record.lock().unwrap();
+            record.open_thread = Some(thread::current().id());
+            record.positions = geometry.transducers().map(|tr| *tr.position()).collect();
+            record.labels = (0..geometry.num_devices())
+                .map(|i| geometry.device_label(i).to_owned())
+                .collect();
+            record.phase_offsets = (0..geometry.num_devices())
+                .map(|i| geometry.device_phase_offset(i))
+                .collect();
+            record.sound_speed = geometry.sound_speed();
+            self.is_open = true;
+            Ok(())
+        }
+
+        fn close(&mut self) -> Result<()> {
+            self.is_open = false;
+            Ok(())
+        }
+
+        fn send(&mut self, tx: &TxDatagram) -> Result<bool> {
+            if tx.header().msg_id == 0xFF {
+                panic!("broken link");
+            }
+            self.record.lock().unwrap().sent = tx.data()[..tx.size()].to_vec();
+            Ok(true)
+        }
+
+        fn receive(&mut self, rx: &mut RxDatagram) -> Result<bool> {
+            rx.messages_mut()
+                .iter_mut()
+                .enumerate()
+                .for_each(|(i, msg)| msg.msg_id = i as u8);
+            Ok(true)
+        }
+
+        fn is_open(&self) -> bool {
+            self.is_open
+        }
+    }
+
+    fn blocking_link() -> (BlockingLink<RecordLink>, Arc<Mutex<Record>>) {
+        let record = Arc::new(Mutex::new(Record::default()));
+        let link = BlockingLink::new(RecordLink {
+            record: record.clone(),
+            is_open: false,
+        });
+        (link, record)
+    }
+
+    fn block_on<F: Future>(f: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(f)
+    }
+
+    #[test]
+    fn open_on_blocking_thread() {
+        let mut geometry = GeometryBuilder::new().sound_speed(343.0).build();
+        geometry.add_device(Vector3::zeros(), Vector3::zeros());
+        geometry.add_device(Vector3::new(10., 20., 30.), Vector3::new(0.3, 1.2, -0.5));
+        geometry.set_device_label(1, "right".to_owned());
+        geometry.set_device_phase_offset(1, 0.25);
+
+        let (mut link, record) = blocking_link();
+        block_on(link.open(&geometry)).unwrap();
+        assert!(link.is_open());
+
+        let record = record.lock().unwrap();
+        assert_ne!(record.open_thread, Some(thread::current().id()));
+        assert_eq!(record.positions.len(), geometry.num_transducers());
+        record
+            .positions
+            .iter()
+            .zip(geometry.transducers())
+            .for_each(|(p, tr)| assert!((p - tr.position()).norm() < 1e-9));
+        assert_eq!(record.labels, vec![geometry.device_label(0), "right"]);
+        assert_eq!(record.phase_offsets, vec![0.0, 0.25]);
+        assert_eq!(record.sound_speed, 343.0);
+    }
+
+    #[test]
+    fn send_and_receive() {
+        let mut geometry = GeometryBuilder::new().legacy_mode().build();
+        geometry.add_device(Vector3::zeros(), Vector3::zeros());
+        geometry.add_device(Vector3::zeros(), Vector3::zeros());
+
+        let (mut link, record) = blocking_link();
+        block_on(async {
+            link.open(&geometry).await.unwrap();
+
+            let mut tx = TxDatagram::new(2);
+            tx.header_mut().msg_id = 0x10;
+            tx.num_bodies = 0;
+            assert!(link.send(&tx).await.unwrap());
+            assert_eq!(record.lock().unwrap().sent, tx.data()[..tx.size()]);
+
+            let mut rx = RxDatagram::new(2);
+            assert!(link.receive(&mut rx).await.unwrap());
+            assert_eq!(rx.messages()[1].msg_id, 1);
+
+            link.close().await.unwrap();
+        });
+        assert!(!link.is_open());
+        assert!(link.into_inner().is_some());
+    }
+
+    #[test]
+    fn link_lost_by_panic() {
+        let (mut link, _) = blocking_link();
+        block_on(async {
+            let mut tx = TxDatagram::new(1);
+            tx.header_mut().msg_id = 0xFF;
+            assert!(link.send(&tx).await.is_err());
+
+            assert!(matches!(
+                link.close()
+                    .await
+                    .unwrap_err()
+                    .downcast_ref::<AUTDInternalError>(),
+                Some(AUTDInternalError::LinkClosed)
+            ));
+        });
+        assert!(!link.is_open());
+        assert!(link.into_inner().is_none());
+    }
+}
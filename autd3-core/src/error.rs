@@ -25,4 +25,14 @@ pub enum AUTDInternalError {
     TransducerNumberNotCorrect { a: usize },
     #[error("Maximum cycle is {} , but {0} is specified", MAX_CYCLE)]
     CycleOutOfRange(u16),
+    #[error("Smoothing window ({0}) must not exceed the modulation buffer size ({1})")]
+    SmoothWindowOutOfRange(usize, usize),
+    #[error("Frequency response at {0:.1} Hz must be positive, but got {1}")]
+    InvalidFreqResponse(f64, f64),
+    #[error("Spacing between the control points ({0:.2} mm) exceeds the allowed jump ({1:.2} mm)")]
+    STMJumpTooLarge(f64, f64),
+    #[error("At least one waypoint is required")]
+    EmptyWaypoints,
+    #[error("Normalized duty ({0}) must be in [0, 1]")]
+    NormalizedDutyOutOfRange(f64),
 }
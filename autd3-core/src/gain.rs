@@ -16,16 +16,28 @@ use std::marker::PhantomData;
 use autd3_driver::{Drive, TxDatagram};
 
 use crate::{
-    geometry::{Geometry, Transducer},
-    interface::DatagramBody,
+    geometry::{Geometry, Transducer, TransducerKind},
+    interface::{DatagramBody, Empty, Filled, Sendable},
 };
 use anyhow::Result;
 
+/// Mapping from the amplitude of [Drive] to the duty ratio
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DutyMap {
+    /// The amplitude is proportional to the emitted sound pressure
+    AmplitudeLinear,
+    /// The amplitude is proportional to the emitted sound intensity, i.e., the square of the pressure
+    IntensityLinear,
+}
+
 pub struct GainProps<T: Transducer> {
     pub built: bool,
     pub phase_sent: bool,
     pub duty_sent: bool,
     pub drives: Vec<Drive>,
+    pub duty_map: DutyMap,
+    /// Scale of the amplitudes applied only to the packed data, e.g., by the power limit of the controller
+    pub amp_scale: f64,
     _t: PhantomData<T>,
 }
 
@@ -36,6 +48,8 @@ impl<T: Transducer> GainProps<T> {
             phase_sent: false,
             duty_sent: false,
             drives: vec![],
+            duty_map: DutyMap::AmplitudeLinear,
+            amp_scale: 1.0,
             _t: PhantomData,
         }
     }
@@ -52,13 +66,80 @@ impl<T: Transducer> GainProps<T> {
             .collect();
     }
 
+    pub fn apply_duty_map(&mut self) {
+        if self.duty_map == DutyMap::IntensityLinear {
+            self.drives.iter_mut().for_each(|d| d.amp = d.amp.sqrt());
+        }
+    }
+
+    /// Sum of the squared amplitudes, as a proxy of the total emitted power
+    pub fn total_power(&self) -> f64 {
+        self.drives.iter().map(|d| d.amp * d.amp).sum()
+    }
+
     pub fn pack_head(&mut self, tx: &mut TxDatagram) {
         T::pack_head(tx);
     }
 
-    pub fn pack_body(&mut self, tx: &mut TxDatagram) -> Result<()> {
+    pub fn pack_body(&mut self, geometry: &Geometry<T>, tx: &mut TxDatagram) -> Result<()> {
+        if geometry.has_device_phase_offset() || self.amp_scale != 1.0 {
+            let mut drives = self.drives.clone();
+            drives.iter_mut().for_each(|d| d.amp *= self.amp_scale);
+            geometry.apply_device_phase_offsets(&mut drives);
+            return T::pack_body(&mut self.phase_sent, &mut self.duty_sent, &drives, tx);
+        }
         T::pack_body(&mut self.phase_sent, &mut self.duty_sent, &self.drives, tx)
     }
+
+    /// Save the amplitudes as a PNG image for debugging
+    ///
+    /// Each transducer is drawn at its position in the grid of the device, and the devices are placed side by side in order of the device id.
+    /// The amplitude is mapped from blue (0) to red (1), and the missing transducers are left black.
+    ///
+    /// # Arguments
+    ///
+    /// * `geometry` - Geometry
+    /// * `path` - Path of the image file
+    ///
+    #[cfg(feature = "image")]
+    pub fn to_heatmap_png<P: AsRef<std::path::Path>>(
+        &self,
+        geometry: &Geometry<T>,
+        path: P,
+    ) -> Result<()> {
+        use autd3_driver::{is_missing_transducer, NUM_TRANS_IN_UNIT, NUM_TRANS_X, NUM_TRANS_Y};
+
+        const CELL_SIZE: u32 = 8;
+
+        let width = (geometry.num_devices() * NUM_TRANS_X) as u32 * CELL_SIZE;
+        let height = NUM_TRANS_Y as u32 * CELL_SIZE;
+        let mut img = image::RgbImage::new(width, height);
+
+        (0..geometry.num_devices()).for_each(|dev| {
+            itertools::iproduct!((0..NUM_TRANS_Y), (0..NUM_TRANS_X))
+                .filter(|&(y, x)| !is_missing_transducer(x, y))
+                .enumerate()
+                .for_each(|(i, (y, x))| {
+                    let amp = self
+                        .drives
+                        .get(dev * NUM_TRANS_IN_UNIT + i)
+                        .map_or(0.0, |d| d.amp.clamp(0.0, 1.0));
+                    let color = image::Rgb([
+                        (amp * 255.0) as u8,
+                        ((1.0 - (2.0 * amp - 1.0).abs()) * 255.0) as u8,
+                        ((1.0 - amp) * 255.0) as u8,
+                    ]);
+                    let px = (dev * NUM_TRANS_X + x) as u32 * CELL_SIZE;
+                    let py = y as u32 * CELL_SIZE;
+                    (0..CELL_SIZE)
+                        .flat_map(|dy| (0..CELL_SIZE).map(move |dx| (dx, dy)))
+                        .for_each(|(dx, dy)| img.put_pixel(px + dx, py + dy, color));
+                });
+        });
+
+        img.save_with_format(path, image::ImageFormat::Png)?;
+        Ok(())
+    }
 }
 
 impl<T: Transducer> Default for GainProps<T> {
@@ -79,4 +160,152 @@ pub trait Gain<T: Transducer>: IGain<T> + DatagramBody<T> {
     fn drives(&self) -> &[Drive];
     fn take_drives(self) -> Vec<Drive>;
     fn built(&self) -> bool;
+    /// Kind of the transducers the gain is calculated for
+    ///
+    /// This is `T::KIND` for ordinary gains.
+    /// Gains created across a dynamic boundary, e.g., FFI, should return the kind they were actually created for,
+    /// so that the controller can reject them if it does not match the geometry.
+    fn transducer_kind(&self) -> TransducerKind {
+        T::KIND
+    }
+}
+
+/// Options of the gains holding [GainProps], which are implemented by `#[derive(Gain)]`
+pub trait GainOptions<T: Transducer>: Gain<T> + Sized {
+    fn props_mut(&mut self) -> &mut GainProps<T>;
+
+    /// Set the mapping from the amplitude to the duty ratio
+    fn with_duty_map(mut self, duty_map: DutyMap) -> Self {
+        let props = self.props_mut();
+        props.duty_map = duty_map;
+        props.built = false;
+        self
+    }
+}
+
+impl<'a, T: Transducer> DatagramBody<T> for Box<dyn 'a + Gain<T>> {
+    fn init(&mut self) -> Result<()> {
+        self.as_mut().init()
+    }
+
+    fn pack(&mut self, geometry: &Geometry<T>, tx: &mut TxDatagram) -> Result<()> {
+        self.as_mut().pack(geometry, tx)
+    }
+
+    fn is_finished(&self) -> bool {
+        self.as_ref().is_finished()
+    }
+
+    fn total_power(&self) -> Option<f64> {
+        self.as_ref().total_power()
+    }
+
+    fn set_amplitude_scale(&mut self, scale: f64) -> bool {
+        self.as_mut().set_amplitude_scale(scale)
+    }
+
+    fn target_kind(&self) -> Option<TransducerKind> {
+        Some(Gain::transducer_kind(self.as_ref()))
+    }
+}
+
+impl<'a, T: Transducer> Sendable<T> for Box<dyn 'a + Gain<T>> {
+    type H = Empty;
+    type B = Filled;
+
+    fn init(&mut self) -> Result<()> {
+        DatagramBody::<T>::init(self)
+    }
+
+    fn pack(&mut self, _msg_id: u8, geometry: &Geometry<T>, tx: &mut TxDatagram) -> Result<()> {
+        DatagramBody::<T>::pack(self, geometry, tx)
+    }
+
+    fn is_finished(&self) -> bool {
+        DatagramBody::<T>::is_finished(self)
+    }
+
+    fn total_power(&self) -> Option<f64> {
+        DatagramBody::<T>::total_power(self)
+    }
+
+    fn set_amplitude_scale(&mut self, scale: f64) -> bool {
+        DatagramBody::<T>::set_amplitude_scale(self, scale)
+    }
+
+    fn target_kind(&self) -> Option<TransducerKind> {
+        DatagramBody::<T>::target_kind(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::{GeometryBuilder, NormalTransducer, Vector3};
+
+    fn duty_of_half_amp(duty_map: DutyMap) -> u16 {
+        let mut geometry = GeometryBuilder::new().build();
+        geometry.add_device(Vector3::zeros(), Vector3::zeros());
+
+        let mut props = GainProps::<NormalTransducer>::new();
+        props.duty_map = duty_map;
+        props.init(&geometry);
+        props.drives.iter_mut().for_each(|d| d.amp = 0.5);
+        props.apply_duty_map();
+
+        // phase and then duty
+        let mut tx = TxDatagram::new(1);
+        props.pack_body(&geometry, &mut tx).unwrap();
+        props.pack_body(&geometry, &mut tx).unwrap();
+        tx.body()[0].data[0]
+    }
+
+    #[test]
+    fn duty_map() {
+        let amplitude = duty_of_half_amp(DutyMap::AmplitudeLinear);
+        let intensity = duty_of_half_amp(DutyMap::IntensityLinear);
+
+        // the duty ratio is asin(amp) / π, i.e., 1/6 for the amplitude 0.5 and 1/4 for the intensity 0.5
+        assert_ne!(amplitude, intensity);
+        assert_eq!(amplitude, (4096. / 6.0f64).round() as u16);
+        assert_eq!(intensity, 1024);
+        assert!((intensity as f64 / amplitude as f64 - 1.5).abs() < 1e-2);
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn heatmap_png() {
+        use autd3_driver::{is_missing_transducer, NUM_TRANS_IN_UNIT, NUM_TRANS_X, NUM_TRANS_Y};
+
+        let mut geometry = GeometryBuilder::new().build();
+        geometry.add_device(Vector3::zeros(), Vector3::zeros());
+        geometry.add_device(Vector3::new(192., 0., 0.), Vector3::zeros());
+
+        let mut props = GainProps::<NormalTransducer>::new();
+        props.init(&geometry);
+        props.drives[..NUM_TRANS_IN_UNIT]
+            .iter_mut()
+            .for_each(|d| d.amp = 1.0);
+
+        let path = std::env::temp_dir().join("autd3_heatmap_test.png");
+        props.to_heatmap_png(&geometry, &path).unwrap();
+        let img = image::open(&path).unwrap().to_rgb8();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(img.width(), 2 * NUM_TRANS_X as u32 * 8);
+        assert_eq!(img.height(), NUM_TRANS_Y as u32 * 8);
+        assert_eq!(img.get_pixel(0, 0), &image::Rgb([255, 0, 0]));
+        assert_eq!(
+            img.get_pixel(NUM_TRANS_X as u32 * 8 + 7, 7),
+            &image::Rgb([0, 0, 255])
+        );
+        let (x, y) = (0..NUM_TRANS_Y)
+            .flat_map(|y| (0..NUM_TRANS_X).map(move |x| (x, y)))
+            .find(|&(x, y)| is_missing_transducer(x, y))
+            .unwrap();
+        assert_eq!(
+            img.get_pixel(x as u32 * 8 + 4, y as u32 * 8 + 4),
+            &image::Rgb([0, 0, 0])
+        );
+    }
 }
@@ -20,7 +20,10 @@ use super::{Matrix3, Matrix4, Quaternion, Transducer, UnitQuaternion, Vector3, V
 pub struct Device<T: Transducer> {
     transducers: Vec<T>,
     origin: Vector3,
+    z_direction: Vector3,
     trans_inv: Matrix3,
+    phase_offset: f64,
+    label: String,
 }
 
 impl<T: Transducer> Device<T> {
@@ -41,10 +44,61 @@ impl<T: Transducer> Device<T> {
         &mut self.transducers
     }
 
+    /// Normal of the device, i.e., the direction in which the transducers emit
+    pub fn z_direction(&self) -> &Vector3 {
+        &self.z_direction
+    }
+
+    /// Phase offset added to all transducers of the device on sending, normalized by 2π
+    pub fn phase_offset(&self) -> f64 {
+        self.phase_offset
+    }
+
+    pub fn set_phase_offset(&mut self, offset: f64) {
+        self.phase_offset = offset;
+    }
+
+    /// Label of the device for logging. Default is the index of the device.
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn set_label(&mut self, label: String) {
+        self.label = label;
+    }
+
     pub fn center(&self) -> Vector3 {
         let sum: Vector3 = self.transducers().iter().map(|t| t.position()).sum();
         sum / self.transducers.len() as f64
     }
+
+    /// Copy of the device placed at the same position as the `id`-th device
+    ///
+    /// The settings of the transducers, e.g., the cycles and the modulation delays, are not copied.
+    pub(crate) fn copy_as(&self, id: usize) -> Self {
+        let transducers = self
+            .transducers
+            .iter()
+            .zip(id * NUM_TRANS_IN_UNIT..)
+            .map(|(tr, i)| {
+                T::new(
+                    i,
+                    *tr.position(),
+                    *tr.x_direction(),
+                    *tr.y_direction(),
+                    *tr.z_direction(),
+                )
+            })
+            .collect();
+        Self {
+            transducers,
+            origin: self.origin,
+            z_direction: self.z_direction,
+            trans_inv: self.trans_inv,
+            phase_offset: self.phase_offset,
+            label: self.label.clone(),
+        }
+    }
 }
 
 impl<T: Transducer> Device<T> {
@@ -84,7 +138,10 @@ impl<T: Transducer> Device<T> {
         Self {
             transducers,
             origin,
+            z_direction,
             trans_inv,
+            phase_offset: 0.0,
+            label: id.to_string(),
         }
     }
 }
@@ -15,7 +15,7 @@ use std::f64::consts::PI;
 
 use autd3_driver::Drive;
 
-use super::{Transducer, Vector3};
+use super::{Transducer, TransducerKind, Vector3};
 
 pub struct LegacyTransducer {
     id: usize,
@@ -27,6 +27,8 @@ pub struct LegacyTransducer {
 }
 
 impl Transducer for LegacyTransducer {
+    const KIND: TransducerKind = TransducerKind::Legacy;
+
     fn new(
         id: usize,
         pos: Vector3,
@@ -73,6 +75,10 @@ impl Transducer for LegacyTransducer {
         4096
     }
 
+    fn duty_scale(&self) -> f64 {
+        510.0
+    }
+
     fn frequency(&self) -> f64 {
         40e3
     }
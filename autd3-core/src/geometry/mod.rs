@@ -25,7 +25,15 @@ pub type UnitQuaternion = nalgebra::UnitQuaternion<f64>;
 pub type Matrix3 = nalgebra::Matrix3<f64>;
 pub type Matrix4 = nalgebra::Matrix4<f64>;
 
-use autd3_driver::NUM_TRANS_IN_UNIT;
+use std::{
+    ops::Range,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        OnceLock,
+    },
+};
+
+use autd3_driver::{Drive, DEVICE_HEIGHT, DEVICE_WIDTH, NUM_TRANS_IN_UNIT};
 pub use builder::*;
 pub use device::*;
 pub use legacy_transducer::*;
@@ -33,22 +41,41 @@ pub use normal_phase_transducer::*;
 pub use normal_transducer::*;
 pub use transducer::*;
 
-#[derive(Default)]
+static NEXT_VERSION: AtomicUsize = AtomicUsize::new(0);
+
+fn next_version() -> usize {
+    NEXT_VERSION.fetch_add(1, Ordering::Relaxed)
+}
+
 pub struct Geometry<T: Transducer> {
     devices: Vec<Device<T>>,
+    positions: OnceLock<Vec<Vector3>>,
+    version: usize,
     pub attenuation: f64,
     pub sound_speed: f64,
 }
 
+impl<T: Transducer> Default for Geometry<T> {
+    fn default() -> Self {
+        Self::new(0.0, 0.0)
+    }
+}
+
 impl<T: Transducer> Geometry<T> {
     fn new(attenuation: f64, sound_speed: f64) -> Geometry<T> {
         Geometry {
             devices: vec![],
+            positions: OnceLock::new(),
+            version: next_version(),
             attenuation,
             sound_speed,
         }
     }
 
+    pub fn transducer_kind(&self) -> TransducerKind {
+        T::KIND
+    }
+
     pub fn num_devices(&self) -> usize {
         self.devices.len()
     }
@@ -62,6 +89,7 @@ impl<T: Transducer> Geometry<T> {
     }
 
     pub fn devices_mut(&mut self) -> &mut [Device<T>] {
+        self.invalidate();
         &mut self.devices
     }
 
@@ -70,11 +98,40 @@ impl<T: Transducer> Geometry<T> {
     }
 
     pub fn transducers_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.invalidate();
         self.devices
             .iter_mut()
             .flat_map(|dev| dev.transducers_mut())
     }
 
+    /// Index of the device which the transducer of the global index `transducer_id` belongs to
+    pub fn device_of(&self, transducer_id: usize) -> usize {
+        transducer_id / NUM_TRANS_IN_UNIT
+    }
+
+    /// Index of the transducer of the global index `transducer_id` within its device
+    pub fn local_index(&self, transducer_id: usize) -> usize {
+        transducer_id % NUM_TRANS_IN_UNIT
+    }
+
+    /// Transducers of the `dev`-th device, in the same order as the drives of the device are sent
+    pub fn transducers_of_device(&self, dev: usize) -> impl Iterator<Item = &T> {
+        self.devices[dev].transducers().iter()
+    }
+
+    /// Positions of all transducers
+    ///
+    /// The positions are cached on the first call, and the cache is discarded whenever the devices or the transducers are borrowed mutably.
+    pub fn positions(&self) -> &[Vector3] {
+        self.positions
+            .get_or_init(|| self.transducers().map(|tr| *tr.position()).collect())
+    }
+
+    fn invalidate(&mut self) {
+        self.version = next_version();
+        self.positions = OnceLock::new();
+    }
+
     pub fn center(&self) -> Vector3 {
         let sum: Vector3 = self.devices().iter().map(|d| d.center()).sum();
         sum / self.devices.len() as f64
@@ -84,9 +141,74 @@ impl<T: Transducer> Geometry<T> {
         self.sound_speed
     }
 
+    /// Value renewed whenever the devices or the transducers may be modified, e.g., to invalidate values cached from the geometry
+    ///
+    /// The values are drawn from a global counter, so that different geometries, e.g., a geometry and its [sub_geometry](#method.sub_geometry), never share a version.
+    /// Note that the changes of the public fields [attenuation](#structfield.attenuation) and [sound_speed](#structfield.sound_speed) are not counted.
+    pub fn version(&self) -> usize {
+        self.version
+    }
+
+    /// Set the phase offset of a device to compensate for, e.g., the skew between devices
+    ///
+    /// The offset is normalized by 2π, and added to the phases of all transducers of the device when a gain is sent, or when a gain is added to [GainSTM](crate::stm::GainSTM).
+    /// Note that the skew cannot be read from the devices directly, so the offset should be obtained by measuring the field, e.g., with `autd3::calibration::calibrate_device_phases`.
+    ///
+    /// # Arguments
+    ///
+    /// * `device_idx` - Index of the device
+    /// * `offset` - Phase offset
+    ///
+    pub fn set_device_phase_offset(&mut self, device_idx: usize, offset: f64) {
+        self.devices[device_idx].set_phase_offset(offset);
+    }
+
+    pub fn device_phase_offset(&self, device_idx: usize) -> f64 {
+        self.devices[device_idx].phase_offset()
+    }
+
+    /// Set the label of a device, e.g., "left wall", used to identify the device in logs instead of the index
+    pub fn set_device_label(&mut self, device_idx: usize, label: String) {
+        self.devices[device_idx].set_label(label);
+    }
+
+    /// Label of a device. Default is the index of the device.
+    pub fn device_label(&self, device_idx: usize) -> &str {
+        self.devices[device_idx].label()
+    }
+
+    /// Whether any device has a non-zero phase offset
+    pub fn has_device_phase_offset(&self) -> bool {
+        self.devices.iter().any(|dev| dev.phase_offset() != 0.0)
+    }
+
+    /// Add the phase offset of each device to the drives
+    pub fn apply_device_phase_offsets(&self, drives: &mut [Drive]) {
+        drives
+            .chunks_mut(NUM_TRANS_IN_UNIT)
+            .zip(self.devices.iter())
+            .for_each(|(drives, dev)| {
+                drives
+                    .iter_mut()
+                    .for_each(|d| d.phase += dev.phase_offset())
+            });
+    }
+
     pub fn set_sound_speed(&mut self, sound_speed: f64) {
         self.sound_speed = sound_speed;
     }
+
+    /// Geometry consisting only of the devices in `range`, e.g., to open a link connected to a part of the devices
+    ///
+    /// The devices are renumbered from 0. The phase offsets and labels of the devices are kept, but the settings of the transducers are not.
+    pub fn sub_geometry(&self, range: Range<usize>) -> Geometry<T> {
+        let mut geometry = Geometry::new(self.attenuation, self.sound_speed);
+        self.devices[range]
+            .iter()
+            .enumerate()
+            .for_each(|(id, dev)| geometry.devices.push(dev.copy_as(id)));
+        geometry
+    }
 }
 
 impl Geometry<LegacyTransducer> {
@@ -123,10 +245,55 @@ impl<T: Transducer> Geometry<T> {
     /// geometry.add_device(Vector3::new(192., 0., 0.), Vector3::new(-PI, 0., 0.));
     /// ```
     pub fn add_device(&mut self, position: Vector3, euler_angles: Vector3) {
-        let q = UnitQuaternion::from_axis_angle(&Vector3::z_axis(), euler_angles.x)
+        self.add_device_quaternion(position, Self::zyz_rotation(euler_angles))
+    }
+
+    /// Add `rows` x `cols` devices arranged in a grid on a plane.
+    ///
+    /// The devices are added in row-major order, i.e., the device in the `r`-th row and the `c`-th column is the `(r * cols + c)`-th device added by this method.
+    /// The origin of that device is placed at `position` + (`c` * ([DEVICE_WIDTH] + `gap`), `r` * ([DEVICE_HEIGHT] + `gap`), 0) in the local coordinate of the grid rotated by `euler_angles`, and all devices have the same rotation.
+    ///
+    /// # Arguments
+    ///
+    /// * `rows` - Number of rows
+    /// * `cols` - Number of columns
+    /// * `gap` - Gap between adjacent devices in mm. Set 0 for the devices in contact.
+    /// * `position` - Global position of the origin of the first device
+    /// * `euler_angles` - ZYZ Euler angles of the grid
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use autd3_core::geometry::{Vector3, GeometryBuilder};
+    ///
+    /// let mut geometry = GeometryBuilder::new().build();
+    ///
+    /// // 3x3 wall
+    /// geometry.add_device_grid(3, 3, 0., Vector3::zeros(), Vector3::zeros());
+    /// ```
+    pub fn add_device_grid(
+        &mut self,
+        rows: usize,
+        cols: usize,
+        gap: f64,
+        position: Vector3,
+        euler_angles: Vector3,
+    ) {
+        let rotation = Self::zyz_rotation(euler_angles);
+        itertools::iproduct!(0..rows, 0..cols).for_each(|(r, c)| {
+            let offset = Vector3::new(
+                c as f64 * (DEVICE_WIDTH + gap),
+                r as f64 * (DEVICE_HEIGHT + gap),
+                0.,
+            );
+            self.add_device_quaternion(position + rotation * offset, rotation);
+        });
+    }
+
+    fn zyz_rotation(euler_angles: Vector3) -> UnitQuaternion {
+        UnitQuaternion::from_axis_angle(&Vector3::z_axis(), euler_angles.x)
             * UnitQuaternion::from_axis_angle(&Vector3::y_axis(), euler_angles.y)
-            * UnitQuaternion::from_axis_angle(&Vector3::z_axis(), euler_angles.z);
-        self.add_device_quaternion(position, q)
+            * UnitQuaternion::from_axis_angle(&Vector3::z_axis(), euler_angles.z)
     }
 
     /// Add device to the geometry.
@@ -140,7 +307,159 @@ impl<T: Transducer> Geometry<T> {
     /// * `rot` - Rotation quaternion.
     ///
     pub fn add_device_quaternion(&mut self, position: Vector3, rotation: UnitQuaternion) {
+        self.invalidate();
         let id = self.devices.len();
         self.devices.push(Device::<T>::new(id, position, rotation));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use super::*;
+
+    fn assert_near(a: Vector3, b: Vector3) {
+        assert!((a - b).norm() < 1e-9, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn device_grid_corners() {
+        let mut geometry = GeometryBuilder::new().legacy_mode().build();
+        geometry.add_device_grid(3, 3, 0., Vector3::new(10., 20., 30.), Vector3::zeros());
+
+        assert_eq!(geometry.num_devices(), 9);
+        assert_near(
+            *geometry.transducers().next().unwrap().position(),
+            Vector3::new(10., 20., 30.),
+        );
+        assert_near(
+            *geometry.transducers().last().unwrap().position(),
+            Vector3::new(566.72, 454.88, 30.),
+        );
+    }
+
+    #[test]
+    fn device_grid_corners_rotated() {
+        let mut geometry = GeometryBuilder::new().legacy_mode().build();
+        geometry.add_device_grid(
+            3,
+            3,
+            0.,
+            Vector3::new(10., 20., 30.),
+            Vector3::new(PI / 2., 0., 0.),
+        );
+
+        assert_near(
+            *geometry.transducers().next().unwrap().position(),
+            Vector3::new(10., 20., 30.),
+        );
+        assert_near(
+            *geometry.transducers().last().unwrap().position(),
+            Vector3::new(-424.88, 576.72, 30.),
+        );
+    }
+
+    #[test]
+    fn positions_follow_transducers() {
+        let mut geometry = GeometryBuilder::new().legacy_mode().build();
+        geometry.add_device(Vector3::zeros(), Vector3::zeros());
+        assert_eq!(geometry.positions().len(), NUM_TRANS_IN_UNIT);
+
+        geometry.add_device(Vector3::new(0., 0., 100.), Vector3::zeros());
+        assert_eq!(geometry.positions().len(), 2 * NUM_TRANS_IN_UNIT);
+        assert_near(
+            geometry.positions()[NUM_TRANS_IN_UNIT],
+            Vector3::new(0., 0., 100.),
+        );
+
+        // replace the first transducer with one moved by 1 mm along x
+        let offset = Vector3::new(1., 0., 0.);
+        let tr = geometry.transducers_mut().next().unwrap();
+        *tr = LegacyTransducer::new(
+            tr.id(),
+            tr.position() + offset,
+            *tr.x_direction(),
+            *tr.y_direction(),
+            *tr.z_direction(),
+        );
+        assert_near(geometry.positions()[0], offset);
+        geometry
+            .transducers()
+            .zip(geometry.positions())
+            .for_each(|(tr, &pos)| assert_near(*tr.position(), pos));
+    }
+
+    #[test]
+    fn device_phase_offset_shifts_device_uniformly() {
+        let mut geometry = GeometryBuilder::new().legacy_mode().build();
+        (0..3).for_each(|i| {
+            geometry.add_device(Vector3::new(192. * i as f64, 0., 0.), Vector3::zeros());
+        });
+        assert!(!geometry.has_device_phase_offset());
+        geometry.set_device_phase_offset(1, 0.25);
+        assert!(geometry.has_device_phase_offset());
+        assert_eq!(geometry.device_phase_offset(1), 0.25);
+
+        let original = geometry
+            .transducers()
+            .map(|tr| Drive {
+                phase: tr.id() as f64 / geometry.num_transducers() as f64,
+                amp: 1.0,
+                cycle: tr.cycle(),
+            })
+            .collect::<Vec<_>>();
+        let mut drives = original.clone();
+        geometry.apply_device_phase_offsets(&mut drives);
+
+        drives
+            .iter()
+            .zip(original.iter())
+            .enumerate()
+            .for_each(|(i, (d, o))| {
+                let shift = if geometry.device_of(i) == 1 {
+                    0.25
+                } else {
+                    0.0
+                };
+                assert!((d.phase - o.phase - shift).abs() < 1e-12);
+                assert_eq!(d.amp, o.amp);
+            });
+    }
+
+    #[test]
+    fn transducer_device_indices() {
+        let mut geometry = GeometryBuilder::new().legacy_mode().build();
+        geometry.add_device(Vector3::zeros(), Vector3::zeros());
+        geometry.add_device(Vector3::new(0., 0., 100.), Vector3::new(0., PI, 0.));
+        geometry.add_device(Vector3::new(192., 0., 0.), Vector3::zeros());
+
+        // the drives are sent in chunks of NUM_TRANS_IN_UNIT per device in the order of the transducers
+        let ids = geometry.transducers().map(|tr| tr.id()).collect::<Vec<_>>();
+        ids.chunks(NUM_TRANS_IN_UNIT)
+            .enumerate()
+            .for_each(|(dev, chunk)| {
+                chunk.iter().enumerate().for_each(|(local, &id)| {
+                    assert_eq!(geometry.device_of(id), dev);
+                    assert_eq!(geometry.local_index(id), local);
+                });
+
+                assert_eq!(
+                    geometry.transducers_of_device(dev).count(),
+                    NUM_TRANS_IN_UNIT
+                );
+                geometry
+                    .transducers_of_device(dev)
+                    .zip(chunk.iter())
+                    .for_each(|(tr, &id)| assert_eq!(tr.id(), id));
+            });
+
+        let tr = geometry.transducers().nth(NUM_TRANS_IN_UNIT + 10).unwrap();
+        let same = geometry
+            .transducers_of_device(geometry.device_of(tr.id()))
+            .nth(geometry.local_index(tr.id()))
+            .unwrap();
+        assert_near(*same.position(), *tr.position());
+        assert_eq!(geometry.device_of(tr.id()), 1);
+    }
+}
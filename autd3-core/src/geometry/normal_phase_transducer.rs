@@ -22,7 +22,7 @@ use crate::{
     interface::{DatagramBody, Empty, Filled, Sendable},
 };
 
-use super::{Geometry, Transducer, Vector3};
+use super::{Geometry, Transducer, TransducerKind, Vector3};
 
 pub struct NormalPhaseTransducer {
     id: usize,
@@ -35,6 +35,8 @@ pub struct NormalPhaseTransducer {
 }
 
 impl Transducer for NormalPhaseTransducer {
+    const KIND: TransducerKind = TransducerKind::NormalPhase;
+
     fn new(
         id: usize,
         pos: Vector3,
@@ -81,6 +83,10 @@ impl Transducer for NormalPhaseTransducer {
         self.cycle
     }
 
+    fn duty_scale(&self) -> f64 {
+        self.cycle as f64
+    }
+
     fn mod_delay(&self) -> u16 {
         self.mod_delay
     }
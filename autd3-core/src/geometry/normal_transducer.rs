@@ -19,7 +19,7 @@ use autd3_driver::{Drive, FPGA_CLK_FREQ, MAX_CYCLE};
 
 use crate::error::AUTDInternalError;
 
-use super::{Transducer, Vector3};
+use super::{Transducer, TransducerKind, Vector3};
 
 pub struct NormalTransducer {
     id: usize,
@@ -32,6 +32,8 @@ pub struct NormalTransducer {
 }
 
 impl Transducer for NormalTransducer {
+    const KIND: TransducerKind = TransducerKind::Normal;
+
     fn new(
         id: usize,
         pos: Vector3,
@@ -78,6 +80,10 @@ impl Transducer for NormalTransducer {
         self.cycle
     }
 
+    fn duty_scale(&self) -> f64 {
+        self.cycle as f64
+    }
+
     fn frequency(&self) -> f64 {
         FPGA_CLK_FREQ as f64 / self.cycle as f64
     }
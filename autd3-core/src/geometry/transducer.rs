@@ -17,7 +17,16 @@ use autd3_driver::{Drive, TxDatagram};
 
 use super::Vector3;
 
+/// Kind of transducers, i.e., the driving mode of the devices
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransducerKind {
+    Legacy,
+    Normal,
+    NormalPhase,
+}
+
 pub trait Transducer: Sized {
+    const KIND: TransducerKind;
     fn new(
         id: usize,
         pos: Vector3,
@@ -32,6 +41,8 @@ pub trait Transducer: Sized {
     fn y_direction(&self) -> &Vector3;
     fn z_direction(&self) -> &Vector3;
     fn cycle(&self) -> u16;
+    /// Duty value corresponding to the full period of PWM. Duty ratio of 1/2 is the maximum output.
+    fn duty_scale(&self) -> f64;
     fn frequency(&self) -> f64;
     fn mod_delay(&self) -> u16;
     fn set_mod_delay(&mut self, delay: u16);
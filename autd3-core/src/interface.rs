@@ -13,7 +13,7 @@
 
 use autd3_driver::TxDatagram;
 
-use crate::geometry::{Geometry, Transducer};
+use crate::geometry::{Geometry, Transducer, TransducerKind};
 use anyhow::Result;
 
 pub struct Empty;
@@ -25,6 +25,20 @@ pub trait Sendable<T: Transducer> {
     fn init(&mut self) -> Result<()>;
     fn pack(&mut self, msg_id: u8, geometry: &Geometry<T>, tx: &mut TxDatagram) -> Result<()>;
     fn is_finished(&self) -> bool;
+    /// Sum of the squared amplitudes of the transducers, if the data drives them
+    fn total_power(&self) -> Option<f64> {
+        None
+    }
+    /// Multiply the amplitudes of all transducers by `scale` when packing, leaving the data itself as is.
+    /// The scale replaces the previous one, so that 1 restores the original amplitudes.
+    /// Returns false if the data does not support scaling.
+    fn set_amplitude_scale(&mut self, _scale: f64) -> bool {
+        false
+    }
+    /// Kind of the transducers the data is made for, if the data depends on it
+    fn target_kind(&self) -> Option<TransducerKind> {
+        None
+    }
 }
 
 pub trait DatagramHeader {
@@ -37,6 +51,20 @@ pub trait DatagramBody<T: Transducer> {
     fn init(&mut self) -> Result<()>;
     fn pack(&mut self, geometry: &Geometry<T>, tx: &mut TxDatagram) -> Result<()>;
     fn is_finished(&self) -> bool;
+    /// Sum of the squared amplitudes of the transducers, if the data drives them
+    fn total_power(&self) -> Option<f64> {
+        None
+    }
+    /// Multiply the amplitudes of all transducers by `scale` when packing, leaving the data itself as is.
+    /// The scale replaces the previous one, so that 1 restores the original amplitudes.
+    /// Returns false if the data does not support scaling.
+    fn set_amplitude_scale(&mut self, _scale: f64) -> bool {
+        false
+    }
+    /// Kind of the transducers the data is made for, if the data depends on it
+    fn target_kind(&self) -> Option<TransducerKind> {
+        None
+    }
 }
 
 #[derive(Default)]
@@ -11,6 +11,8 @@
  *
  */
 
+#[cfg(feature = "async")]
+pub mod async_link;
 pub mod delay;
 pub mod error;
 pub mod gain;
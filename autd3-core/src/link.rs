@@ -23,4 +23,8 @@ pub trait Link: Send {
     fn send(&mut self, tx: &TxDatagram) -> Result<bool>;
     fn receive(&mut self, rx: &mut RxDatagram) -> Result<bool>;
     fn is_open(&self) -> bool;
+    /// Whether [Link::receive] returns the data from the devices. If not, the controller skips reading.
+    fn supports_read(&self) -> bool {
+        true
+    }
 }
@@ -11,15 +11,31 @@
  *
  */
 
+use std::f64::consts::PI;
+
 use anyhow::Result;
+use rustfft::{num_complex::Complex, FftPlanner};
+
+use crate::{error::AUTDInternalError, interface::DatagramHeader};
 
-use crate::interface::DatagramHeader;
+/// Transform of the duty ratios of the buffer, applied in order right after the buffer is calculated
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BufferTransform {
+    /// Multiply the amplitudes encoded in the buffer by the factor
+    Scale(f64),
+    /// Add the bias to the values of the buffer
+    Offset(u8),
+}
 
 pub struct ModProps {
     pub buffer: Vec<u8>,
     pub freq_div: u32,
     pub built: bool,
     pub sent: usize,
+    pub phase_offset: f64,
+    pub smooth_window: usize,
+    pub freq_response: Option<Box<dyn Fn(f64) -> f64 + Send + Sync>>,
+    pub transforms: Vec<BufferTransform>,
 }
 
 impl ModProps {
@@ -29,7 +45,94 @@ impl ModProps {
             freq_div: 40960,
             built: false,
             sent: 0,
+            phase_offset: 0.0,
+            smooth_window: 1,
+            freq_response: None,
+            transforms: vec![],
+        }
+    }
+
+    /// Apply [transforms](Self::transforms) to the buffer in order, clamping the result of each into the valid range.
+    ///
+    /// [BufferTransform::Scale] decodes each value into the amplitude, i.e., sin(value / 255 * π / 2), scales it, and encodes it back with asin.
+    pub fn apply_transforms(&mut self) {
+        self.transforms.iter().for_each(|t| {
+            self.buffer.iter_mut().for_each(|d| {
+                *d = match *t {
+                    BufferTransform::Scale(factor) => {
+                        let amp = (*d as f64 / 255.0 * PI / 2.0).sin() * factor;
+                        (amp.clamp(0.0, 1.0).asin() * 2.0 / PI * 255.0).round() as u8
+                    }
+                    BufferTransform::Offset(bias) => d.saturating_add(bias),
+                }
+            })
+        });
+    }
+
+    /// Divide each frequency component of the buffer in amplitude by `freq_response` at its frequency.
+    ///
+    /// The DC component is kept as is, and the resulting amplitude is clamped to the range from 0 to 1.
+    pub fn apply_freq_response(&mut self, sampling_freq: f64) -> Result<()> {
+        let len = self.buffer.len();
+        let curve = match &self.freq_response {
+            Some(curve) if len > 1 => curve,
+            _ => return Ok(()),
+        };
+
+        let mut spectrum = self
+            .buffer
+            .iter()
+            .map(|&d| Complex::new((d as f64 / 255.0 * PI / 2.0).sin(), 0.0))
+            .collect::<Vec<_>>();
+        let mut planner = FftPlanner::new();
+        planner.plan_fft_forward(len).process(&mut spectrum);
+        for (k, c) in spectrum.iter_mut().enumerate().skip(1) {
+            let freq = k.min(len - k) as f64 * sampling_freq / len as f64;
+            let response = curve(freq);
+            if response.is_nan() || response <= 0.0 {
+                return Err(AUTDInternalError::InvalidFreqResponse(freq, response).into());
+            }
+            *c /= response;
+        }
+        planner.plan_fft_inverse(len).process(&mut spectrum);
+
+        self.buffer
+            .iter_mut()
+            .zip(spectrum.iter())
+            .for_each(|(m, c)| {
+                let amp = c.re / len as f64;
+                *m = (amp.clamp(0.0, 1.0).asin() * 2.0 / PI * 255.0).round() as u8;
+            });
+
+        Ok(())
+    }
+
+    /// Apply the circular moving average of `smooth_window` samples to the buffer in amplitude.
+    pub fn apply_smooth(&mut self) -> Result<()> {
+        let window = self.smooth_window;
+        let len = self.buffer.len();
+        if window <= 1 || len == 0 {
+            return Ok(());
         }
+        if window > len {
+            return Err(AUTDInternalError::SmoothWindowOutOfRange(window, len).into());
+        }
+
+        let amps = self
+            .buffer
+            .iter()
+            .map(|&d| (d as f64 / 255.0 * PI / 2.0).sin())
+            .collect::<Vec<_>>();
+        let start = window / 2;
+        self.buffer.iter_mut().enumerate().for_each(|(i, m)| {
+            let amp = (0..window)
+                .map(|k| amps[(i + len + k - start) % len])
+                .sum::<f64>()
+                / window as f64;
+            *m = (amp.clamp(0.0, 1.0).asin() * 2.0 / PI * 255.0).round() as u8;
+        });
+
+        Ok(())
     }
 }
 
@@ -44,6 +147,209 @@ pub trait Modulation: DatagramHeader {
     fn build(&mut self) -> Result<()>;
     fn rebuild(&mut self) -> Result<()>;
     fn buffer(&self) -> &[u8];
+    /// Sampling frequency division of the modulation, independent of that of STM.
     fn sampling_frequency_division(&mut self) -> &mut u32;
     fn sampling_freq(&self) -> f64;
 }
+
+/// Options of the modulations holding [ModProps], which are implemented by `#[derive(Modulation)]`
+pub trait ModulationOptions: Modulation + Sized {
+    fn props_mut(&mut self) -> &mut ModProps;
+
+    /// Circularly rotate the buffer so that it starts at `fraction` of its length.
+    fn with_phase_offset(mut self, fraction: f64) -> Self {
+        let props = self.props_mut();
+        props.phase_offset = fraction;
+        props.built = false;
+        self
+    }
+    /// Smooth the buffer with the moving average of `window` samples, wrapping at the edges.
+    ///
+    /// The window must not exceed the length of the buffer, otherwise building fails.
+    fn smooth(mut self, window: usize) -> Self {
+        let props = self.props_mut();
+        props.smooth_window = window;
+        props.built = false;
+        self
+    }
+    /// Equalize the frequency response of the transducers, e.g., so that modulations at different frequencies are perceived with the same intensity.
+    ///
+    /// Each frequency component of the modulation in amplitude is divided by `curve` at its frequency in Hz, while the DC component is kept.
+    /// Thus, `curve` is the measured response relative to that of the reference, e.g., 1 at the frequency to be kept as is.
+    /// Note that the amplitude boosted beyond the range from 0 to 1 is clipped.
+    fn with_freq_response<F: Fn(f64) -> f64 + Send + Sync + 'static>(mut self, curve: F) -> Self {
+        let props = self.props_mut();
+        props.freq_response = Some(Box::new(curve));
+        props.built = false;
+        self
+    }
+    /// Multiply the amplitudes of the modulation by `factor`, e.g., 0.8 to reduce the depth to 80 %.
+    ///
+    /// Since the buffer holds the duty ratios encoded with asin, the scaling is done in amplitude by decoding and re-encoding the buffer.
+    /// The transforms by [scaled](Self::scaled) and [offset](Self::offset) are applied in the order of calls, and each result is clamped to the valid range.
+    fn scaled(mut self, factor: f64) -> Self {
+        let props = self.props_mut();
+        props.transforms.push(BufferTransform::Scale(factor));
+        props.built = false;
+        self
+    }
+    /// Add `bias` to the values of the buffer, i.e., the encoded duty ratios. See [scaled](Self::scaled) for the composition.
+    fn offset(mut self, bias: u8) -> Self {
+        let props = self.props_mut();
+        props.transforms.push(BufferTransform::Offset(bias));
+        props.built = false;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transformed(buffer: Vec<u8>, transforms: Vec<BufferTransform>) -> Vec<u8> {
+        let mut props = ModProps::new();
+        props.buffer = buffer;
+        props.transforms = transforms;
+        props.apply_transforms();
+        props.buffer
+    }
+
+    fn amp(d: u8) -> f64 {
+        (d as f64 / 255.0 * PI / 2.0).sin()
+    }
+
+    #[test]
+    fn scale_in_amplitude() {
+        let buffer: Vec<u8> = (0..=255).collect();
+        assert_eq!(
+            transformed(buffer.clone(), vec![BufferTransform::Scale(1.0)]),
+            buffer
+        );
+
+        let scaled = transformed(buffer.clone(), vec![BufferTransform::Scale(0.5)]);
+        assert_eq!(scaled[255], 85);
+        buffer.iter().zip(scaled.iter()).for_each(|(&d, &s)| {
+            assert!((amp(s) - amp(d) * 0.5).abs() < 1. / 255. * PI / 2.);
+        });
+    }
+
+    #[test]
+    fn scale_clamped() {
+        assert_eq!(
+            transformed(vec![0, 85, 255], vec![BufferTransform::Scale(2.0)]),
+            vec![0, 255, 255]
+        );
+        assert_eq!(
+            transformed(vec![0, 85, 255], vec![BufferTransform::Scale(-1.0)]),
+            vec![0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn transforms_in_order() {
+        assert_eq!(
+            transformed(
+                vec![170],
+                vec![BufferTransform::Offset(85), BufferTransform::Scale(0.5)]
+            ),
+            vec![85]
+        );
+        assert_eq!(
+            transformed(
+                vec![170],
+                vec![BufferTransform::Scale(0.5), BufferTransform::Offset(85)]
+            ),
+            vec![((amp(170) * 0.5).asin() * 2.0 / PI * 255.0).round() as u8 + 85]
+        );
+    }
+
+    fn smoothed(buffer: Vec<u8>, window: usize) -> Result<Vec<u8>> {
+        let mut props = ModProps::new();
+        props.buffer = buffer;
+        props.smooth_window = window;
+        props.apply_smooth()?;
+        Ok(props.buffer)
+    }
+
+    #[test]
+    fn smooth_circular() {
+        assert_eq!(smoothed(vec![255, 0, 0, 0], 1).unwrap(), vec![255, 0, 0, 0]);
+        assert_eq!(smoothed(vec![255, 0, 0, 0], 2).unwrap(), vec![85, 85, 0, 0]);
+        assert_eq!(
+            smoothed(vec![0, 0, 0, 255], 3).unwrap(),
+            vec![55, 0, 55, 55]
+        );
+        assert_eq!(smoothed(vec![128; 8], 8).unwrap(), vec![128; 8]);
+    }
+
+    #[test]
+    fn smooth_window_out_of_range() {
+        assert!(matches!(
+            smoothed(vec![0; 4], 5)
+                .unwrap_err()
+                .downcast_ref::<AUTDInternalError>(),
+            Some(AUTDInternalError::SmoothWindowOutOfRange(5, 4))
+        ));
+    }
+
+    /// Amplitude of the component at `bin` of the buffer in amplitude
+    fn component(buffer: &[u8], bin: usize) -> f64 {
+        let n = buffer.len() as f64;
+        let (re, im) = buffer
+            .iter()
+            .enumerate()
+            .fold((0., 0.), |(re, im), (i, &d)| {
+                let theta = 2. * PI * (bin * i) as f64 / n;
+                (re + amp(d) * theta.cos(), im + amp(d) * theta.sin())
+            });
+        2. * re.hypot(im) / n
+    }
+
+    /// Sine wave at `freq` Hz in amplitude, sampled at 12 kHz for 10 ms, i.e., with the frequency resolution of 100 Hz
+    fn sine(freq: f64) -> Vec<u8> {
+        (0..120)
+            .map(|i| {
+                let a = 0.5 + 0.1 * (2. * PI * freq * i as f64 / 12000.).sin();
+                (a.asin() * 2. / PI * 255.).round() as u8
+            })
+            .collect()
+    }
+
+    fn equalized(buffer: Vec<u8>) -> Result<Vec<u8>> {
+        let mut props = ModProps::new();
+        props.buffer = buffer;
+        props.freq_response = Some(Box::new(|f| 1. / (1. + f / 200.)));
+        props.apply_freq_response(12000.)?;
+        Ok(props.buffer)
+    }
+
+    #[test]
+    fn freq_response_scales_by_frequency() {
+        let low = sine(100.);
+        let high = sine(300.);
+        let low_eq = equalized(low.clone()).unwrap();
+        let high_eq = equalized(high.clone()).unwrap();
+
+        let low_scale = component(&low_eq, 1) / component(&low, 1);
+        let high_scale = component(&high_eq, 3) / component(&high, 3);
+        assert!((low_scale - 1.5).abs() < 0.02, "{}", low_scale);
+        assert!((high_scale - 2.5).abs() < 0.02, "{}", high_scale);
+
+        // DC is kept
+        let mean = |b: &[u8]| b.iter().map(|&d| amp(d)).sum::<f64>() / b.len() as f64;
+        assert!((mean(&low_eq) - mean(&low)).abs() < 0.005);
+        assert!((mean(&high_eq) - mean(&high)).abs() < 0.005);
+    }
+
+    #[test]
+    fn freq_response_must_be_positive() {
+        let mut props = ModProps::new();
+        props.buffer = sine(100.);
+        props.freq_response = Some(Box::new(|f| if f > 1000. { 0. } else { 1. }));
+        let err = props.apply_freq_response(12000.).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<AUTDInternalError>(),
+            Some(AUTDInternalError::InvalidFreqResponse(f, r)) if *f > 1000. && *r == 0.
+        ));
+    }
+}
@@ -18,6 +18,11 @@ use crate::{
 use anyhow::Result;
 use autd3_driver::TxDatagram;
 
+/// Configuration of the silencer
+///
+/// Note that the firmware does not support reading back the silencer configuration from the devices,
+/// since the received data of each device has only one byte for the response.
+/// Use [step](Self::step) and [cycle](Self::cycle) to get the values to be sent.
 pub struct SilencerConfig {
     pub(crate) step: u16,
     pub(crate) cycle: u16,
@@ -36,6 +41,14 @@ impl SilencerConfig {
     pub fn none() -> Self {
         Self::new(0xFFFF, 4096)
     }
+
+    pub fn step(&self) -> u16 {
+        self.step
+    }
+
+    pub fn cycle(&self) -> u16 {
+        self.cycle
+    }
 }
 
 impl DatagramHeader for SilencerConfig {
@@ -81,3 +94,29 @@ impl Default for SilencerConfig {
         Self::new(10, 4096)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn getters_match_sent_values() {
+        [
+            SilencerConfig::new(20, 2048),
+            SilencerConfig::default(),
+            SilencerConfig::none(),
+        ]
+        .into_iter()
+        .for_each(|mut config| {
+            let mut tx = TxDatagram::new(1);
+            DatagramHeader::init(&mut config).unwrap();
+            DatagramHeader::pack(&mut config, 0x10, &mut tx).unwrap();
+            let header = tx.header().silencer_header();
+            assert_eq!(config.step(), header.step);
+            assert_eq!(config.cycle(), header.cycle);
+        });
+
+        let config = SilencerConfig::default();
+        assert_eq!((config.step(), config.cycle()), (10, 4096));
+    }
+}
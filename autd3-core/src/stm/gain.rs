@@ -24,6 +24,10 @@ use autd3_driver::{Drive, Mode, TxDatagram, FPGA_CLK_FREQ, STM_SAMPLING_FREQ_DIV
 
 use super::STM;
 
+/// STM of gains
+///
+/// Each frame is sent as a whole body, even if it differs from the previous frame in only a few transducers.
+/// This is because the firmware stores the STM frames in the FPGA memory in the order of the transducer index and has no way to address a part of them, so that a delta-encoded update is not supported.
 pub struct GainSTM<T: Transducer> {
     gains: Vec<Vec<Drive>>,
     sample_freq_div: u32,
@@ -62,7 +66,8 @@ impl<T: Transducer> GainSTM<T> {
 
         gain.build(geometry)?;
 
-        let drives = gain.take_drives();
+        let mut drives = gain.take_drives();
+        geometry.apply_device_phase_offsets(&mut drives);
 
         self.gains.push(drives);
         Ok(())
@@ -380,3 +385,46 @@ impl Sendable<NormalPhaseTransducer> for GainSTM<NormalPhaseTransducer> {
         DatagramBody::<NormalPhaseTransducer>::is_finished(self)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::{GeometryBuilder, Vector3};
+
+    #[test]
+    fn every_frame_sends_whole_body() {
+        let mut geometry = GeometryBuilder::new().legacy_mode().build();
+        geometry.add_device(Vector3::zeros(), Vector3::zeros());
+
+        let drive = Drive {
+            phase: 0.25,
+            amp: 1.0,
+            cycle: 4096,
+        };
+        let mut stm = GainSTM::<LegacyTransducer>::new();
+        let mut drives = vec![drive; geometry.num_transducers()];
+        stm.gains.push(drives.clone());
+        drives[5].phase = 0.75;
+        stm.gains.push(drives);
+
+        let mut tx = TxDatagram::new(1);
+        let mut frames = vec![];
+        DatagramBody::<LegacyTransducer>::init(&mut stm).unwrap();
+        while !DatagramBody::<LegacyTransducer>::is_finished(&stm) {
+            DatagramBody::<LegacyTransducer>::pack(&mut stm, &geometry, &mut tx).unwrap();
+            frames.push(tx.body()[0].data);
+        }
+
+        // the first frame has only the header
+        assert_eq!(frames.len(), 3);
+        let (prev, next) = (&frames[1], &frames[2]);
+        assert!(prev.iter().all(|&d| d != 0));
+        assert!(next.iter().all(|&d| d != 0));
+        prev.iter()
+            .zip(next.iter())
+            .enumerate()
+            .for_each(|(i, (a, b))| {
+                assert_eq!(a != b, i == 5);
+            });
+    }
+}
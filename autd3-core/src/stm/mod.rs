@@ -15,12 +15,113 @@ mod gain;
 mod point;
 
 pub use gain::GainSTM;
-pub use point::PointSTM;
+pub use point::{helix_points, InterpMode, PointSTM};
 
 pub trait STM {
     fn set_freq(&mut self, freq: f64) -> f64;
     fn freq(&self) -> f64;
+    /// Relative error of the current frequency from `requested`, e.g., the value passed to [set_freq](Self::set_freq)
+    ///
+    /// The frequency is quantized by the integer sampling frequency division, and limited by its minimum [STM_SAMPLING_FREQ_DIV_MIN](autd3_driver::STM_SAMPLING_FREQ_DIV_MIN).
+    /// For example, 37 Hz over 1000 points results in about 37.001 Hz, but 37 Hz over 4000 points results in about 25.4 Hz, i.e., the error of -31 %.
+    fn freq_error(&self, requested: f64) -> f64 {
+        (self.freq() - requested) / requested
+    }
     fn sampling_freq(&self) -> f64;
+    /// Set the sampling frequency division of STM.
+    ///
+    /// STM is sampled by its own clock in the FPGA, independently of the sampling frequency division of the modulation, which is set by [Modulation::sampling_frequency_division](crate::modulation::Modulation::sampling_frequency_division).
+    /// Hence, a modulation and STM sent at once as a header and a body run at their own rates, e.g., a fast spatial sweep with a slow amplitude envelope.
+    /// See [check_rate_compatibility] for the beating between them.
     fn set_sampling_freq_div(&mut self, freq_div: u32);
     fn sampling_freq_div(&mut self) -> u32;
+    /// Set the sampling frequency division clamped to the valid range, and return the value actually applied.
+    ///
+    /// Unlike [set_sampling_freq_div](Self::set_sampling_freq_div), the value below the minimum does not cause an error on sending.
+    fn set_sampling_freq_div_clamped(&mut self, freq_div: u32) -> u32 {
+        let freq_div = freq_div.max(autd3_driver::STM_SAMPLING_FREQ_DIV_MIN);
+        self.set_sampling_freq_div(freq_div);
+        freq_div
+    }
+}
+
+/// Result of [check_rate_compatibility]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RateWarning {
+    /// One frequency is an integer multiple of the other
+    Compatible,
+    /// The frequencies are not harmonically related. The value is the frequency of the beat with the nearest harmonic.
+    NotHarmonic(f64),
+}
+
+/// Check whether the frequency of STM and that of modulation are harmonically related.
+/// Otherwise, beating artifacts appear when they are used together.
+///
+/// # Arguments
+///
+/// * `seq_freq` - Frequency of STM
+/// * `mod_freq` - Frequency of modulation
+///
+pub fn check_rate_compatibility(seq_freq: f64, mod_freq: f64) -> RateWarning {
+    const TOLERANCE: f64 = 1e-3;
+
+    let (high, low) = if seq_freq > mod_freq {
+        (seq_freq, mod_freq)
+    } else {
+        (mod_freq, seq_freq)
+    };
+    let ratio = high / low;
+    let harmonic = ratio.round();
+    if (ratio - harmonic).abs() < TOLERANCE * harmonic {
+        RateWarning::Compatible
+    } else {
+        RateWarning::NotHarmonic((high - harmonic * low).abs())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_compatibility() {
+        assert_eq!(
+            check_rate_compatibility(150., 150.),
+            RateWarning::Compatible
+        );
+        assert_eq!(
+            check_rate_compatibility(300., 150.),
+            RateWarning::Compatible
+        );
+        assert_eq!(check_rate_compatibility(50., 150.), RateWarning::Compatible);
+        assert_eq!(
+            check_rate_compatibility(150.01, 300.),
+            RateWarning::Compatible
+        );
+
+        match check_rate_compatibility(160., 150.) {
+            RateWarning::NotHarmonic(beat) => assert!((beat - 10.).abs() < 1e-9),
+            r => panic!("{:?}", r),
+        }
+        match check_rate_compatibility(100., 220.) {
+            RateWarning::NotHarmonic(beat) => assert!((beat - 20.).abs() < 1e-9),
+            r => panic!("{:?}", r),
+        }
+    }
+
+    #[test]
+    fn set_sampling_freq_div_clamped() {
+        let min = autd3_driver::STM_SAMPLING_FREQ_DIV_MIN;
+
+        let mut stm = PointSTM::new();
+        assert_eq!(stm.set_sampling_freq_div_clamped(min - 1), min);
+        assert_eq!(stm.sampling_freq_div(), min);
+        assert_eq!(stm.set_sampling_freq_div_clamped(0), min);
+        assert_eq!(stm.set_sampling_freq_div_clamped(min * 2), min * 2);
+        assert_eq!(stm.sampling_freq_div(), min * 2);
+
+        let mut stm = GainSTM::<crate::geometry::LegacyTransducer>::new();
+        assert_eq!(stm.set_sampling_freq_div_clamped(1), min);
+        assert_eq!(stm.sampling_freq_div(), min);
+    }
 }
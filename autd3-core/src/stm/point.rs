@@ -11,19 +11,31 @@
  *
  */
 
+use std::f64::consts::PI;
+
 use crate::{
+    error::AUTDInternalError,
     geometry::{Geometry, Transducer, Vector3},
     interface::{DatagramBody, Empty, Filled, Sendable},
 };
 
 use anyhow::{Ok, Result};
 use autd3_driver::{
-    SeqFocus, TxDatagram, FPGA_CLK_FREQ, POINT_STM_BODY_DATA_SIZE, POINT_STM_HEAD_DATA_SIZE,
-    STM_SAMPLING_FREQ_DIV_MIN,
+    SeqFocus, TxDatagram, FPGA_CLK_FREQ, POINT_STM_BODY_DATA_SIZE, POINT_STM_DUTY_SHIFT_MAX,
+    POINT_STM_HEAD_DATA_SIZE, STM_SAMPLING_FREQ_DIV_MIN,
 };
 
 use super::STM;
 
+/// Interpolation between the waypoints of [PointSTM::with_interpolation]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InterpMode {
+    /// Straight lines between the waypoints
+    Linear,
+    /// Catmull-Rom spline passing through the waypoints, whose velocity is continuous
+    CatmullRom,
+}
+
 pub struct PointSTM {
     control_points: Vec<(Vector3, u8)>,
     sample_freq_div: u32,
@@ -43,6 +55,65 @@ impl PointSTM {
         }
     }
 
+    /// Generate `target_points` control points along the closed path through `waypoints`, since STM loops back to the first point
+    ///
+    /// The points are evenly spaced in the parameter of each segment, i.e., the same number of points per segment, so that the focus moves faster on longer segments.
+    /// The duty shift is interpolated linearly in both modes.
+    ///
+    /// # Arguments
+    ///
+    /// * `waypoints` - Waypoints and their duty shifts
+    /// * `target_points` - Number of the control points, up to [POINT_STM_BUF_SIZE_MAX](autd3_driver::POINT_STM_BUF_SIZE_MAX)
+    /// * `mode` - Interpolation between the waypoints
+    ///
+    pub fn with_interpolation(
+        waypoints: &[(Vector3, u8)],
+        target_points: usize,
+        mode: InterpMode,
+    ) -> Result<Self> {
+        if target_points > autd3_driver::POINT_STM_BUF_SIZE_MAX {
+            return Err(autd3_driver::FPGAError::PointSTMOutOfBuffer(target_points).into());
+        }
+        if waypoints.is_empty() {
+            return Err(AUTDInternalError::EmptyWaypoints.into());
+        }
+
+        let n = waypoints.len();
+        let point = |i: isize| &waypoints[i.rem_euclid(n as isize) as usize];
+        let control_points = (0..target_points)
+            .map(|k| {
+                let s = k as f64 * n as f64 / target_points as f64;
+                let i = s.floor() as isize;
+                let t = s - i as f64;
+                let (p1, d1) = point(i);
+                let (p2, d2) = point(i + 1);
+                let pos = match mode {
+                    InterpMode::Linear => p1 + (p2 - p1) * t,
+                    InterpMode::CatmullRom => {
+                        let (p0, _) = point(i - 1);
+                        let (p3, _) = point(i + 2);
+                        (p1 * 2.0
+                            + (p2 - p0) * t
+                            + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t * t
+                            + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t * t * t)
+                            * 0.5
+                    }
+                };
+                let duty = (*d1 as f64 + (*d2 as f64 - *d1 as f64) * t).round() as u8;
+                (pos, duty)
+            })
+            .collect();
+
+        Ok(Self::with_control_points(control_points))
+    }
+
+    /// Add a control point
+    ///
+    /// # Arguments
+    ///
+    /// * `point` - Position of the focus
+    /// * `duty_shift` - Duty shift up to [POINT_STM_DUTY_SHIFT_MAX], i.e., any `u8`. The duty ratio is 2^-`duty_shift` of the maximum.
+    ///
     pub fn add(&mut self, point: Vector3, duty_shift: u8) -> Result<()> {
         if self.control_points.len() + 1 > autd3_driver::POINT_STM_BUF_SIZE_MAX {
             return Err(autd3_driver::FPGAError::PointSTMOutOfBuffer(
@@ -54,13 +125,125 @@ impl PointSTM {
         Ok(())
     }
 
+    /// Add a control point with the duty ratio normalized by the maximum, which is rounded to the nearest available one, i.e., a power of 1/2
+    ///
+    /// The duty less than the minimum available one, including 0, results in the minimum.
+    pub fn add_normalized(&mut self, point: Vector3, duty: f64) -> Result<()> {
+        if !(0.0..=1.0).contains(&duty) {
+            return Err(AUTDInternalError::NormalizedDutyOutOfRange(duty).into());
+        }
+        let duty_shift = (-duty.log2())
+            .round()
+            .clamp(0.0, POINT_STM_DUTY_SHIFT_MAX as f64) as u8;
+        self.add(point, duty_shift)
+    }
+
+    /// Add the control points as many as the buffer can hold, and return the number of the points added.
+    ///
+    /// Unlike [add](Self::add), the points exceeding [POINT_STM_BUF_SIZE_MAX](autd3_driver::POINT_STM_BUF_SIZE_MAX) are not an error but left unconsumed in the iterator.
+    pub fn try_add_points<I: IntoIterator<Item = (Vector3, u8)>>(&mut self, points: I) -> usize {
+        let remaining =
+            autd3_driver::POINT_STM_BUF_SIZE_MAX.saturating_sub(self.control_points.len());
+        let len = self.control_points.len();
+        self.control_points
+            .extend(points.into_iter().take(remaining));
+        self.control_points.len() - len
+    }
+
     pub fn size(&self) -> usize {
         self.control_points.len()
     }
 
+    /// Recommend the sampling frequency division with which the focus does not jump more than `max_jump_mm` in a period of ultrasound.
+    ///
+    /// Since the transducers are driven at 40 kHz, the control points sampled faster than that are skipped, and the focus effectively jumps over multiple points in a period.
+    /// The returned value is the minimum division, i.e., the fastest playback, with which the distance traveled in a period is within `max_jump_mm`.
+    /// Returns an error if the spacing between consecutive control points already exceeds `max_jump_mm`, since it cannot be improved by the division.
+    pub fn recommend_freq_div(&self, max_jump_mm: f64) -> Result<u32> {
+        // period of 40 kHz ultrasound in FPGA clocks
+        const ULTRASOUND_PERIOD: f64 = 4096.0;
+
+        let max_spacing = self
+            .control_points
+            .iter()
+            .zip(self.control_points.iter().cycle().skip(1))
+            .map(|((a, _), (b, _))| (b - a).norm())
+            .fold(0.0, f64::max);
+        if max_spacing > max_jump_mm {
+            return Err(AUTDInternalError::STMJumpTooLarge(max_spacing, max_jump_mm).into());
+        }
+        if max_spacing == 0.0 {
+            return Ok(STM_SAMPLING_FREQ_DIV_MIN);
+        }
+
+        let div = (ULTRASOUND_PERIOD * max_spacing / max_jump_mm).ceil() as u32;
+        Ok(div.max(STM_SAMPLING_FREQ_DIV_MIN))
+    }
+
     pub fn control_points(&self) -> &[(Vector3, u8)] {
         &self.control_points
     }
+
+    /// Split the control points into frames in the same way as sending, and yield each frame with whether it is the last.
+    ///
+    /// # Arguments
+    ///
+    /// * `head_cap` - Maximum number of points in the first frame, i.e., [POINT_STM_HEAD_DATA_SIZE] when sending
+    /// * `body_cap` - Maximum number of points in the subsequent frames, i.e., [POINT_STM_BODY_DATA_SIZE] when sending
+    ///
+    pub fn frames(
+        &self,
+        head_cap: usize,
+        body_cap: usize,
+    ) -> impl Iterator<Item = (&[(Vector3, u8)], bool)> {
+        assert!(head_cap > 0 && body_cap > 0);
+        let len = self.control_points.len();
+        let mut sent = 0;
+        std::iter::from_fn(move || {
+            if sent == len {
+                return None;
+            }
+            let cap = if sent == 0 { head_cap } else { body_cap };
+            let size = (len - sent).min(cap);
+            let frame = &self.control_points[sent..sent + size];
+            sent += size;
+            Some((frame, sent == len))
+        })
+    }
+}
+
+/// Generate control points along a helix around the z-axis, e.g., to transport a levitated object upward
+///
+/// The helix starts at `base_center + (radius, 0, 0)` and rises by `pitch` per turn.
+///
+/// # Arguments
+///
+/// * `base_center` - Center of the bottom of the helix
+/// * `radius` - Radius of the helix
+/// * `pitch` - Height per turn. Negative value makes the helix go downward.
+/// * `turns` - Number of turns
+/// * `points_per_turn` - Number of control points per turn
+/// * `duty` - Duty shift of all control points
+///
+pub fn helix_points(
+    base_center: Vector3,
+    radius: f64,
+    pitch: f64,
+    turns: f64,
+    points_per_turn: usize,
+    duty: u8,
+) -> Vec<(Vector3, u8)> {
+    let n = (turns * points_per_turn as f64).round() as usize;
+    (0..n)
+        .map(|i| {
+            let t = i as f64 / points_per_turn as f64;
+            let theta = 2.0 * PI * t;
+            (
+                base_center + Vector3::new(radius * theta.cos(), radius * theta.sin(), pitch * t),
+                duty,
+            )
+        })
+        .collect()
 }
 
 impl Default for PointSTM {
@@ -166,3 +349,156 @@ impl STM for PointSTM {
         self.sample_freq_div
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::{GeometryBuilder, LegacyTransducer};
+
+    #[test]
+    fn add_accepts_duty_shift_max() {
+        let mut stm = PointSTM::new();
+        stm.add(Vector3::zeros(), 0).unwrap();
+        stm.add(Vector3::zeros(), POINT_STM_DUTY_SHIFT_MAX).unwrap();
+        assert_eq!(stm.control_points[1].1, POINT_STM_DUTY_SHIFT_MAX);
+    }
+
+    #[test]
+    fn add_normalized_boundaries() {
+        let mut stm = PointSTM::new();
+        for duty in [1.0, 0.5, 0.3, 0.0] {
+            stm.add_normalized(Vector3::zeros(), duty).unwrap();
+        }
+        let shifts: Vec<_> = stm.control_points.iter().map(|&(_, s)| s).collect();
+        assert_eq!(shifts, vec![0, 1, 2, POINT_STM_DUTY_SHIFT_MAX]);
+
+        for duty in [-0.1, 1.1, f64::NAN] {
+            assert!(stm.add_normalized(Vector3::zeros(), duty).is_err());
+        }
+        assert_eq!(stm.size(), 4);
+    }
+
+    #[test]
+    fn frames() {
+        let mut stm = PointSTM::new();
+        assert_eq!(stm.frames(3, 4).count(), 0);
+
+        (0..10).for_each(|i| stm.add(Vector3::new(i as f64, 0., 0.), 0).unwrap());
+        let frames = stm.frames(3, 4).collect::<Vec<_>>();
+        assert_eq!(
+            frames
+                .iter()
+                .map(|(f, l)| (f.len(), *l))
+                .collect::<Vec<_>>(),
+            vec![(3, false), (4, false), (3, true)]
+        );
+        assert_eq!(
+            frames
+                .iter()
+                .flat_map(|(f, _)| f.iter())
+                .copied()
+                .collect::<Vec<_>>(),
+            stm.control_points()
+        );
+    }
+
+    #[test]
+    fn frames_same_as_sending() {
+        let mut geometry = GeometryBuilder::new().legacy_mode().build();
+        geometry.add_device(Vector3::zeros(), Vector3::zeros());
+
+        let mut stm = PointSTM::new();
+        (0..POINT_STM_HEAD_DATA_SIZE + 2 * POINT_STM_BODY_DATA_SIZE + 1)
+            .for_each(|i| stm.add(Vector3::new(i as f64, 0., 0.), 0).unwrap());
+
+        let mut tx = TxDatagram::new(1);
+        let mut num_frames = 0;
+        DatagramBody::<LegacyTransducer>::init(&mut stm).unwrap();
+        while !DatagramBody::<LegacyTransducer>::is_finished(&stm) {
+            DatagramBody::<LegacyTransducer>::pack(&mut stm, &geometry, &mut tx).unwrap();
+            num_frames += 1;
+        }
+
+        let frames = stm
+            .frames(POINT_STM_HEAD_DATA_SIZE, POINT_STM_BODY_DATA_SIZE)
+            .collect::<Vec<_>>();
+        assert_eq!(frames.len(), num_frames);
+        assert_eq!(frames.len(), 4);
+        assert!(frames.last().unwrap().1);
+    }
+
+    #[test]
+    fn try_add_points_up_to_capacity() {
+        let max = autd3_driver::POINT_STM_BUF_SIZE_MAX;
+        let mut stm = PointSTM::new();
+        assert_eq!(
+            stm.try_add_points(std::iter::repeat_n((Vector3::zeros(), 0), max - 3)),
+            max - 3
+        );
+
+        let mut points = (0..10).map(|i| (Vector3::new(i as f64, 0., 0.), 0));
+        assert_eq!(stm.try_add_points(points.by_ref()), 3);
+        assert_eq!(stm.size(), max);
+        assert_eq!(stm.control_points()[max - 1].0, Vector3::new(2., 0., 0.));
+        // the rest are left in the iterator
+        assert_eq!(points.next().unwrap().0, Vector3::new(3., 0., 0.));
+
+        assert_eq!(stm.try_add_points(points), 0);
+        assert_eq!(stm.size(), max);
+    }
+
+    #[test]
+    fn recommend_freq_div() {
+        // square with sides of 10 mm, including the closing edge back to the first point
+        let mut stm = PointSTM::new();
+        stm.add(Vector3::new(0., 0., 0.), 0).unwrap();
+        stm.add(Vector3::new(10., 0., 0.), 0).unwrap();
+        stm.add(Vector3::new(10., 10., 0.), 0).unwrap();
+        stm.add(Vector3::new(0., 10., 0.), 0).unwrap();
+
+        assert_eq!(stm.recommend_freq_div(10.).unwrap(), 4096);
+        assert_eq!(stm.recommend_freq_div(20.).unwrap(), 2048);
+        assert_eq!(stm.recommend_freq_div(24.).unwrap(), 1707);
+        assert_eq!(
+            stm.recommend_freq_div(100.).unwrap(),
+            STM_SAMPLING_FREQ_DIV_MIN
+        );
+
+        let err = stm.recommend_freq_div(5.).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<AUTDInternalError>(),
+            Some(AUTDInternalError::STMJumpTooLarge(s, m)) if *s == 10. && *m == 5.
+        ));
+
+        let mut stm = PointSTM::new();
+        stm.add(Vector3::new(1., 2., 3.), 0).unwrap();
+        stm.add(Vector3::new(1., 2., 3.), 0).unwrap();
+        assert_eq!(
+            stm.recommend_freq_div(1.).unwrap(),
+            STM_SAMPLING_FREQ_DIV_MIN
+        );
+    }
+
+    #[test]
+    fn helix_points() {
+        let center = Vector3::new(10., 20., 150.);
+        let points = super::helix_points(center, 5., 2., 2.5, 8, 3);
+        assert_eq!(points.len(), 20);
+
+        assert!((points[0].0 - center - Vector3::new(5., 0., 0.)).norm() < 1e-9);
+        // a quarter turn
+        assert!((points[2].0 - center - Vector3::new(0., 5., 0.5)).norm() < 1e-9);
+        // one turn rises by the pitch
+        assert!((points[8].0 - center - Vector3::new(5., 0., 2.)).norm() < 1e-9);
+
+        points.iter().enumerate().for_each(|(i, &(p, duty))| {
+            let r = p - center;
+            assert!((r.x.hypot(r.y) - 5.).abs() < 1e-9);
+            assert!((r.z - 2. * i as f64 / 8.).abs() < 1e-9);
+            assert_eq!(duty, 3);
+        });
+
+        let points = super::helix_points(center, 5., -2., 1., 4, 0);
+        assert!(points.iter().skip(1).all(|(p, _)| p.z < center.z));
+    }
+}
@@ -12,7 +12,7 @@
  */
 
 use crate::{
-    fpga::{Duty, LegacyDrive, Phase},
+    fpga::{Duty, LegacyDrive, Phase, POINT_STM_DUTY_SHIFT_MASK, POINT_STM_DUTY_SHIFT_OFFSET},
     hardware::NUM_TRANS_IN_UNIT,
     Drive, Mode, POINT_STM_FIXED_NUM_UNIT,
 };
@@ -96,7 +96,8 @@ impl SeqFocus {
             ((y << 2) & 0xFFFC) as u16 | ((x >> 30) & 0x0002) as u16 | ((x >> 16) & 0x0001) as u16;
         let d2 =
             ((z << 4) & 0xFFF0) as u16 | ((y >> 28) & 0x0008) as u16 | ((y >> 14) & 0x0007) as u16;
-        let d3 = (((duty_shift as u16) << 6) & 0x3FC0) as u16
+        let d3 = ((u16::from(duty_shift) << POINT_STM_DUTY_SHIFT_OFFSET)
+            & POINT_STM_DUTY_SHIFT_MASK)
             | ((z >> 26) & 0x0020) as u16
             | ((z >> 12) & 0x001F) as u16;
         SeqFocus {
@@ -243,3 +244,31 @@ impl GainSTMBodyBody {
         unsafe { std::mem::transmute(&mut self.data) }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::POINT_STM_DUTY_SHIFT_MAX;
+
+    #[test]
+    fn duty_shift_max_fills_field() {
+        assert_eq!(POINT_STM_DUTY_SHIFT_MAX, u8::MAX);
+        let focus = SeqFocus::new(0., 0., 0., POINT_STM_DUTY_SHIFT_MAX);
+        assert_eq!(focus.buf[3], POINT_STM_DUTY_SHIFT_MASK);
+    }
+
+    #[test]
+    fn duty_shift_does_not_disturb_z() {
+        let z = -POINT_STM_FIXED_NUM_UNIT;
+        let without_shift = SeqFocus::new(0., 0., z, 0).buf[3];
+        assert_eq!(without_shift & POINT_STM_DUTY_SHIFT_MASK, 0);
+        for duty_shift in [1, 0x80, POINT_STM_DUTY_SHIFT_MAX] {
+            let d3 = SeqFocus::new(0., 0., z, duty_shift).buf[3];
+            assert_eq!(d3 & !POINT_STM_DUTY_SHIFT_MASK, without_shift);
+            assert_eq!(
+                (d3 & POINT_STM_DUTY_SHIFT_MASK) >> POINT_STM_DUTY_SHIFT_OFFSET,
+                duty_shift as u16
+            );
+        }
+    }
+}
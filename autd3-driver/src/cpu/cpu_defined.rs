@@ -22,6 +22,17 @@ pub const MSG_EMU_GEOMETRY_SET: u8 = 0xFF;
 pub const MOD_HEAD_DATA_SIZE: usize = 120;
 pub const MOD_BODY_DATA_SIZE: usize = 124;
 
+/// Bytes at the end of the modulation data area reserved for the checksum
+#[cfg(feature = "checksum")]
+pub const MOD_CHECKSUM_RESERVED_SIZE: usize = 2;
+#[cfg(not(feature = "checksum"))]
+pub const MOD_CHECKSUM_RESERVED_SIZE: usize = 0;
+
+/// Maximum number of modulation data sent in the first frame
+pub const MOD_HEAD_CHUNK_SIZE: usize = MOD_HEAD_DATA_SIZE - MOD_CHECKSUM_RESERVED_SIZE;
+/// Maximum number of modulation data sent in the subsequent frames
+pub const MOD_BODY_CHUNK_SIZE: usize = MOD_BODY_DATA_SIZE - MOD_CHECKSUM_RESERVED_SIZE;
+
 pub const POINT_STM_HEAD_DATA_SIZE: usize = 61;
 pub const POINT_STM_BODY_DATA_SIZE: usize = 62;
 
@@ -43,7 +54,7 @@ bitflags::bitflags! {
 }
 
 #[repr(u16)]
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Mode {
     PhaseDutyFull = 0x0001,
     PhaseFull = 0x0002,
@@ -11,7 +11,15 @@
  *
  */
 
-use crate::cpu::{Body, GlobalHeader};
+use anyhow::Result;
+
+use crate::{
+    cpu::{
+        Body, CPUControlFlags, CPUError, GlobalHeader, MOD_BODY_DATA_SIZE, MOD_HEAD_DATA_SIZE,
+        MSG_BEGIN, MSG_END,
+    },
+    fpga::{FPGAControlFlags, FPGAError, MOD_SAMPLING_FREQ_DIV_MIN, SILENCER_CYCLE_MIN},
+};
 
 #[derive(Clone)]
 pub struct TxDatagram {
@@ -40,6 +48,10 @@ impl TxDatagram {
         &self.data
     }
 
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+
     pub fn header(&self) -> &GlobalHeader {
         unsafe {
             (self.data.as_ptr() as *const GlobalHeader)
@@ -76,6 +88,130 @@ impl TxDatagram {
     pub fn copy_from(&mut self, src: &TxDatagram) {
         self.data.copy_from_slice(&src.data);
     }
+
+    /// Compare with `other` and list the differences.
+    /// Each variant of [DatagramDiff] holds the value of `self` first and that of `other` second.
+    pub fn diff(&self, other: &TxDatagram) -> Vec<DatagramDiff> {
+        let mut diffs = Vec::new();
+
+        let (h, oh) = (self.header(), other.header());
+        if h.msg_id != oh.msg_id {
+            diffs.push(DatagramDiff::MsgId(h.msg_id, oh.msg_id));
+        }
+        if h.fpga_flag != oh.fpga_flag {
+            diffs.push(DatagramDiff::FPGAFlag(h.fpga_flag, oh.fpga_flag));
+        }
+        if h.cpu_flag != oh.cpu_flag {
+            diffs.push(DatagramDiff::CPUFlag(h.cpu_flag, oh.cpu_flag));
+        }
+        if h.size != oh.size {
+            diffs.push(DatagramDiff::Size(h.size, oh.size));
+        }
+        h.data
+            .iter()
+            .zip(oh.data.iter())
+            .enumerate()
+            .filter(|(_, (a, b))| a != b)
+            .for_each(|(i, (&a, &b))| diffs.push(DatagramDiff::HeaderData(i, a, b)));
+
+        if self.num_bodies != other.num_bodies {
+            diffs.push(DatagramDiff::NumBodies(self.num_bodies, other.num_bodies));
+        }
+        let header_size = std::mem::size_of::<GlobalHeader>();
+        let body_size = std::mem::size_of::<Body>();
+        let num_bodies = self.num_bodies.min(other.num_bodies);
+        self.data[header_size..header_size + body_size * num_bodies]
+            .iter()
+            .zip(other.data[header_size..].iter())
+            .enumerate()
+            .filter(|(_, (a, b))| a != b)
+            .for_each(|(i, (&a, &b))| {
+                diffs.push(DatagramDiff::Body(i / body_size, i % body_size, a, b))
+            });
+
+        diffs
+    }
+
+    /// Check the consistency of the header fields, flags, and the number of bodies.
+    ///
+    /// Special messages such as clear and reading versions are not checked.
+    pub fn validate(&self) -> Result<()> {
+        if self.num_bodies > self.size {
+            return Err(CPUError::InvalidDatagram(format!(
+                "{} bodies are to be sent, but the buffer has only {}",
+                self.num_bodies, self.size
+            ))
+            .into());
+        }
+
+        let header = self.header();
+        if !(MSG_BEGIN..=MSG_END).contains(&header.msg_id) {
+            return Ok(());
+        }
+
+        let cpu_flag = header.cpu_flag;
+        let size = header.size as usize;
+        if cpu_flag.contains(CPUControlFlags::MOD) {
+            if size == 0 {
+                return Err(CPUError::InvalidDatagram(
+                    "MOD flag is set, but the size of modulation data is 0".to_owned(),
+                )
+                .into());
+            }
+            if cpu_flag.contains(CPUControlFlags::MOD_BEGIN) {
+                if size > MOD_HEAD_DATA_SIZE {
+                    return Err(CPUError::ModulationHeadDataSizeOutOfRange(size).into());
+                }
+                let freq_div = header.mod_head().freq_div;
+                if freq_div < MOD_SAMPLING_FREQ_DIV_MIN {
+                    return Err(FPGAError::ModFreqDivOutOfRange(freq_div).into());
+                }
+            } else if size > MOD_BODY_DATA_SIZE {
+                return Err(CPUError::ModulationBodyDataSizeOutOfRange(size).into());
+            }
+        } else {
+            if cpu_flag.contains(CPUControlFlags::CONFIG_SILENCER | CPUControlFlags::CONFIG_SYNC) {
+                return Err(CPUError::InvalidDatagram(
+                    "CONFIG_SILENCER and CONFIG_SYNC flags are set at the same time".to_owned(),
+                )
+                .into());
+            }
+            if cpu_flag.contains(CPUControlFlags::CONFIG_SILENCER) {
+                let cycle = header.silencer_header().cycle;
+                if cycle < SILENCER_CYCLE_MIN {
+                    return Err(FPGAError::SilencerCycleOutOfRange(cycle).into());
+                }
+            }
+            if cpu_flag.contains(CPUControlFlags::CONFIG_SYNC) && self.num_bodies == 0 {
+                return Err(CPUError::InvalidDatagram(
+                    "CONFIG_SYNC flag is set, but no body is to be sent".to_owned(),
+                )
+                .into());
+            }
+        }
+
+        if cpu_flag.contains(CPUControlFlags::WRITE_BODY) && self.num_bodies == 0 {
+            return Err(CPUError::InvalidDatagram(
+                "WRITE_BODY flag is set, but no body is to be sent".to_owned(),
+            )
+            .into());
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DatagramDiff {
+    MsgId(u8, u8),
+    FPGAFlag(FPGAControlFlags, FPGAControlFlags),
+    CPUFlag(CPUControlFlags, CPUControlFlags),
+    Size(u8, u8),
+    /// Index in the header data, and the bytes.
+    HeaderData(usize, u8, u8),
+    NumBodies(usize, usize),
+    /// Device index, byte offset in the body, and the bytes.
+    Body(usize, usize, u8, u8),
 }
 
 #[derive(Clone, Copy)]
@@ -119,8 +255,116 @@ impl RxDatagram {
     pub fn messages_mut(&mut self) -> &mut [RxMessage] {
         &mut self.data
     }
+
+    /// Indices of the devices which have not processed the message of `msg_id` yet.
+    pub fn unacked(&self, msg_id: u8) -> Vec<usize> {
+        self.data
+            .iter()
+            .enumerate()
+            .filter(|(_, msg)| msg.msg_id != msg_id)
+            .map(|(i, _)| i)
+            .collect()
+    }
 }
 
 pub fn is_msg_processed(msg_id: u8, rx: &RxDatagram) -> bool {
     rx.data.iter().all(|msg| msg.msg_id == msg_id)
 }
+
+/// The message id following `current`, which wraps around within [MSG_BEGIN, MSG_END] to avoid the ids reserved for special messages.
+pub fn next_msg_id(current: u8) -> u8 {
+    if (MSG_BEGIN..MSG_END).contains(&current) {
+        current + 1
+    } else {
+        MSG_BEGIN
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::{
+        MSG_CLEAR, MSG_EMU_GEOMETRY_SET, MSG_RD_CPU_VERSION, MSG_RD_FPGA_FUNCTION,
+        MSG_RD_FPGA_VERSION,
+    };
+
+    const RESERVED: [u8; 5] = [
+        MSG_CLEAR,
+        MSG_RD_CPU_VERSION,
+        MSG_RD_FPGA_VERSION,
+        MSG_RD_FPGA_FUNCTION,
+        MSG_EMU_GEOMETRY_SET,
+    ];
+
+    #[test]
+    fn next_msg_id_wraps_without_reserved() {
+        let period = (MSG_END - MSG_BEGIN) as usize + 1;
+        let mut seen = vec![0; 256];
+        let mut id = MSG_CLEAR;
+        for _ in 0..period {
+            id = next_msg_id(id);
+            assert!(!RESERVED.contains(&id), "reserved id {:#04X}", id);
+            assert!((MSG_BEGIN..=MSG_END).contains(&id));
+            seen[id as usize] += 1;
+        }
+        assert_eq!(id, MSG_END);
+        assert!((MSG_BEGIN..=MSG_END).all(|i| seen[i as usize] == 1));
+        assert_eq!(next_msg_id(id), MSG_BEGIN);
+    }
+
+    #[test]
+    fn next_msg_id_from_any_id() {
+        (0..=u8::MAX).map(next_msg_id).for_each(|id| {
+            assert!(!RESERVED.contains(&id), "reserved id {:#04X}", id);
+            assert!((MSG_BEGIN..=MSG_END).contains(&id));
+        });
+    }
+
+    #[test]
+    fn diff() {
+        let a = TxDatagram::new(2);
+        let mut b = a.clone();
+        assert!(a.diff(&b).is_empty());
+
+        b.header_mut().msg_id = 0x10;
+        b.header_mut().fpga_flag = FPGAControlFlags::LEGACY_MODE;
+        b.header_mut().data[3] = 0xFF;
+        b.body_mut()[1].data[2] = 0x1234;
+        assert_eq!(
+            a.diff(&b),
+            vec![
+                DatagramDiff::MsgId(0x00, 0x10),
+                DatagramDiff::FPGAFlag(FPGAControlFlags::NONE, FPGAControlFlags::LEGACY_MODE),
+                DatagramDiff::HeaderData(3, 0x00, 0xFF),
+                DatagramDiff::Body(1, 4, 0x00, 0x34),
+                DatagramDiff::Body(1, 5, 0x00, 0x12),
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_num_bodies() {
+        let a = TxDatagram::new(2);
+        let mut b = a.clone();
+        b.body_mut()[1].data[0] = 0xFFFF;
+        b.num_bodies = 1;
+        assert_eq!(a.diff(&b), vec![DatagramDiff::NumBodies(2, 1)]);
+    }
+
+    #[test]
+    fn unacked() {
+        let mut rx = RxDatagram::new(4);
+        rx.messages_mut()
+            .iter_mut()
+            .zip([0x10, 0x0F, 0x10, 0x00])
+            .for_each(|(msg, id)| msg.msg_id = id);
+        assert_eq!(rx.unacked(0x10), vec![1, 3]);
+        assert!(!is_msg_processed(0x10, &rx));
+
+        rx.messages_mut()
+            .iter_mut()
+            .for_each(|msg| msg.msg_id = 0x10);
+        assert!(rx.unacked(0x10).is_empty());
+        assert!(is_msg_processed(0x10, &rx));
+    }
+}
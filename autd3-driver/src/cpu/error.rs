@@ -17,6 +17,11 @@ use thiserror::Error;
 pub enum CPUError {
     #[error("{} device{} connected, but {} {} specified", a, if *a == 1 {" is"} else {"s are"}, b, if *b== 1 {"is"} else {"are"})]
     DeviceNumberNotCorrect { a: usize, b: usize },
+    #[error("{} transducer{} to be driven, but the mask has {} element{}", a, if *a == 1 {" is"} else {"s are"}, b, if *b == 1 {""} else {"s"})]
+    MaskSizeNotCorrect { a: usize, b: usize },
+    #[cfg(feature = "checksum")]
+    #[error("Modulation data of {0} bytes overlap the checksum field")]
+    NoRoomForChecksum(usize),
     #[error(
         "Maximum size is {}, but {0} data are to be send",
         crate::cpu::MOD_HEAD_DATA_SIZE
@@ -39,4 +44,6 @@ pub enum CPUError {
     PointSTMBodyDataSizeOutOfRange(usize),
     #[error("PhaseHalf is not supported in Normal mode")]
     PhaseHalfNotSupported,
+    #[error("Inconsistent datagram: {0}")]
+    InvalidDatagram(String),
 }
@@ -279,6 +279,51 @@ pub fn normal_phase_body(drive: &[Drive], tx: &mut TxDatagram) -> Result<()> {
     Ok(())
 }
 
+/// Zero the drives of the transducers whose `mask` is false, e.g., to silence faulty transducers
+fn mask_drives(drive: &[Drive], mask: &[bool]) -> Result<Vec<Drive>> {
+    if mask.len() != drive.len() {
+        return Err(CPUError::MaskSizeNotCorrect {
+            a: drive.len(),
+            b: mask.len(),
+        }
+        .into());
+    }
+    Ok(drive
+        .iter()
+        .zip(mask.iter())
+        .map(|(&d, &enabled)| {
+            if enabled {
+                d
+            } else {
+                Drive {
+                    phase: 0.0,
+                    amp: 0.0,
+                    cycle: d.cycle,
+                }
+            }
+        })
+        .collect())
+}
+
+/// Same as [normal_legacy_body], but the transducers whose `mask` is false are not driven
+pub fn normal_legacy_body_masked(
+    drive: &[Drive],
+    mask: &[bool],
+    tx: &mut TxDatagram,
+) -> Result<()> {
+    normal_legacy_body(&mask_drives(drive, mask)?, tx)
+}
+
+/// Same as [normal_duty_body], but the duties of the transducers whose `mask` is false are zero
+pub fn normal_duty_body_masked(drive: &[Drive], mask: &[bool], tx: &mut TxDatagram) -> Result<()> {
+    normal_duty_body(&mask_drives(drive, mask)?, tx)
+}
+
+/// Same as [normal_phase_body], but the phases of the transducers whose `mask` is false are zero
+pub fn normal_phase_body_masked(drive: &[Drive], mask: &[bool], tx: &mut TxDatagram) -> Result<()> {
+    normal_phase_body(&mask_drives(drive, mask)?, tx)
+}
+
 pub fn point_stm_head(tx: &mut TxDatagram) {
     tx.header_mut().cpu_flag.remove(CPUControlFlags::WRITE_BODY);
     tx.header_mut().cpu_flag.remove(CPUControlFlags::MOD_DELAY);
@@ -617,3 +662,226 @@ pub fn fpga_functions(tx: &mut TxDatagram) {
     tx.header_mut().cpu_flag = CPUControlFlags::from_bits(0x05).unwrap(); // For backward compatibility before 1.9
     tx.num_bodies = 0;
 }
+
+#[cfg(feature = "checksum")]
+const CHECKSUM_OFFSET: usize = crate::cpu::HEADER_SIZE - std::mem::size_of::<u16>();
+
+/// CRC-16/CCITT-FALSE over the header without the checksum field and the bodies to be sent
+#[cfg(feature = "checksum")]
+fn crc16(tx: &TxDatagram) -> u16 {
+    let header = &tx.data()[..CHECKSUM_OFFSET];
+    let body = &tx.data()[crate::cpu::HEADER_SIZE..tx.size()];
+    header.iter().chain(body.iter()).fold(0xFFFF, |crc, &b| {
+        (0..8).fold(crc ^ ((b as u16) << 8), |crc, _| {
+            if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+/// Store the CRC16 of the header and the bodies in the last two bytes of the header
+///
+/// This must be called after all the other operations on `tx`. The current firmware ignores the checksum.
+/// With the `checksum` feature, modulation data are split into frames of at most [MOD_HEAD_CHUNK_SIZE](crate::cpu::MOD_HEAD_CHUNK_SIZE) and [MOD_BODY_CHUNK_SIZE](crate::cpu::MOD_BODY_CHUNK_SIZE) bytes to leave room for the checksum.
+/// Modulation frames whose data nevertheless reach the last two bytes of the header are rejected.
+#[cfg(feature = "checksum")]
+pub fn finalize_checksum(tx: &mut TxDatagram) -> Result<()> {
+    let header = tx.header();
+    if header.cpu_flag.contains(CPUControlFlags::MOD) {
+        let head_size = if header.cpu_flag.contains(CPUControlFlags::MOD_BEGIN) {
+            std::mem::size_of::<u32>()
+        } else {
+            0
+        };
+        let used = 4 + head_size + header.size as usize;
+        if used > CHECKSUM_OFFSET {
+            return Err(CPUError::NoRoomForChecksum(header.size as _).into());
+        }
+    }
+
+    let crc = crc16(tx);
+    tx.data_mut()[CHECKSUM_OFFSET..crate::cpu::HEADER_SIZE].copy_from_slice(&crc.to_le_bytes());
+    Ok(())
+}
+
+/// Check the checksum stored by [finalize_checksum]
+#[cfg(feature = "checksum")]
+pub fn verify_checksum(tx: &TxDatagram) -> bool {
+    let stored = u16::from_le_bytes([tx.data()[CHECKSUM_OFFSET], tx.data()[CHECKSUM_OFFSET + 1]]);
+    stored == crc16(tx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "checksum")]
+    use crate::cpu::{MOD_BODY_CHUNK_SIZE, MOD_HEAD_CHUNK_SIZE};
+
+    #[cfg(feature = "checksum")]
+    fn tx_with_data() -> TxDatagram {
+        let mut tx = TxDatagram::new(2);
+        tx.body_mut()
+            .iter_mut()
+            .flat_map(|b| b.data.iter_mut())
+            .enumerate()
+            .for_each(|(i, d)| *d = i as u16);
+        modulation(0x10, &[0xFF; 8], true, 40960, true, &mut tx).unwrap();
+        tx
+    }
+
+    #[cfg(feature = "checksum")]
+    #[test]
+    fn checksum_round_trip() {
+        let mut tx = tx_with_data();
+        finalize_checksum(&mut tx).unwrap();
+        assert!(verify_checksum(&tx));
+        assert_eq!(tx.num_bodies, 2);
+        assert_eq!(tx.header().mod_head().data[0..8], [0xFF; 8]);
+    }
+
+    #[cfg(feature = "checksum")]
+    #[test]
+    fn checksum_detects_bit_flip() {
+        let mut tx = tx_with_data();
+        finalize_checksum(&mut tx).unwrap();
+        let size = tx.size();
+        [
+            0,
+            2,
+            5,
+            CHECKSUM_OFFSET - 1,
+            crate::cpu::HEADER_SIZE,
+            size - 1,
+        ]
+        .into_iter()
+        .for_each(|i| {
+            (0..8).for_each(|bit| {
+                tx.data_mut()[i] ^= 1 << bit;
+                assert!(!verify_checksum(&tx));
+                tx.data_mut()[i] ^= 1 << bit;
+            });
+        });
+        assert!(verify_checksum(&tx));
+    }
+
+    #[cfg(feature = "checksum")]
+    #[test]
+    fn checksum_room_in_modulation_chunk() {
+        let mut tx = TxDatagram::new(1);
+        modulation(
+            0x10,
+            &[0xFF; MOD_HEAD_CHUNK_SIZE],
+            true,
+            40960,
+            false,
+            &mut tx,
+        )
+        .unwrap();
+        finalize_checksum(&mut tx).unwrap();
+        assert!(verify_checksum(&tx));
+        assert_eq!(
+            tx.header().mod_head().data[..MOD_HEAD_CHUNK_SIZE],
+            [0xFF; MOD_HEAD_CHUNK_SIZE]
+        );
+
+        modulation(
+            0x11,
+            &[0xFF; MOD_BODY_CHUNK_SIZE],
+            false,
+            40960,
+            true,
+            &mut tx,
+        )
+        .unwrap();
+        finalize_checksum(&mut tx).unwrap();
+        assert!(verify_checksum(&tx));
+
+        modulation(
+            0x12,
+            &[0xFF; MOD_HEAD_DATA_SIZE],
+            true,
+            40960,
+            false,
+            &mut tx,
+        )
+        .unwrap();
+        assert!(matches!(
+            finalize_checksum(&mut tx)
+                .unwrap_err()
+                .downcast_ref::<CPUError>(),
+            Some(CPUError::NoRoomForChecksum(_))
+        ));
+    }
+
+    type BodyOp = fn(&[Drive], &mut TxDatagram) -> Result<()>;
+    type MaskedBodyOp = fn(&[Drive], &[bool], &mut TxDatagram) -> Result<()>;
+
+    #[test]
+    fn masked_body() {
+        let drive = (0..NUM_TRANS_IN_UNIT * 2)
+            .map(|i| Drive {
+                phase: (i % 7 + 1) as f64 / 8.,
+                amp: 1.,
+                cycle: 4096,
+            })
+            .collect::<Vec<_>>();
+        let mask = (0..drive.len()).map(|i| i % 3 != 0).collect::<Vec<_>>();
+
+        [
+            (
+                normal_legacy_body as BodyOp,
+                normal_legacy_body_masked as MaskedBodyOp,
+            ),
+            (normal_duty_body, normal_duty_body_masked),
+            (normal_phase_body, normal_phase_body_masked),
+        ]
+        .into_iter()
+        .for_each(|(op, masked_op)| {
+            let mut tx = TxDatagram::new(2);
+            op(&drive, &mut tx).unwrap();
+            let mut tx_masked = TxDatagram::new(2);
+            masked_op(&drive, &mask, &mut tx_masked).unwrap();
+
+            assert_eq!(tx_masked.num_bodies, 2);
+            assert_eq!(tx_masked.header().cpu_flag, tx.header().cpu_flag);
+            tx.body()
+                .iter()
+                .flat_map(|b| b.data.iter())
+                .zip(tx_masked.body().iter().flat_map(|b| b.data.iter()))
+                .zip(mask.iter())
+                .for_each(|((&d, &m), &enabled)| {
+                    assert_ne!(d, 0);
+                    assert_eq!(m, if enabled { d } else { 0 });
+                });
+        });
+    }
+
+    #[test]
+    fn masked_body_size_mismatch() {
+        let drive = vec![
+            Drive {
+                phase: 0.,
+                amp: 1.,
+                cycle: 4096,
+            };
+            NUM_TRANS_IN_UNIT
+        ];
+        let mask = vec![true; NUM_TRANS_IN_UNIT - 1];
+        let mut tx = TxDatagram::new(1);
+        [
+            normal_legacy_body_masked as MaskedBodyOp,
+            normal_duty_body_masked,
+            normal_phase_body_masked,
+        ]
+        .into_iter()
+        .for_each(|op| {
+            assert!(matches!(
+                op(&drive, &mask, &mut tx).unwrap_err().downcast_ref::<CPUError>(),
+                Some(CPUError::MaskSizeNotCorrect { a, b }) if *a == NUM_TRANS_IN_UNIT && *b == NUM_TRANS_IN_UNIT - 1
+            ));
+        });
+    }
+}
@@ -13,6 +13,8 @@
 
 use std::fmt;
 
+use crate::cpu::Mode;
+
 const ENABLED_STM_BIT: u8 = 0x01;
 const ENABLED_MODULATOR_BIT: u8 = 0x02;
 const ENABLED_SILENCER_BIT: u8 = 0x04;
@@ -40,6 +42,10 @@ impl FirmwareInfo {
         }
     }
 
+    pub fn idx(&self) -> usize {
+        self.idx
+    }
+
     pub fn cpu_version(&self) -> String {
         Self::firmware_version_map(self.cpu_version_number)
     }
@@ -64,6 +70,39 @@ impl FirmwareInfo {
         (self.fpga_function_bits & ENABLED_MOD_DELAY_BIT) == ENABLED_MOD_DELAY_BIT
     }
 
+    /// Modes of GainSTM supported by the firmware
+    ///
+    /// Every [Mode] is encoded in the GainSTM body of the protocol this driver speaks, so the modes are limited only by whether the FPGA reports STM as enabled.
+    /// Note that [Mode::PhaseHalf] is additionally unavailable in Normal mode regardless of the firmware.
+    pub fn supported_gain_modes(&self) -> Vec<Mode> {
+        if !self.stm_enabled() {
+            return vec![];
+        }
+        vec![Mode::PhaseDutyFull, Mode::PhaseFull, Mode::PhaseHalf]
+    }
+
+    /// Whether the CPU and FPGA firmware versions are the same
+    pub fn matches_version(&self) -> bool {
+        self.cpu_version_number == self.fpga_version_number
+    }
+
+    /// Indices of the devices whose CPU and FPGA versions differ from each other or from those of the first device
+    pub fn mismatched_devices(infos: &[FirmwareInfo]) -> Vec<usize> {
+        let first = match infos.first() {
+            Some(first) => first,
+            None => return vec![],
+        };
+        infos
+            .iter()
+            .filter(|info| {
+                !info.matches_version()
+                    || info.cpu_version_number != first.cpu_version_number
+                    || info.fpga_version_number != first.fpga_version_number
+            })
+            .map(|info| info.idx)
+            .collect()
+    }
+
     fn firmware_version_map(version_number: u8) -> String {
         match version_number {
             0 => "older than v0.4".to_string(),
@@ -91,3 +130,21 @@ impl fmt::Display for FirmwareInfo {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn supported_gain_modes_of_v2_3() {
+        let info = FirmwareInfo::new(0, 0x83, 0x83, ENABLED_STM_BIT | ENABLED_MODULATOR_BIT);
+        assert_eq!(info.cpu_version(), "v2.3");
+        assert_eq!(
+            info.supported_gain_modes(),
+            vec![Mode::PhaseDutyFull, Mode::PhaseFull, Mode::PhaseHalf]
+        );
+
+        let info = FirmwareInfo::new(0, 0x83, 0x83, ENABLED_MODULATOR_BIT);
+        assert!(info.supported_gain_modes().is_empty());
+    }
+}
@@ -21,6 +21,15 @@ pub const MOD_SAMPLING_FREQ_DIV_MIN: u32 = 1160;
 pub const MOD_BUF_SIZE_MAX: usize = 65536;
 
 pub const POINT_STM_FIXED_NUM_UNIT: f64 = 0.025; //mm
+/// Bit offset of the duty shift in the last word of a control point packed by [SeqFocus](crate::SeqFocus)
+pub(crate) const POINT_STM_DUTY_SHIFT_OFFSET: u16 = 6;
+/// Bit mask of the duty shift in the last word of a control point packed by [SeqFocus](crate::SeqFocus), i.e., bits 6 to 13
+pub(crate) const POINT_STM_DUTY_SHIFT_MASK: u16 = 0x3FC0;
+/// Maximum duty shift of a control point of PointSTM. The duty ratio is halved for each shift, i.e., 2^-shift of the maximum.
+///
+/// The duty shift occupies the 8 bits of [POINT_STM_DUTY_SHIFT_MASK] in [SeqFocus](crate::SeqFocus), so every `u8` is representable.
+pub const POINT_STM_DUTY_SHIFT_MAX: u8 =
+    (POINT_STM_DUTY_SHIFT_MASK >> POINT_STM_DUTY_SHIFT_OFFSET) as u8;
 
 pub const STM_SAMPLING_FREQ_DIV_MIN: u32 = 1612;
 pub const POINT_STM_BUF_SIZE_MAX: usize = 65536;
@@ -62,6 +71,13 @@ impl LegacyDrive {
         self.duty = (510.0 * d.amp.asin() / PI).round() as u8;
         self.phase = Self::to_phase(d);
     }
+
+    /// Decode into phase and amplitude in the same units as [Drive], i.e., phase normalized by 2π and amplitude in [0, 1].
+    pub fn to_phase_amp(&self) -> (f64, f64) {
+        let phase = self.phase as f64 / 256.0;
+        let amp = (self.duty as f64 * PI / 510.0).sin();
+        (phase, amp)
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -99,3 +115,33 @@ impl FPGAInfo {
         (self.info & 0x01) != 0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legacy_drive_to_phase_amp() {
+        let mut legacy = LegacyDrive { phase: 0, duty: 0 };
+        [(0.0, 0.0), (0.25, 0.5), (0.5, 1.0), (0.75, 0.3)]
+            .into_iter()
+            .for_each(|(phase, amp)| {
+                legacy.set(&Drive {
+                    phase,
+                    amp,
+                    cycle: 4096,
+                });
+                let (p, a) = legacy.to_phase_amp();
+                assert!((p - phase).abs() < 1.0 / 256.0);
+                assert!((a - amp).abs() < 1e-2);
+            });
+
+        let max = LegacyDrive {
+            phase: 0xFF,
+            duty: 0xFF,
+        };
+        let (p, a) = max.to_phase_amp();
+        assert_eq!(p, 255.0 / 256.0);
+        assert!((a - 1.0).abs() < 1e-4);
+    }
+}
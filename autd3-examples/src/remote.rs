@@ -0,0 +1,34 @@
+/*
+ * File: remote.rs
+ * Project: src
+ * Created Date: 15/10/2022
+ * Author: Shun Suzuki
+ * -----
+ * Last Modified: 15/10/2022
+ * Modified By: Shun Suzuki (suzuki@hapis.k.u-tokyo.ac.jp)
+ * -----
+ * Copyright (c) 2022 Shun Suzuki. All rights reserved.
+ *
+ */
+
+mod test_runner;
+mod tests;
+
+use anyhow::Result;
+
+use autd3::prelude::*;
+use autd3_link_remote::Remote;
+
+fn main() -> Result<()> {
+    let mut geometry = GeometryBuilder::new().legacy_mode().build();
+    geometry.add_device(Vector3::zeros(), Vector3::zeros());
+    geometry.add_device(Vector3::new(DEVICE_WIDTH, 0.0, 0.0), Vector3::zeros());
+
+    let link = Remote::new("127.0.0.1:50633".parse()?, 2);
+
+    let autd = Controller::open(geometry, link).expect("Failed to open");
+
+    run!(autd);
+
+    Ok(())
+}
@@ -0,0 +1,55 @@
+/*
+ * File: gpu.rs
+ * Project: benches
+ * Created Date: 15/10/2022
+ * Author: Shun Suzuki
+ * -----
+ * Last Modified: 15/10/2022
+ * Modified By: Shun Suzuki (suzuki@hapis.k.u-tokyo.ac.jp)
+ * -----
+ * Copyright (c) 2022 Shun Suzuki. All rights reserved.
+ *
+ */
+
+use std::time::Instant;
+
+use autd3_gain_holo::{Backend, Complex, GpuBackend, MatrixXc, NalgebraBackend, Transpose};
+
+const NUM_TRANSDUCERS: usize = 256;
+const NUM_FOCI: usize = 100;
+const ITERATIONS: usize = 100;
+
+fn bench<B: Backend>(name: &str, g: &MatrixXc) {
+    let mut c = MatrixXc::zeros(NUM_FOCI, NUM_FOCI);
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        B::matrix_mul(
+            Transpose::NoTrans,
+            Transpose::ConjTrans,
+            Complex::new(1., 0.),
+            g,
+            g,
+            Complex::new(0., 0.),
+            &mut c,
+        );
+    }
+    println!(
+        "{}: {:?} per multiplication",
+        name,
+        start.elapsed() / ITERATIONS as u32
+    );
+}
+
+fn main() {
+    if let Err(e) = GpuBackend::new() {
+        eprintln!("{}", e);
+        return;
+    }
+
+    let g = MatrixXc::from_fn(NUM_FOCI, NUM_TRANSDUCERS, |i, j| {
+        Complex::from_polar(1., (i * NUM_TRANSDUCERS + j) as f64 * 0.1)
+    });
+
+    bench::<NalgebraBackend>("CPU", &g);
+    bench::<GpuBackend>("GPU", &g);
+}
@@ -0,0 +1,53 @@
+/*
+ * File: propagation_matrix.rs
+ * Project: benches
+ * Created Date: 15/10/2022
+ * Author: Shun Suzuki
+ * -----
+ * Last Modified: 15/10/2022
+ * Modified By: Shun Suzuki (suzuki@hapis.k.u-tokyo.ac.jp)
+ * -----
+ * Copyright (c) 2022 Shun Suzuki. All rights reserved.
+ *
+ */
+
+use std::time::Instant;
+
+use autd3_core::geometry::{GeometryBuilder, Vector3};
+use autd3_gain_holo::generate_propagation_matrix;
+
+const NUM_DEVICES_X: usize = 3;
+const NUM_DEVICES_Y: usize = 3;
+const NUM_FOCI: usize = 50;
+const ITERATIONS: usize = 100;
+
+// Run with and without the `parallel` feature to compare
+fn main() {
+    let mut geometry = GeometryBuilder::new().legacy_mode().build();
+    for y in 0..NUM_DEVICES_Y {
+        for x in 0..NUM_DEVICES_X {
+            geometry.add_device(
+                Vector3::new(192. * x as f64, 151.4 * y as f64, 0.),
+                Vector3::zeros(),
+            );
+        }
+    }
+    let center = geometry.center();
+    let foci = (0..NUM_FOCI)
+        .map(|i| {
+            let theta = 2.0 * std::f64::consts::PI * i as f64 / NUM_FOCI as f64;
+            center + Vector3::new(50. * theta.cos(), 50. * theta.sin(), 150.)
+        })
+        .collect::<Vec<_>>();
+
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        std::hint::black_box(generate_propagation_matrix(&geometry, &foci));
+    }
+    println!(
+        "{} foci x {} transducers: {:?} per generation",
+        NUM_FOCI,
+        geometry.num_transducers(),
+        start.elapsed() / ITERATIONS as u32
+    );
+}
@@ -20,6 +20,10 @@ pub type MatrixX = Matrix<f64, Dynamic, Dynamic, VecStorage<f64, Dynamic, Dynami
 pub type VectorXc = Matrix<Complex, Dynamic, U1, VecStorage<Complex, Dynamic, U1>>;
 pub type VectorX = Matrix<f64, Dynamic, U1, VecStorage<f64, Dynamic, U1>>;
 
+pub type Complex32 = nalgebra::Complex<f32>;
+pub type MatrixXc32 = Matrix<Complex32, Dynamic, Dynamic, VecStorage<Complex32, Dynamic, Dynamic>>;
+pub type VectorXc32 = Matrix<Complex32, Dynamic, U1, VecStorage<Complex32, Dynamic, U1>>;
+
 pub enum Transpose {
     NoTrans = 111,
     Trans = 112,
@@ -28,9 +32,23 @@ pub enum Transpose {
 }
 
 pub trait Backend {
+    /// Whether the gains supporting it, e.g., [GSPAT](crate::GSPAT), store their large matrices in single precision
+    const SINGLE_PRECISION: bool = false;
+
     fn hadamard_product(a: &MatrixXc, b: &MatrixXc, c: &mut MatrixXc);
     fn real(a: &MatrixXc, b: &mut MatrixX);
     fn imag(a: &VectorXc, b: &mut VectorX);
+    /// Singular value decomposition `matrix = U diag(S) V^H`, returning `(U, S, V^H)` in the thin form
+    ///
+    /// The default implementation computes it with nalgebra on the CPU.
+    fn svd(matrix: &MatrixXc) -> (MatrixXc, VectorX, MatrixXc) {
+        let svd = matrix.clone().svd(true, true);
+        match (svd.u, svd.v_t) {
+            (Some(u), Some(v_t)) => (u, svd.singular_values, v_t),
+            _ => unreachable!(),
+        }
+    }
+
     fn pseudo_inverse_svd(matrix: MatrixXc, alpha: f64, result: &mut MatrixXc);
     fn max_eigen_vector(matrix: MatrixXc) -> VectorXc;
     fn matrix_add(alpha: f64, a: &MatrixX, beta: f64, b: &mut MatrixX);
@@ -78,15 +96,10 @@ impl Backend for NalgebraBackend {
     }
 
     fn pseudo_inverse_svd(matrix: MatrixXc, alpha: f64, result: &mut MatrixXc) {
-        let svd = matrix.svd(true, true);
-        let s_inv = MatrixXc::from_diagonal(
-            &svd.singular_values
-                .map(|s| Complex::new(s / (s * s + alpha * alpha), 0.)),
-        );
-        *result = match (&svd.v_t, &svd.u) {
-            (Some(v_t), Some(u)) => v_t.adjoint() * s_inv * u.adjoint(),
-            _ => unreachable!(),
-        };
+        let (u, s, v_t) = Self::svd(&matrix);
+        let s_inv =
+            MatrixXc::from_diagonal(&s.map(|s| Complex::new(s / (s * s + alpha * alpha), 0.)));
+        *result = v_t.adjoint() * s_inv * u.adjoint();
     }
 
     fn max_eigen_vector(matrix: MatrixXc) -> VectorXc {
@@ -222,3 +235,91 @@ impl Backend for NalgebraBackend {
         new_mat
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NalgebraF32Backend;
+
+    fn c(re: f64, im: f64) -> Complex {
+        Complex::new(re, im)
+    }
+
+    // A = [[1, i], [0, 1], [1, 0]], whose pseudo-inverse (A^H A)^-1 A^H is 1/3 [[1, -i, 2], [-i, 2, i]]
+    fn known_matrix() -> (MatrixXc, MatrixXc) {
+        let a = MatrixXc::from_row_slice(
+            3,
+            2,
+            &[
+                c(1., 0.),
+                c(0., 1.),
+                c(0., 0.),
+                c(1., 0.),
+                c(1., 0.),
+                c(0., 0.),
+            ],
+        );
+        let pinv = MatrixXc::from_row_slice(
+            2,
+            3,
+            &[
+                c(1., 0.),
+                c(0., -1.),
+                c(2., 0.),
+                c(0., -1.),
+                c(2., 0.),
+                c(0., 1.),
+            ],
+        ) / c(3., 0.);
+        (a, pinv)
+    }
+
+    fn assert_near(a: &MatrixXc, b: &MatrixXc) {
+        assert_eq!(a.shape(), b.shape());
+        assert!((a - b).norm() < 1e-9, "{} != {}", a, b);
+    }
+
+    fn check_svd<B: Backend>() {
+        let (a, _) = known_matrix();
+        let (u, s, v_t) = B::svd(&a);
+        assert_eq!((u.shape(), s.len(), v_t.shape()), ((3, 2), 2, (2, 2)));
+        let s = MatrixXc::from_diagonal(&s.map(|s| c(s, 0.)));
+        assert_near(&(u * s * v_t), &a);
+    }
+
+    fn check_pseudo_inverse<B: Backend>() {
+        let (a, expected) = known_matrix();
+        let mut pinv = MatrixXc::zeros(0, 0);
+        B::pseudo_inverse_svd(a.clone(), 0., &mut pinv);
+        assert_near(&pinv, &expected);
+        assert_near(&(&a * &pinv * &a), &a);
+
+        // Tikhonov regularization: (A^H A + alpha^2 I)^-1 A^H
+        let alpha = 0.5;
+        let reg = (a.adjoint() * &a + MatrixXc::identity(2, 2) * c(alpha * alpha, 0.))
+            .try_inverse()
+            .unwrap()
+            * a.adjoint();
+        B::pseudo_inverse_svd(a, alpha, &mut pinv);
+        assert_near(&pinv, &reg);
+    }
+
+    #[test]
+    fn svd_nalgebra() {
+        check_svd::<NalgebraBackend>();
+        check_svd::<NalgebraF32Backend>();
+    }
+
+    #[test]
+    fn pseudo_inverse_nalgebra() {
+        check_pseudo_inverse::<NalgebraBackend>();
+        check_pseudo_inverse::<NalgebraF32Backend>();
+    }
+
+    #[cfg(feature = "gpu")]
+    #[test]
+    fn pseudo_inverse_gpu() {
+        check_svd::<crate::GpuBackend>();
+        check_pseudo_inverse::<crate::GpuBackend>();
+    }
+}
@@ -13,7 +13,11 @@
 
 use std::f64::consts::PI;
 
-use crate::{constraint::Constraint, macros::propagate, Complex};
+use crate::{
+    constraint::Constraint,
+    macros::{pin_phase, propagate, single_focus},
+    Complex,
+};
 use anyhow::Result;
 use autd3_core::{
     gain::{Gain, GainProps, IGain},
@@ -31,6 +35,7 @@ pub struct Greedy<T: Transducer, C: Constraint> {
     amps: Vec<f64>,
     phase_candidates: Vec<Complex>,
     constraint: C,
+    phase_reference: Option<usize>,
 }
 
 impl<T: Transducer, C: Constraint> Greedy<T, C> {
@@ -38,6 +43,12 @@ impl<T: Transducer, C: Constraint> Greedy<T, C> {
         Self::with_param(foci, amps, constraint, 16)
     }
 
+    /// constructor from pairs of a focus and its amplitude
+    pub fn from_targets<I: IntoIterator<Item = (Vector3, f64)>>(targets: I, constraint: C) -> Self {
+        let (foci, amps) = targets.into_iter().unzip();
+        Self::new(foci, amps, constraint)
+    }
+
     pub fn with_param(foci: Vec<Vector3>, amps: Vec<f64>, constraint: C, phase_div: usize) -> Self {
         assert!(foci.len() == amps.len());
         let mut phase_candidates = Vec::with_capacity(phase_div);
@@ -50,12 +61,35 @@ impl<T: Transducer, C: Constraint> Greedy<T, C> {
             amps,
             phase_candidates,
             constraint,
+            phase_reference: None,
+        }
+    }
+
+    /// Shift the phase of the solution so that the field at the `idx`-th focus has zero phase.
+    ///
+    /// Since the absolute phase does not affect the amplitudes, this makes the phase of the field deterministic across runs.
+    pub fn with_phase_reference(self, idx: usize) -> Self {
+        assert!(idx < self.foci.len());
+        Self {
+            phase_reference: Some(idx),
+            ..self
         }
     }
 }
 
 impl<T: Transducer, C: Constraint> IGain<T> for Greedy<T, C> {
     fn calc(&mut self, geometry: &Geometry<T>) -> Result<()> {
+        if self.foci.len() == 1 {
+            single_focus(
+                &mut self.props.drives,
+                geometry,
+                self.foci[0],
+                self.amps[0],
+                &self.constraint,
+            );
+            return Ok(());
+        }
+
         let m = self.foci.len();
 
         let attenuation = geometry.attenuation;
@@ -116,6 +150,11 @@ impl<T: Transducer, C: Constraint> IGain<T> for Greedy<T, C> {
             self.props.drives[trans.id()].amp = amp;
             self.props.drives[trans.id()].phase = phase;
         });
+
+        if let Some(idx) = self.phase_reference {
+            pin_phase(&mut self.props.drives, geometry, self.foci[idx]);
+        }
+
         Ok(())
     }
 }
@@ -47,10 +47,29 @@ impl Constraint for Uniform {
     }
 }
 
-pub struct Clamp {}
+/// Clamp the amplitude of each transducer to [0, `max`]
+///
+/// [Default] clamps to [0, 1].
+pub struct Clamp {
+    max: f64,
+}
+
+impl Clamp {
+    pub fn new(max: f64) -> Self {
+        Self {
+            max: max.clamp(0.0, 1.0),
+        }
+    }
+}
+
+impl Default for Clamp {
+    fn default() -> Self {
+        Self::new(1.0)
+    }
+}
 
 impl Constraint for Clamp {
     fn convert(&self, v: f64, _max: f64) -> f64 {
-        v.clamp(0.0, 1.0)
+        v.clamp(0.0, self.max)
     }
 }
@@ -0,0 +1,40 @@
+/*
+ * File: directivity.rs
+ * Project: src
+ * Created Date: 15/10/2022
+ * Author: Shun Suzuki
+ * -----
+ * Last Modified: 15/10/2022
+ * Modified By: Shun Suzuki (suzuki@hapis.k.u-tokyo.ac.jp)
+ * -----
+ * Copyright (c) 2022 Shun Suzuki. All rights reserved.
+ *
+ */
+
+use autd3_core::utils::directivity_t4010a1;
+
+/// Directivity of transducers used in the propagation model
+pub trait Directivity {
+    /// Relative amplitude at `theta` radians from the normal of the transducer
+    fn gain(theta: f64) -> f64;
+}
+
+/// Emitting equally in all directions
+pub struct Omnidirectional {}
+
+impl Directivity for Omnidirectional {
+    fn gain(_theta: f64) -> f64 {
+        1.0
+    }
+}
+
+/// Directivity of T4010A1 (Nippon Ceramic), the transducer mounted on AUTD3
+///
+/// The angle is passed to [directivity_t4010a1] as is, as the propagation model has always done.
+pub struct T4010A1 {}
+
+impl Directivity for T4010A1 {
+    fn gain(theta: f64) -> f64 {
+        directivity_t4010a1(theta)
+    }
+}
@@ -17,4 +17,14 @@ use thiserror::Error;
 pub enum HoloError {
     #[error("Failed to solve linear system")]
     SolveFailed,
+    #[error("Requested total amplitude ({requested}) exceeds the estimated capacity of the array ({max})")]
+    Infeasible { requested: f64, max: f64 },
+    #[error("Invalid image: {0}")]
+    InvalidImage(String),
+    #[error("Invalid plane: {0}")]
+    InvalidPlane(String),
+    #[error("GPU is not available: {0}")]
+    GpuUnavailable(String),
+    #[error("Solver panicked: {0}")]
+    SolverPanicked(String),
 }
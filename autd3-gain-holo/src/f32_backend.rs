@@ -0,0 +1,140 @@
+/*
+ * File: f32_backend.rs
+ * Project: src
+ * Created Date: 15/10/2022
+ * Author: Shun Suzuki
+ * -----
+ * Last Modified: 15/10/2022
+ * Modified By: Shun Suzuki (suzuki@hapis.k.u-tokyo.ac.jp)
+ * -----
+ * Copyright (c) 2022 Shun Suzuki. All rights reserved.
+ *
+ */
+
+use crate::{
+    Backend, Complex, Complex32, MatrixX, MatrixXc, MatrixXc32, NalgebraBackend, Transpose,
+    VectorX, VectorXc, VectorXc32,
+};
+
+fn to_f32(m: &MatrixXc) -> MatrixXc32 {
+    m.map(|c| Complex32::new(c.re as f32, c.im as f32))
+}
+
+fn to_f32_vec(v: &VectorXc) -> VectorXc32 {
+    v.map(|c| Complex32::new(c.re as f32, c.im as f32))
+}
+
+fn to_f32_scalar(c: Complex) -> Complex32 {
+    Complex32::new(c.re as f32, c.im as f32)
+}
+
+fn op(trans: Transpose, m: MatrixXc32) -> MatrixXc32 {
+    match trans {
+        Transpose::NoTrans => m,
+        Transpose::Trans => m.transpose(),
+        Transpose::ConjTrans => m.adjoint(),
+        Transpose::ConjNoTrans => m.conjugate(),
+    }
+}
+
+/// Backend computing in single precision on CPU
+///
+/// [GSPAT](crate::GSPAT) stores the propagation matrix and its backward matrix in `f32` with this backend, which halves the memory used by them.
+///
+/// Through the [Backend] interface, the matrices are stored in `f64`.
+/// [hadamard_product](Backend::hadamard_product), [matrix_mul](Backend::matrix_mul), and [matrix_mul_vec](Backend::matrix_mul_vec) copy their operands into `f32` to compute, which rather increases the memory usage temporarily, and the other operations, e.g., decompositions and solvers, are delegated to [NalgebraBackend].
+/// Hence, for the other gains, this only shows the accuracy of the computation in single precision.
+pub struct NalgebraF32Backend {}
+
+impl Backend for NalgebraF32Backend {
+    const SINGLE_PRECISION: bool = true;
+
+    fn hadamard_product(a: &MatrixXc, b: &MatrixXc, c: &mut MatrixXc) {
+        *c = to_f32(a)
+            .component_mul(&to_f32(b))
+            .map(|x| Complex::new(x.re as f64, x.im as f64));
+    }
+
+    fn real(a: &MatrixXc, b: &mut MatrixX) {
+        NalgebraBackend::real(a, b)
+    }
+
+    fn imag(a: &VectorXc, b: &mut VectorX) {
+        NalgebraBackend::imag(a, b)
+    }
+
+    fn pseudo_inverse_svd(matrix: MatrixXc, alpha: f64, result: &mut MatrixXc) {
+        NalgebraBackend::pseudo_inverse_svd(matrix, alpha, result)
+    }
+
+    fn max_eigen_vector(matrix: MatrixXc) -> VectorXc {
+        NalgebraBackend::max_eigen_vector(matrix)
+    }
+
+    fn matrix_add(alpha: f64, a: &MatrixX, beta: f64, b: &mut MatrixX) {
+        NalgebraBackend::matrix_add(alpha, a, beta, b)
+    }
+
+    fn matrix_mul(
+        trans_a: Transpose,
+        trans_b: Transpose,
+        alpha: Complex,
+        a: &MatrixXc,
+        b: &MatrixXc,
+        beta: Complex,
+        c: &mut MatrixXc,
+    ) {
+        let ab = op(trans_a, to_f32(a)) * op(trans_b, to_f32(b));
+        let r = ab * to_f32_scalar(alpha) + to_f32(c) * to_f32_scalar(beta);
+        *c = r.map(|x| Complex::new(x.re as f64, x.im as f64));
+    }
+
+    fn matrix_mul_vec(
+        trans_a: Transpose,
+        alpha: Complex,
+        a: &MatrixXc,
+        b: &VectorXc,
+        beta: Complex,
+        c: &mut VectorXc,
+    ) {
+        let ab = op(trans_a, to_f32(a)) * to_f32_vec(b);
+        let r = ab * to_f32_scalar(alpha) + to_f32_vec(c) * to_f32_scalar(beta);
+        *c = r.map(|x| Complex::new(x.re as f64, x.im as f64));
+    }
+
+    fn vector_add(alpha: f64, a: &VectorX, b: &mut VectorX) {
+        NalgebraBackend::vector_add(alpha, a, b)
+    }
+
+    fn solve_ch(a: MatrixXc, b: &mut VectorXc) -> bool {
+        NalgebraBackend::solve_ch(a, b)
+    }
+
+    fn solve_g(a: MatrixX, b: &mut VectorX) -> bool {
+        NalgebraBackend::solve_g(a, b)
+    }
+
+    fn dot(a: &VectorX, b: &VectorX) -> f64 {
+        NalgebraBackend::dot(a, b)
+    }
+
+    fn dot_c(a: &VectorXc, b: &VectorXc) -> Complex {
+        NalgebraBackend::dot_c(a, b)
+    }
+
+    fn max_coefficient(a: &VectorX) -> f64 {
+        NalgebraBackend::max_coefficient(a)
+    }
+
+    fn max_coefficient_c(a: &VectorXc) -> f64 {
+        NalgebraBackend::max_coefficient_c(a)
+    }
+
+    fn concat_row(a: MatrixXc, b: &MatrixXc) -> MatrixXc {
+        NalgebraBackend::concat_row(a, b)
+    }
+
+    fn concat_col(a: MatrixXc, b: &MatrixXc) -> MatrixXc {
+        NalgebraBackend::concat_col(a, b)
+    }
+}
@@ -0,0 +1,146 @@
+/*
+ * File: field.rs
+ * Project: src
+ * Created Date: 15/10/2022
+ * Author: Shun Suzuki
+ * -----
+ * Last Modified: 15/10/2022
+ * Modified By: Shun Suzuki (suzuki@hapis.k.u-tokyo.ac.jp)
+ * -----
+ * Copyright (c) 2022 Shun Suzuki. All rights reserved.
+ *
+ */
+
+use std::f64::consts::PI;
+
+use autd3_core::{
+    geometry::{Geometry, Transducer, Vector3},
+    Drive,
+};
+
+use crate::{macros::propagate, Complex};
+
+/// Evaluator of the acoustic field produced by drives
+///
+/// By default, the field is evaluated in the free field.
+pub struct FieldEvaluator<'a, T: Transducer> {
+    geometry: &'a Geometry<T>,
+    ground_plane: Option<(f64, f64)>,
+}
+
+impl<'a, T: Transducer> FieldEvaluator<'a, T> {
+    pub fn new(geometry: &'a Geometry<T>) -> Self {
+        Self {
+            geometry,
+            ground_plane: None,
+        }
+    }
+
+    /// Add a reflecting plane parallel to the xy-plane, modeled by image sources
+    ///
+    /// # Arguments
+    ///
+    /// * `z` - z-coordinate of the plane
+    /// * `reflection` - Pressure reflection coefficient of the plane, from 0 (absorbing) to 1 (rigid)
+    ///
+    pub fn with_ground_plane(self, z: f64, reflection: f64) -> Self {
+        Self {
+            ground_plane: Some((z, reflection)),
+            ..self
+        }
+    }
+
+    /// Complex pressure at each point
+    pub fn eval(&self, drives: &[Drive], points: &[Vector3]) -> Vec<Complex> {
+        let sound_speed = self.geometry.sound_speed();
+        let attenuation = self.geometry.attenuation;
+        points
+            .iter()
+            .map(|&p| {
+                self.geometry
+                    .transducers()
+                    .map(|tr| {
+                        let d = &drives[tr.id()];
+                        let q = Complex::from_polar(d.amp, 2.0 * PI * (d.phase - 0.5));
+                        let wavenum = tr.wavenumber(sound_speed);
+                        let pos = tr.position();
+                        let dir = tr.z_direction();
+                        let direct = propagate(pos, dir, attenuation, wavenum, p);
+                        let reflected = match self.ground_plane {
+                            Some((z, reflection)) => {
+                                let image_pos = Vector3::new(pos.x, pos.y, 2.0 * z - pos.z);
+                                let image_dir = Vector3::new(dir.x, dir.y, -dir.z);
+                                propagate(&image_pos, &image_dir, attenuation, wavenum, p)
+                                    * reflection
+                            }
+                            None => Complex::new(0., 0.),
+                        };
+                        q * (direct + reflected)
+                    })
+                    .sum()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use autd3_core::geometry::{GeometryBuilder, LegacyTransducer};
+
+    use super::*;
+
+    fn setup() -> (Geometry<LegacyTransducer>, Vec<Drive>) {
+        let mut geometry = GeometryBuilder::new().legacy_mode().build();
+        geometry.add_device(Vector3::zeros(), Vector3::zeros());
+        let drives = geometry
+            .transducers()
+            .map(|tr| Drive {
+                phase: (tr.id() as f64 * 0.37).fract(),
+                amp: 1.0,
+                cycle: tr.cycle(),
+            })
+            .collect();
+        (geometry, drives)
+    }
+
+    #[test]
+    fn absorbing_ground_plane_is_free_field() {
+        let (geometry, drives) = setup();
+        let points = [
+            geometry.center() + Vector3::new(0., 0., 150.),
+            geometry.center() + Vector3::new(30., -20., 250.),
+        ];
+
+        let free = FieldEvaluator::new(&geometry).eval(&drives, &points);
+        let absorbing = FieldEvaluator::new(&geometry)
+            .with_ground_plane(300., 0.)
+            .eval(&drives, &points);
+        free.iter()
+            .zip(absorbing.iter())
+            .for_each(|(a, b)| assert_eq!(a, b));
+    }
+
+    #[test]
+    fn rigid_ground_plane_doubles_pressure_on_plane() {
+        const Z: f64 = 300.;
+        let (geometry, drives) = setup();
+        let points = [
+            Vector3::new(geometry.center().x, geometry.center().y, Z),
+            Vector3::new(30., -20., Z),
+        ];
+
+        let free = FieldEvaluator::new(&geometry).eval(&drives, &points);
+        let rigid = FieldEvaluator::new(&geometry)
+            .with_ground_plane(Z, 1.)
+            .eval(&drives, &points);
+        let half = FieldEvaluator::new(&geometry)
+            .with_ground_plane(Z, 0.5)
+            .eval(&drives, &points);
+        free.iter()
+            .zip(rigid.iter().zip(half.iter()))
+            .for_each(|(f, (r, h))| {
+                assert!((r - f * 2.).norm() < 1e-9 * f.norm());
+                assert!((h - f * 1.5).norm() < 1e-9 * f.norm());
+            });
+    }
+}
@@ -0,0 +1,522 @@
+/*
+ * File: gpu_backend.rs
+ * Project: src
+ * Created Date: 15/10/2022
+ * Author: Shun Suzuki
+ * -----
+ * Last Modified: 15/10/2022
+ * Modified By: Shun Suzuki (suzuki@hapis.k.u-tokyo.ac.jp)
+ * -----
+ * Copyright (c) 2022 Shun Suzuki. All rights reserved.
+ *
+ */
+
+use std::sync::{mpsc::channel, OnceLock};
+
+use anyhow::Result;
+use wgpu::util::DeviceExt;
+
+use crate::{
+    error::HoloError, Backend, Complex, MatrixX, MatrixXc, NalgebraBackend, Transpose, VectorX,
+    VectorXc,
+};
+
+const SHADER: &str = r#"
+struct Params {
+    m: u32,
+    n: u32,
+    k: u32,
+    trans_a: u32,
+    trans_b: u32,
+    lda: u32,
+    ldb: u32,
+    _pad: u32,
+    alpha: vec2<f32>,
+    beta: vec2<f32>,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> a: array<vec2<f32>>;
+@group(0) @binding(2) var<storage, read> b: array<vec2<f32>>;
+@group(0) @binding(3) var<storage, read_write> c: array<vec2<f32>>;
+
+fn cmul(x: vec2<f32>, y: vec2<f32>) -> vec2<f32> {
+    return vec2<f32>(x.x * y.x - x.y * y.y, x.x * y.y + x.y * y.x);
+}
+
+// trans: 0 = NoTrans, 1 = Trans, 2 = ConjTrans, 3 = ConjNoTrans
+fn load(trans: u32, ld: u32, row: u32, col: u32, is_a: bool) -> vec2<f32> {
+    var idx: u32;
+    if (trans == 0u || trans == 3u) {
+        idx = row + col * ld;
+    } else {
+        idx = col + row * ld;
+    }
+    var v: vec2<f32>;
+    if (is_a) {
+        v = a[idx];
+    } else {
+        v = b[idx];
+    }
+    if (trans >= 2u) {
+        v.y = -v.y;
+    }
+    return v;
+}
+
+@compute @workgroup_size(8, 8)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x;
+    let j = id.y;
+    if (i >= params.m || j >= params.n) {
+        return;
+    }
+    var acc = vec2<f32>(0.0, 0.0);
+    for (var l = 0u; l < params.k; l = l + 1u) {
+        acc = acc + cmul(load(params.trans_a, params.lda, i, l, true), load(params.trans_b, params.ldb, l, j, false));
+    }
+    let idx = i + j * params.m;
+    c[idx] = cmul(params.alpha, acc) + cmul(params.beta, c[idx]);
+}
+"#;
+
+const WORKGROUP_SIZE: u32 = 8;
+
+struct Context {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::ComputePipeline,
+}
+
+static CONTEXT: OnceLock<Result<Context, String>> = OnceLock::new();
+
+impl Context {
+    fn new() -> Result<Self, String> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            compatible_surface: None,
+            force_fallback_adapter: false,
+        }))
+        .ok_or_else(|| "no adapter found".to_owned())?;
+        let (device, queue) = pollster::block_on(adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                label: None,
+                required_features: wgpu::Features::empty(),
+                required_limits: wgpu::Limits::downlevel_defaults(),
+            },
+            None,
+        ))
+        .map_err(|e| e.to_string())?;
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: None,
+            layout: None,
+            module: &module,
+            entry_point: "main",
+        });
+        Ok(Self {
+            device,
+            queue,
+            pipeline,
+        })
+    }
+
+    fn init() -> &'static Result<Context, String> {
+        CONTEXT.get_or_init(Self::new)
+    }
+
+    fn get() -> Option<&'static Context> {
+        Self::init().as_ref().ok()
+    }
+
+    fn to_f32(v: &[Complex]) -> Vec<f32> {
+        v.iter().flat_map(|c| [c.re as f32, c.im as f32]).collect()
+    }
+
+    fn trans_code(trans: &Transpose) -> u32 {
+        match trans {
+            Transpose::NoTrans => 0,
+            Transpose::Trans => 1,
+            Transpose::ConjTrans => 2,
+            Transpose::ConjNoTrans => 3,
+        }
+    }
+
+    // c (m x n) = alpha * op(a) * op(b) + beta * c, where all matrices are column-major
+    // Returns false, leaving c untouched, if the result cannot be read back from GPU.
+    #[allow(clippy::too_many_arguments)]
+    fn gemm(
+        &self,
+        trans_a: &Transpose,
+        trans_b: &Transpose,
+        (m, n, k): (usize, usize, usize),
+        alpha: Complex,
+        a: (&[Complex], usize),
+        b: (&[Complex], usize),
+        beta: Complex,
+        c: &mut [Complex],
+    ) -> bool {
+        let params_u32 = [
+            m as u32,
+            n as u32,
+            k as u32,
+            Self::trans_code(trans_a),
+            Self::trans_code(trans_b),
+            a.1 as u32,
+            b.1 as u32,
+            0,
+        ];
+        let params_f32 = [
+            alpha.re as f32,
+            alpha.im as f32,
+            beta.re as f32,
+            beta.im as f32,
+        ];
+        let mut params = bytemuck::cast_slice::<u32, u8>(&params_u32).to_vec();
+        params.extend_from_slice(bytemuck::cast_slice(&params_f32));
+
+        let storage = |contents: &[f32], usage: wgpu::BufferUsages| {
+            self.device
+                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: None,
+                    contents: bytemuck::cast_slice(contents),
+                    usage,
+                })
+        };
+        let params_buf = self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: None,
+                contents: &params,
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+        let a_buf = storage(&Self::to_f32(a.0), wgpu::BufferUsages::STORAGE);
+        let b_buf = storage(&Self::to_f32(b.0), wgpu::BufferUsages::STORAGE);
+        let c_buf = storage(
+            &Self::to_f32(c),
+            wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        );
+        let size = c_buf.size();
+        let staging = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &self.pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: params_buf.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: a_buf.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: b_buf.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: c_buf.as_entire_binding(),
+                },
+            ],
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: None,
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(
+                (m as u32).div_ceil(WORKGROUP_SIZE),
+                (n as u32).div_ceil(WORKGROUP_SIZE),
+                1,
+            );
+        }
+        encoder.copy_buffer_to_buffer(&c_buf, 0, &staging, 0, size);
+        self.queue.submit(Some(encoder.finish()));
+
+        let slice = staging.slice(..);
+        let (tx, rx) = channel();
+        slice.map_async(wgpu::MapMode::Read, move |r| {
+            let _ = tx.send(r);
+        });
+        self.device.poll(wgpu::Maintain::Wait);
+        match rx.recv() {
+            Ok(Ok(())) => {
+                let data = slice.get_mapped_range();
+                bytemuck::cast_slice::<u8, f32>(&data)
+                    .chunks(2)
+                    .zip(c.iter_mut())
+                    .for_each(|(v, c)| *c = Complex::new(v[0] as f64, v[1] as f64));
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+fn op_shape(trans: &Transpose, rows: usize, cols: usize) -> (usize, usize) {
+    match trans {
+        Transpose::NoTrans | Transpose::ConjNoTrans => (rows, cols),
+        Transpose::Trans | Transpose::ConjTrans => (cols, rows),
+    }
+}
+
+/// Backend running matrix multiplications on GPU via wgpu
+///
+/// Only [matrix_mul](Backend::matrix_mul) and [matrix_mul_vec](Backend::matrix_mul_vec) run on GPU, and the other operations are delegated to [NalgebraBackend].
+/// The multiplications are computed in single precision, so that the results agree with [NalgebraBackend] only within the tolerance of `f32`.
+/// If no GPU is available, or the result of a multiplication cannot be read back from GPU, the operations fall back to [NalgebraBackend]. Use [new](GpuBackend::new) to check the availability in advance.
+pub struct GpuBackend {}
+
+impl GpuBackend {
+    /// Initialize GPU. Returns an error if no GPU is available.
+    pub fn new() -> Result<Self> {
+        match Context::init() {
+            Ok(_) => Ok(Self {}),
+            Err(e) => Err(HoloError::GpuUnavailable(e.clone()).into()),
+        }
+    }
+}
+
+impl Backend for GpuBackend {
+    fn hadamard_product(a: &MatrixXc, b: &MatrixXc, c: &mut MatrixXc) {
+        NalgebraBackend::hadamard_product(a, b, c)
+    }
+
+    fn real(a: &MatrixXc, b: &mut MatrixX) {
+        NalgebraBackend::real(a, b)
+    }
+
+    fn imag(a: &VectorXc, b: &mut VectorX) {
+        NalgebraBackend::imag(a, b)
+    }
+
+    fn pseudo_inverse_svd(matrix: MatrixXc, alpha: f64, result: &mut MatrixXc) {
+        NalgebraBackend::pseudo_inverse_svd(matrix, alpha, result)
+    }
+
+    fn max_eigen_vector(matrix: MatrixXc) -> VectorXc {
+        NalgebraBackend::max_eigen_vector(matrix)
+    }
+
+    fn matrix_add(alpha: f64, a: &MatrixX, beta: f64, b: &mut MatrixX) {
+        NalgebraBackend::matrix_add(alpha, a, beta, b)
+    }
+
+    fn matrix_mul(
+        trans_a: Transpose,
+        trans_b: Transpose,
+        alpha: Complex,
+        a: &MatrixXc,
+        b: &MatrixXc,
+        beta: Complex,
+        c: &mut MatrixXc,
+    ) {
+        let (m, k) = op_shape(&trans_a, a.nrows(), a.ncols());
+        let (_, n) = op_shape(&trans_b, b.nrows(), b.ncols());
+        let done = match Context::get() {
+            Some(ctx) if m * n * k > 0 => ctx.gemm(
+                &trans_a,
+                &trans_b,
+                (m, n, k),
+                alpha,
+                (a.as_slice(), a.nrows()),
+                (b.as_slice(), b.nrows()),
+                beta,
+                c.as_mut_slice(),
+            ),
+            _ => false,
+        };
+        if !done {
+            NalgebraBackend::matrix_mul(trans_a, trans_b, alpha, a, b, beta, c);
+        }
+    }
+
+    fn matrix_mul_vec(
+        trans_a: Transpose,
+        alpha: Complex,
+        a: &MatrixXc,
+        b: &VectorXc,
+        beta: Complex,
+        c: &mut VectorXc,
+    ) {
+        let (m, k) = op_shape(&trans_a, a.nrows(), a.ncols());
+        let done = match Context::get() {
+            Some(ctx) if m * k > 0 => ctx.gemm(
+                &trans_a,
+                &Transpose::NoTrans,
+                (m, 1, k),
+                alpha,
+                (a.as_slice(), a.nrows()),
+                (b.as_slice(), b.nrows()),
+                beta,
+                c.as_mut_slice(),
+            ),
+            _ => false,
+        };
+        if !done {
+            NalgebraBackend::matrix_mul_vec(trans_a, alpha, a, b, beta, c);
+        }
+    }
+
+    fn vector_add(alpha: f64, a: &VectorX, b: &mut VectorX) {
+        NalgebraBackend::vector_add(alpha, a, b)
+    }
+
+    fn solve_ch(a: MatrixXc, b: &mut VectorXc) -> bool {
+        NalgebraBackend::solve_ch(a, b)
+    }
+
+    fn solve_g(a: MatrixX, b: &mut VectorX) -> bool {
+        NalgebraBackend::solve_g(a, b)
+    }
+
+    fn dot(a: &VectorX, b: &VectorX) -> f64 {
+        NalgebraBackend::dot(a, b)
+    }
+
+    fn dot_c(a: &VectorXc, b: &VectorXc) -> Complex {
+        NalgebraBackend::dot_c(a, b)
+    }
+
+    fn max_coefficient(a: &VectorX) -> f64 {
+        NalgebraBackend::max_coefficient(a)
+    }
+
+    fn max_coefficient_c(a: &VectorXc) -> f64 {
+        NalgebraBackend::max_coefficient_c(a)
+    }
+
+    fn concat_row(a: MatrixXc, b: &MatrixXc) -> MatrixXc {
+        NalgebraBackend::concat_row(a, b)
+    }
+
+    fn concat_col(a: MatrixXc, b: &MatrixXc) -> MatrixXc {
+        NalgebraBackend::concat_col(a, b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use autd3_core::{
+        gain::Gain,
+        geometry::{GeometryBuilder, Vector3},
+    };
+
+    use super::*;
+    use crate::{FieldEvaluator, Naive, Normalize, GS, GSPAT};
+
+    fn random_matrix(rows: usize, cols: usize) -> MatrixXc {
+        use rand::Rng;
+        let mut rng = rand::thread_rng();
+        MatrixXc::from_fn(rows, cols, |_, _| {
+            Complex::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0))
+        })
+    }
+
+    fn assert_close(a: &[Complex], b: &[Complex]) {
+        a.iter()
+            .zip(b.iter())
+            .for_each(|(a, b)| assert!((a - b).norm() < 1e-4 * (1.0 + b.norm())));
+    }
+
+    fn trans(i: usize) -> Transpose {
+        match i {
+            0 => Transpose::NoTrans,
+            1 => Transpose::Trans,
+            2 => Transpose::ConjTrans,
+            _ => Transpose::ConjNoTrans,
+        }
+    }
+
+    #[test]
+    fn matrix_mul_same_as_nalgebra() {
+        let a = random_matrix(6, 6);
+        let b = random_matrix(6, 6);
+        let c = random_matrix(6, 6);
+        let alpha = Complex::new(0.5, -1.0);
+        let beta = Complex::new(-0.3, 0.2);
+        (0..4).for_each(|ta| {
+            (0..4).for_each(|tb| {
+                let mut c_gpu = c.clone();
+                GpuBackend::matrix_mul(trans(ta), trans(tb), alpha, &a, &b, beta, &mut c_gpu);
+                let mut c_cpu = c.clone();
+                NalgebraBackend::matrix_mul(trans(ta), trans(tb), alpha, &a, &b, beta, &mut c_cpu);
+                assert_close(c_gpu.as_slice(), c_cpu.as_slice());
+            });
+        });
+    }
+
+    #[test]
+    fn matrix_mul_vec_same_as_nalgebra() {
+        let a = random_matrix(5, 7);
+        let b = random_matrix(7, 1).column(0).into_owned();
+        let c = random_matrix(5, 1).column(0).into_owned();
+        let alpha = Complex::new(0.5, -1.0);
+        let beta = Complex::new(-0.3, 0.2);
+        let mut c_gpu = c.clone();
+        GpuBackend::matrix_mul_vec(Transpose::NoTrans, alpha, &a, &b, beta, &mut c_gpu);
+        let mut c_cpu = c;
+        NalgebraBackend::matrix_mul_vec(Transpose::NoTrans, alpha, &a, &b, beta, &mut c_cpu);
+        assert_close(c_gpu.as_slice(), c_cpu.as_slice());
+    }
+
+    macro_rules! assert_same_field {
+        ($gain:ident) => {{
+            let mut geometry = GeometryBuilder::new().legacy_mode().build();
+            geometry.add_device(Vector3::zeros(), Vector3::zeros());
+            geometry.add_device(Vector3::new(192., 0., 0.), Vector3::zeros());
+            let center = geometry.center() + Vector3::new(0., 0., 150.);
+            let foci = vec![
+                center + Vector3::new(30., 0., 0.),
+                center - Vector3::new(30., 0., 0.),
+                center + Vector3::new(0., 30., 0.),
+            ];
+            let amps = vec![1.0, 0.8, 0.6];
+
+            let mut cpu =
+                $gain::<NalgebraBackend, _, _>::new(foci.clone(), amps.clone(), Normalize {});
+            cpu.build(&geometry).unwrap();
+            let mut gpu = $gain::<GpuBackend, _, _>::new(foci.clone(), amps, Normalize {});
+            gpu.build(&geometry).unwrap();
+
+            let evaluator = FieldEvaluator::new(&geometry);
+            let p_cpu = evaluator.eval(cpu.drives(), &foci);
+            let p_gpu = evaluator.eval(gpu.drives(), &foci);
+            p_cpu.iter().zip(p_gpu.iter()).for_each(|(a, b)| {
+                assert!((a.norm() - b.norm()).abs() / a.norm() < 0.01);
+            });
+        }};
+    }
+
+    #[test]
+    fn naive_same_as_nalgebra() {
+        assert_same_field!(Naive);
+    }
+
+    #[test]
+    fn gs_same_as_nalgebra() {
+        assert_same_field!(GS);
+    }
+
+    #[test]
+    fn gspat_same_as_nalgebra() {
+        assert_same_field!(GSPAT);
+    }
+}
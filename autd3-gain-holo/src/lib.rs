@@ -14,15 +14,31 @@
 mod backend;
 mod combinatorial;
 mod constraint;
+mod directivity;
 mod error;
+mod f32_backend;
+mod field;
+#[cfg(feature = "gpu")]
+mod gpu_backend;
 mod linear_synthesis;
 mod macros;
 mod matrix;
 mod nls;
+mod solver;
+mod utils;
 
 pub use backend::*;
 pub use combinatorial::*;
 pub use constraint::*;
+pub use directivity::*;
+pub use error::HoloError;
+pub use f32_backend::NalgebraF32Backend;
+pub use field::FieldEvaluator;
+#[cfg(feature = "gpu")]
+pub use gpu_backend::GpuBackend;
 pub use linear_synthesis::*;
+pub use macros::{generate_propagation_matrix, generate_propagation_matrix_with};
 pub use matrix::*;
 pub use nls::*;
+pub use solver::HoloSolver;
+pub use utils::*;
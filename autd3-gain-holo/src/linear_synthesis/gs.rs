@@ -12,8 +12,11 @@
  */
 
 use crate::{
-    constraint::Constraint, macros::generate_propagation_matrix, Backend, Complex, Transpose,
-    VectorXc,
+    constraint::Constraint,
+    macros::{
+        db_to_ratio, generate_propagation_matrix, pin_phase, relative_amp_error, single_focus,
+    },
+    Backend, Complex, Transpose, VectorXc,
 };
 use anyhow::Result;
 use autd3_core::{
@@ -33,8 +36,10 @@ pub struct GS<B: Backend, T: Transducer, C: Constraint> {
     foci: Vec<Vector3>,
     amps: Vec<f64>,
     repeat: usize,
+    eps: Option<f64>,
     backend: PhantomData<B>,
     constraint: C,
+    phase_reference: Option<usize>,
 }
 
 impl<B: Backend, T: Transducer, C: Constraint> GS<B, T, C> {
@@ -42,6 +47,12 @@ impl<B: Backend, T: Transducer, C: Constraint> GS<B, T, C> {
         Self::with_param(foci, amps, constraint, 100)
     }
 
+    /// constructor from pairs of a focus and its amplitude
+    pub fn from_targets<I: IntoIterator<Item = (Vector3, f64)>>(targets: I, constraint: C) -> Self {
+        let (foci, amps) = targets.into_iter().unzip();
+        Self::new(foci, amps, constraint)
+    }
+
     pub fn with_param(foci: Vec<Vector3>, amps: Vec<f64>, constraint: C, repeat: usize) -> Self {
         assert!(foci.len() == amps.len());
         Self {
@@ -49,14 +60,46 @@ impl<B: Backend, T: Transducer, C: Constraint> GS<B, T, C> {
             foci,
             amps,
             repeat,
+            eps: None,
             backend: PhantomData,
             constraint,
+            phase_reference: None,
+        }
+    }
+
+    /// Stop the iteration when the relative error of the focal amplitudes falls below `db` decibels.
+    pub fn with_eps_db(self, db: f64) -> Self {
+        Self {
+            eps: Some(db_to_ratio(db)),
+            ..self
+        }
+    }
+
+    /// Shift the phase of the solution so that the field at the `idx`-th focus has zero phase.
+    ///
+    /// Since the absolute phase does not affect the amplitudes, this makes the phase of the field deterministic across runs.
+    pub fn with_phase_reference(self, idx: usize) -> Self {
+        assert!(idx < self.foci.len());
+        Self {
+            phase_reference: Some(idx),
+            ..self
         }
     }
 }
 
 impl<B: Backend, T: Transducer, C: Constraint> IGain<T> for GS<B, T, C> {
     fn calc(&mut self, geometry: &Geometry<T>) -> Result<()> {
+        if self.foci.len() == 1 {
+            single_focus(
+                &mut self.props.drives,
+                geometry,
+                self.foci[0],
+                self.amps[0],
+                &self.constraint,
+            );
+            return Ok(());
+        }
+
         let m = self.foci.len();
         let n = geometry.num_devices() * NUM_TRANS_IN_UNIT;
 
@@ -77,6 +120,11 @@ impl<B: Backend, T: Transducer, C: Constraint> IGain<T> for GS<B, T, C> {
                 Complex::new(0., 0.),
                 &mut gamma,
             );
+            if let Some(eps) = self.eps {
+                if relative_amp_error(&gamma, &self.amps) <= eps {
+                    break;
+                }
+            }
             for i in 0..m {
                 p[i] = gamma[i] / gamma[i].abs() * self.amps[i];
             }
@@ -101,6 +149,10 @@ impl<B: Backend, T: Transducer, C: Constraint> IGain<T> for GS<B, T, C> {
             self.props.drives[tr.id()].phase = phase;
         });
 
+        if let Some(idx) = self.phase_reference {
+            pin_phase(&mut self.props.drives, geometry, self.foci[idx]);
+        }
+
         Ok(())
     }
 }
@@ -12,8 +12,12 @@
  */
 
 use crate::{
-    constraint::Constraint, macros::generate_propagation_matrix, Backend, Complex, MatrixXc,
-    Transpose, VectorXc,
+    constraint::Constraint,
+    macros::{
+        db_to_ratio, generate_propagation_matrix_f32, pin_phase, relative_amp_error, single_focus,
+        PropagationCache,
+    },
+    Backend, Complex, Complex32, MatrixXc, MatrixXc32, Transpose, VectorXc,
 };
 use anyhow::Result;
 use autd3_core::{
@@ -28,20 +32,44 @@ use std::{f64::consts::PI, marker::PhantomData};
 /// Reference
 /// * Diego Martinez Plasencia et al. "Gs-pat: high-speed multi-point sound-fields for phased arrays of transducers," ACMTrans-actions on Graphics (TOG), 39(4):138–1, 2020.
 ///
-/// Not yet been implemented with GPU.
+/// The matrix multiplications can be run on GPU with `GpuBackend` by enabling the `gpu` feature.
+/// With [NalgebraF32Backend](crate::NalgebraF32Backend), the large matrices are stored in single precision.
 #[derive(Gain)]
 pub struct GSPAT<B: Backend, T: Transducer, C: Constraint> {
     props: GainProps<T>,
     foci: Vec<Vector3>,
     amps: Vec<f64>,
     repeat: usize,
+    eps: Option<f64>,
+    tolerance: Option<f64>,
     backend: PhantomData<B>,
     constraint: C,
+    phase_reference: Option<usize>,
+    cache: PropagationCache,
+    cache32: PropagationCache<MatrixXc32>,
+    residuals: Vec<f64>,
 }
 
 impl<B: Backend, T: Transducer, C: Constraint> GSPAT<B, T, C> {
+    /// constructor
+    ///
+    /// The number of iterations is chosen by [default_repeat](#method.default_repeat).
     pub fn new(foci: Vec<Vector3>, amps: Vec<f64>, constraint: C) -> Self {
-        Self::with_param(foci, amps, constraint, 100)
+        let repeat = Self::default_repeat(foci.len());
+        Self::with_param(foci, amps, constraint, repeat)
+    }
+
+    /// constructor from pairs of a focus and its amplitude
+    pub fn from_targets<I: IntoIterator<Item = (Vector3, f64)>>(targets: I, constraint: C) -> Self {
+        let (foci, amps) = targets.into_iter().unzip();
+        Self::new(foci, amps, constraint)
+    }
+
+    /// Default number of iterations for `num_foci` foci
+    ///
+    /// It is 10 times the number of foci, bounded within [10, 200].
+    pub fn default_repeat(num_foci: usize) -> usize {
+        (10 * num_foci).clamp(10, 200)
     }
 
     pub fn with_param(foci: Vec<Vector3>, amps: Vec<f64>, constraint: C, repeat: usize) -> Self {
@@ -51,34 +79,129 @@ impl<B: Backend, T: Transducer, C: Constraint> GSPAT<B, T, C> {
             foci,
             amps,
             repeat,
+            eps: None,
+            tolerance: None,
             backend: PhantomData,
             constraint,
+            phase_reference: None,
+            cache: PropagationCache::default(),
+            cache32: PropagationCache::default(),
+            residuals: vec![],
         }
     }
+
+    /// Stop the iteration when the relative error of the focal amplitudes falls below `db` decibels.
+    pub fn with_eps_db(self, db: f64) -> Self {
+        Self {
+            eps: Some(db_to_ratio(db)),
+            ..self
+        }
+    }
+
+    /// Stop the iteration when the residual recorded in [last_residuals](#method.last_residuals) falls below `tolerance`.
+    pub fn with_tolerance(self, tolerance: f64) -> Self {
+        Self {
+            tolerance: Some(tolerance),
+            ..self
+        }
+    }
+
+    pub fn with_repeat(self, repeat: usize) -> Self {
+        Self { repeat, ..self }
+    }
+
+    /// Residuals of the iterations in the last build
+    ///
+    /// The residual of an iteration is the norm of the change of the focal amplitudes `|gamma|` relative to the norm of the new amplitudes.
+    /// It decreases toward zero as the iteration converges.
+    /// It is empty for a single focus, which is solved without iteration.
+    pub fn last_residuals(&self) -> &[f64] {
+        &self.residuals
+    }
+
+    /// Replace the target amplitudes, e.g., to update the field every frame
+    ///
+    /// The propagation matrix computed in the previous build is reused as long as the geometry and the foci are unchanged.
+    pub fn with_amps(mut self, amps: Vec<f64>) -> Self {
+        assert!(self.foci.len() == amps.len());
+        self.amps = amps;
+        self.props.built = false;
+        self
+    }
+
+    /// Discard the cached propagation matrix, e.g., when the geometry is modified in a way not tracked by [Geometry::version]
+    pub fn invalidate_cache(&mut self) {
+        self.cache.invalidate();
+        self.cache32.invalidate();
+    }
+
+    /// Shift the phase of the solution so that the field at the `idx`-th focus has zero phase.
+    ///
+    /// Since the absolute phase does not affect the amplitudes, this makes the phase of the field deterministic across runs.
+    pub fn with_phase_reference(self, idx: usize) -> Self {
+        assert!(idx < self.foci.len());
+        Self {
+            phase_reference: Some(idx),
+            ..self
+        }
+    }
+}
+
+/// Matrix from the focal amplitudes back to the transducers, stored in the precision of the backend
+enum Backward {
+    Double(MatrixXc),
+    Single(MatrixXc32),
 }
 
 impl<B: Backend, T: Transducer, C: Constraint> IGain<T> for GSPAT<B, T, C> {
     fn calc(&mut self, geometry: &Geometry<T>) -> Result<()> {
+        self.residuals.clear();
+        if self.foci.len() == 1 {
+            single_focus(
+                &mut self.props.drives,
+                geometry,
+                self.foci[0],
+                self.amps[0],
+                &self.constraint,
+            );
+            return Ok(());
+        }
+
         let m = self.foci.len();
         let n = geometry.num_devices() * NUM_TRANS_IN_UNIT;
 
-        let g = generate_propagation_matrix(geometry, &self.foci);
+        let (r, b) = if B::SINGLE_PRECISION {
+            let g = self
+                .cache32
+                .get_with(geometry, &self.foci, generate_propagation_matrix_f32);
 
-        let denomi = g.column_sum();
-        let b = g
-            .map_with_location(|i, _, a| Complex::new(self.amps[i], 0.0) * a.conj() / denomi[i])
-            .transpose();
+            let denomi = g.column_sum();
+            let b = g
+                .map_with_location(|i, _, a| a.conj() * (self.amps[i] as f32) / denomi[i])
+                .transpose();
 
-        let mut r = MatrixXc::zeros(m, m);
-        B::matrix_mul(
-            Transpose::NoTrans,
-            Transpose::NoTrans,
-            Complex::new(1., 0.),
-            &g,
-            &b,
-            Complex::new(0., 0.),
-            &mut r,
-        );
+            let r = (g * &b).map(|c| Complex::new(c.re as f64, c.im as f64));
+            (r, Backward::Single(b))
+        } else {
+            let g = self.cache.get(geometry, &self.foci);
+
+            let denomi = g.column_sum();
+            let b = g
+                .map_with_location(|i, _, a| Complex::new(self.amps[i], 0.0) * a.conj() / denomi[i])
+                .transpose();
+
+            let mut r = MatrixXc::zeros(m, m);
+            B::matrix_mul(
+                Transpose::NoTrans,
+                Transpose::NoTrans,
+                Complex::new(1., 0.),
+                g,
+                &b,
+                Complex::new(0., 0.),
+                &mut r,
+            );
+            (r, Backward::Double(b))
+        };
 
         let mut p = VectorXc::from_iterator(m, self.amps.iter().map(|&a| Complex::new(a, 0.)));
 
@@ -92,9 +215,25 @@ impl<B: Backend, T: Transducer, C: Constraint> IGain<T> for GSPAT<B, T, C> {
             &mut gamma,
         );
         for _ in 0..self.repeat {
+            if let Some(eps) = self.eps {
+                // the error of the field to be output, not of gamma itself
+                let mut output = VectorXc::zeros(m);
+                B::matrix_mul_vec(
+                    Transpose::NoTrans,
+                    Complex::new(1., 0.),
+                    &r,
+                    &output_amps(&gamma, &self.amps),
+                    Complex::new(0., 0.),
+                    &mut output,
+                );
+                if relative_amp_error(&output, &self.amps) <= eps {
+                    break;
+                }
+            }
             for i in 0..m {
                 p[i] = gamma[i] / gamma[i].abs() * self.amps[i];
             }
+            let prev = gamma.map(|g| g.abs());
             B::matrix_mul_vec(
                 Transpose::NoTrans,
                 Complex::new(1., 0.),
@@ -103,21 +242,34 @@ impl<B: Backend, T: Transducer, C: Constraint> IGain<T> for GSPAT<B, T, C> {
                 Complex::new(0., 0.),
                 &mut gamma,
             );
+            let amp = gamma.map(|g| g.abs());
+            let residual = (&amp - prev).norm() / amp.norm();
+            self.residuals.push(residual);
+            if matches!(self.tolerance, Some(tolerance) if residual < tolerance) {
+                break;
+            }
         }
 
-        for i in 0..m {
-            p[i] = gamma[i] / gamma[i].norm_sqr() * self.amps[i] * self.amps[i];
-        }
+        let p = output_amps(&gamma, &self.amps);
 
-        let mut q = VectorXc::zeros(n);
-        B::matrix_mul_vec(
-            Transpose::NoTrans,
-            Complex::new(1., 0.),
-            &b,
-            &p,
-            Complex::new(0., 0.),
-            &mut q,
-        );
+        let q = match b {
+            Backward::Double(b) => {
+                let mut q = VectorXc::zeros(n);
+                B::matrix_mul_vec(
+                    Transpose::NoTrans,
+                    Complex::new(1., 0.),
+                    &b,
+                    &p,
+                    Complex::new(0., 0.),
+                    &mut q,
+                );
+                q
+            }
+            Backward::Single(b) => {
+                let p = p.map(|c| Complex32::new(c.re as f32, c.im as f32));
+                (b * p).map(|c| Complex::new(c.re as f64, c.im as f64))
+            }
+        };
 
         let max_coefficient = B::max_coefficient_c(&q).abs();
         geometry.transducers().for_each(|tr| {
@@ -127,6 +279,200 @@ impl<B: Backend, T: Transducer, C: Constraint> IGain<T> for GSPAT<B, T, C> {
             self.props.drives[tr.id()].phase = phase;
         });
 
+        if let Some(idx) = self.phase_reference {
+            pin_phase(&mut self.props.drives, geometry, self.foci[idx]);
+        }
+
         Ok(())
     }
 }
+
+/// Focal amplitudes to be back-propagated to the transducers, compensating the amplitudes of `gamma` achieved by the iteration
+fn output_amps(gamma: &VectorXc, amps: &[f64]) -> VectorXc {
+    VectorXc::from_iterator(
+        gamma.len(),
+        gamma
+            .iter()
+            .zip(amps.iter())
+            .map(|(g, &a)| g / g.norm_sqr() * a * a),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use autd3_core::geometry::{GeometryBuilder, LegacyTransducer};
+
+    use super::*;
+    use crate::{FieldEvaluator, NalgebraBackend, NalgebraF32Backend, Normalize};
+
+    fn geometry(pos: Vector3) -> Geometry<LegacyTransducer> {
+        let mut geometry = GeometryBuilder::new().legacy_mode().build();
+        geometry.add_device(pos, Vector3::zeros());
+        geometry
+    }
+
+    #[test]
+    fn f32_close_to_f64() {
+        let mut geometry = GeometryBuilder::new().legacy_mode().build();
+        geometry.add_device(Vector3::zeros(), Vector3::zeros());
+        geometry.add_device(Vector3::new(192., 0., 0.), Vector3::zeros());
+        let center = geometry.center() + Vector3::new(0., 0., 150.);
+        let foci = vec![
+            center + Vector3::new(30., 0., 0.),
+            center - Vector3::new(30., 0., 0.),
+            center + Vector3::new(0., 30., 0.),
+            center - Vector3::new(0., 30., 0.),
+        ];
+        let amps = vec![1.0, 0.8, 0.6, 1.0];
+
+        let mut g64 = GSPAT::<NalgebraBackend, _, _>::new(foci.clone(), amps.clone(), Normalize {});
+        g64.build(&geometry).unwrap();
+        let mut g32 =
+            GSPAT::<NalgebraF32Backend, _, _>::new(foci.clone(), amps.clone(), Normalize {});
+        g32.build(&geometry).unwrap();
+
+        let evaluator = FieldEvaluator::new(&geometry);
+        let p64 = evaluator.eval(g64.drives(), &foci);
+        let p32 = evaluator.eval(g32.drives(), &foci);
+        p64.iter().zip(p32.iter()).for_each(|(a, b)| {
+            assert!((a.abs() - b.abs()).abs() / a.abs() < 0.01);
+        });
+    }
+
+    #[test]
+    fn cache_not_shared_between_geometries() {
+        let geometry_a = geometry(Vector3::zeros());
+        let geometry_b = geometry(Vector3::new(50., 30., 0.));
+        let center = geometry_a.center() + Vector3::new(0., 0., 150.);
+        let foci = vec![
+            center + Vector3::new(20., 0., 0.),
+            center - Vector3::new(20., 0., 0.),
+        ];
+
+        let mut g = GSPAT::<NalgebraBackend, _, _>::new(foci.clone(), vec![1., 1.], Normalize {});
+        g.build(&geometry_a).unwrap();
+        g.rebuild(&geometry_b).unwrap();
+
+        let mut expect = GSPAT::<NalgebraBackend, _, _>::new(foci, vec![1., 1.], Normalize {});
+        expect.build(&geometry_b).unwrap();
+
+        g.drives()
+            .iter()
+            .zip(expect.drives().iter())
+            .for_each(|(a, b)| {
+                assert_eq!(a.amp, b.amp);
+                assert_eq!(a.phase, b.phase);
+            });
+    }
+
+    fn setup() -> (Geometry<LegacyTransducer>, Vec<Vector3>) {
+        let mut geometry = GeometryBuilder::new().legacy_mode().build();
+        geometry.add_device(Vector3::zeros(), Vector3::zeros());
+        geometry.add_device(Vector3::new(192., 0., 0.), Vector3::zeros());
+        let center = geometry.center() + Vector3::new(0., 0., 150.);
+        let foci = vec![
+            center + Vector3::new(30., 0., 0.),
+            center - Vector3::new(30., 0., 0.),
+            center + Vector3::new(0., 30., 0.),
+            center - Vector3::new(0., 30., 0.),
+        ];
+        (geometry, foci)
+    }
+
+    #[test]
+    fn eps_db() {
+        let (geometry, foci) = setup();
+        let amps = vec![1.0, 0.8, 0.6, 1.0];
+
+        [-20., -40.].into_iter().for_each(|db| {
+            let mut g = GSPAT::<NalgebraBackend, _, _>::with_param(
+                foci.clone(),
+                amps.clone(),
+                Normalize {},
+                200,
+            )
+            .with_eps_db(db);
+            g.build(&geometry).unwrap();
+
+            let p = FieldEvaluator::new(&geometry).eval(g.drives(), &foci);
+            assert!(relative_amp_error(&VectorXc::from_vec(p), &amps) <= db_to_ratio(db));
+            assert!(g.last_residuals().len() < 200);
+        });
+    }
+
+    #[test]
+    fn phase_reference() {
+        let (geometry, foci) = setup();
+
+        (0..foci.len()).for_each(|idx| {
+            let mut g =
+                GSPAT::<NalgebraBackend, _, _>::new(foci.clone(), vec![1.0; 4], Normalize {})
+                    .with_phase_reference(idx);
+            g.build(&geometry).unwrap();
+
+            let p = FieldEvaluator::new(&geometry).eval(g.drives(), &foci[idx..idx + 1]);
+            assert!(p[0].arg().abs() < 1e-9);
+        });
+    }
+
+    #[test]
+    fn single_focus_same_as_focus() {
+        let (geometry, foci) = setup();
+        let focus = foci[0];
+
+        let mut g = GSPAT::<NalgebraBackend, _, _>::new(vec![focus], vec![1.0], Normalize {});
+        g.build(&geometry).unwrap();
+
+        // solved directly, without the iteration and the propagation matrix
+        assert!(g.last_residuals().is_empty());
+        assert!(g.cache.cached.is_none());
+
+        // the phases of the Focus gain of autd3, up to the constant offset of the phase convention of holo gains
+        geometry.transducers().for_each(|tr| {
+            let expect = tr.align_phase_at((focus - tr.position()).norm(), geometry.sound_speed());
+            let diff = (g.drives()[tr.id()].phase - expect - 0.5).rem_euclid(1.0);
+            assert!(diff.min(1.0 - diff) < 1e-9);
+            assert!(g.drives()[tr.id()].amp > 0.);
+        });
+    }
+
+    #[test]
+    fn default_repeat() {
+        type G = GSPAT<NalgebraBackend, LegacyTransducer, Normalize>;
+        assert_eq!(G::default_repeat(1), 10);
+        assert_eq!(G::default_repeat(4), 40);
+        assert_eq!(G::default_repeat(20), 200);
+        assert_eq!(G::default_repeat(100), 200);
+
+        let (geometry, foci) = setup();
+        let mut g = G::new(foci.clone(), vec![1.0; 4], Normalize {});
+        g.build(&geometry).unwrap();
+        assert_eq!(g.last_residuals().len(), G::default_repeat(foci.len()));
+
+        let mut g = G::new(foci, vec![1.0; 4], Normalize {}).with_repeat(7);
+        g.build(&geometry).unwrap();
+        assert_eq!(g.last_residuals().len(), 7);
+    }
+
+    #[test]
+    fn from_targets() {
+        let (geometry, foci) = setup();
+        let amps = vec![1.0, 0.8, 0.6, 1.0];
+
+        let mut g = GSPAT::<NalgebraBackend, _, _>::from_targets(
+            foci.iter().copied().zip(amps.iter().copied()),
+            Normalize {},
+        );
+        g.build(&geometry).unwrap();
+        let mut expect = GSPAT::<NalgebraBackend, _, _>::new(foci, amps, Normalize {});
+        expect.build(&geometry).unwrap();
+
+        g.drives()
+            .iter()
+            .zip(expect.drives().iter())
+            .for_each(|(a, b)| {
+                assert_eq!(a.amp, b.amp);
+                assert_eq!(a.phase, b.phase);
+            });
+    }
+}
@@ -12,8 +12,9 @@
  */
 
 use crate::{
-    constraint::Constraint, macros::generate_propagation_matrix, Backend, Complex, Transpose,
-    VectorXc,
+    constraint::Constraint,
+    macros::{generate_propagation_matrix, pin_phase, single_focus},
+    Backend, Complex, Transpose, VectorXc,
 };
 use anyhow::Result;
 use autd3_core::{
@@ -33,6 +34,7 @@ pub struct Naive<B: Backend, T: Transducer, C: Constraint> {
     amps: Vec<f64>,
     backend: PhantomData<B>,
     constraint: C,
+    phase_reference: Option<usize>,
 }
 
 impl<B: Backend, T: Transducer, C: Constraint> Naive<B, T, C> {
@@ -44,11 +46,40 @@ impl<B: Backend, T: Transducer, C: Constraint> Naive<B, T, C> {
             amps,
             backend: PhantomData,
             constraint,
+            phase_reference: None,
+        }
+    }
+
+    /// constructor from pairs of a focus and its amplitude
+    pub fn from_targets<I: IntoIterator<Item = (Vector3, f64)>>(targets: I, constraint: C) -> Self {
+        let (foci, amps) = targets.into_iter().unzip();
+        Self::new(foci, amps, constraint)
+    }
+
+    /// Shift the phase of the solution so that the field at the `idx`-th focus has zero phase.
+    ///
+    /// Since the absolute phase does not affect the amplitudes, this makes the phase of the field deterministic across runs.
+    pub fn with_phase_reference(self, idx: usize) -> Self {
+        assert!(idx < self.foci.len());
+        Self {
+            phase_reference: Some(idx),
+            ..self
         }
     }
 }
 impl<B: Backend, T: Transducer, C: Constraint> IGain<T> for Naive<B, T, C> {
     fn calc(&mut self, geometry: &Geometry<T>) -> Result<()> {
+        if self.foci.len() == 1 {
+            single_focus(
+                &mut self.props.drives,
+                geometry,
+                self.foci[0],
+                self.amps[0],
+                &self.constraint,
+            );
+            return Ok(());
+        }
+
         let m = self.foci.len();
         let n = geometry.num_devices() * NUM_TRANS_IN_UNIT;
 
@@ -72,6 +103,10 @@ impl<B: Backend, T: Transducer, C: Constraint> IGain<T> for Naive<B, T, C> {
             self.props.drives[tr.id()].phase = phase;
         });
 
+        if let Some(idx) = self.phase_reference {
+            pin_phase(&mut self.props.drives, geometry, self.foci[idx]);
+        }
+
         Ok(())
     }
 }
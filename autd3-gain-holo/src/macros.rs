@@ -11,14 +11,18 @@
  *
  */
 
-use crate::{Complex, MatrixXc};
+use crate::{
+    constraint::Constraint,
+    directivity::{Directivity, T4010A1},
+    Complex, Complex32, MatrixXc, MatrixXc32, VectorXc,
+};
 use autd3_core::{
     geometry::{Geometry, Transducer, Vector3},
-    utils::directivity_t4010a1 as directivity,
-    NUM_TRANS_IN_UNIT,
+    Drive, NUM_TRANS_IN_UNIT,
 };
 #[allow(unused)]
 use nalgebra::ComplexField;
+use std::f64::consts::PI;
 
 pub fn propagate(
     source_pos: &Vector3,
@@ -26,40 +30,248 @@ pub fn propagate(
     atten: f64,
     wavenum: f64,
     target: Vector3,
+) -> Complex {
+    propagate_with::<T4010A1>(source_pos, source_dir, atten, wavenum, target)
+}
+
+/// Sound pressure at `target` emitted from a transducer with the directivity `D`
+pub fn propagate_with<D: Directivity>(
+    source_pos: &Vector3,
+    source_dir: &Vector3,
+    atten: f64,
+    wavenum: f64,
+    target: Vector3,
 ) -> Complex {
     let diff = target - source_pos;
     let dist = diff.norm();
     let theta = source_dir.angle(&diff);
 
-    let d = directivity(theta);
+    let d = D::gain(theta);
     let r = d * (-dist * atten).exp() / dist;
     let phi = -wavenum * dist;
     r * Complex::new(0., phi).exp()
 }
 
+/// Propagation matrix from the transducers to the foci, assuming the directivity of T4010A1
 pub fn generate_propagation_matrix<T: Transducer>(
     geometry: &Geometry<T>,
     foci: &[Vector3],
 ) -> MatrixXc {
+    generate_propagation_matrix_with::<T4010A1, T>(geometry, foci)
+}
+
+/// Propagation matrix from the transducers to the foci with the directivity `D`
+pub fn generate_propagation_matrix_with<D: Directivity, T: Transducer>(
+    geometry: &Geometry<T>,
+    foci: &[Vector3],
+) -> MatrixXc {
+    propagation_matrix::<D, T, _>(geometry, foci, |c| c)
+}
+
+/// Propagation matrix stored in single precision, assuming the directivity of T4010A1
+///
+/// Each element is computed in double precision and then rounded, so that the matrix in double precision is never allocated.
+pub(crate) fn generate_propagation_matrix_f32<T: Transducer>(
+    geometry: &Geometry<T>,
+    foci: &[Vector3],
+) -> MatrixXc32 {
+    propagation_matrix::<T4010A1, T, _>(geometry, foci, |c| {
+        Complex32::new(c.re as f32, c.im as f32)
+    })
+}
+
+fn propagation_matrix<D: Directivity, T: Transducer, S: nalgebra::Scalar + Send>(
+    geometry: &Geometry<T>,
+    foci: &[Vector3],
+    convert: fn(Complex) -> S,
+) -> nalgebra::DMatrix<S> {
     let m = foci.len();
     let num_device = geometry.num_devices();
     let num_trans = num_device * NUM_TRANS_IN_UNIT;
     let sound_speed = geometry.sound_speed();
 
-    MatrixXc::from_iterator(
-        m,
-        num_trans,
-        geometry.transducers().flat_map(|trans| {
-            foci.iter().map(move |&fp| {
-                let wavenum = trans.wavenumber(sound_speed);
-                propagate(
-                    trans.position(),
-                    trans.z_direction(),
-                    geometry.attenuation,
-                    wavenum,
-                    fp,
-                )
-            })
-        }),
-    )
+    #[cfg(not(feature = "parallel"))]
+    {
+        nalgebra::DMatrix::from_iterator(
+            m,
+            num_trans,
+            geometry
+                .transducers()
+                .zip(geometry.positions())
+                .flat_map(|(trans, pos)| {
+                    foci.iter().map(move |&fp| {
+                        let wavenum = trans.wavenumber(sound_speed);
+                        convert(propagate_with::<D>(
+                            pos,
+                            trans.z_direction(),
+                            geometry.attenuation,
+                            wavenum,
+                            fp,
+                        ))
+                    })
+                }),
+        )
+    }
+
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+
+        let attenuation = geometry.attenuation;
+        let sources = geometry
+            .transducers()
+            .zip(geometry.positions())
+            .map(|(trans, pos)| (*pos, *trans.z_direction(), trans.wavenumber(sound_speed)))
+            .collect::<Vec<_>>();
+        nalgebra::DMatrix::from_vec(
+            m,
+            num_trans,
+            sources
+                .par_iter()
+                .flat_map_iter(|(pos, dir, wavenum)| {
+                    foci.iter().map(move |&fp| {
+                        convert(propagate_with::<D>(pos, dir, attenuation, *wavenum, fp))
+                    })
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Geometry version, attenuation, sound speed, and foci
+type PropagationKey = (usize, f64, f64, Vec<Vector3>);
+
+/// Propagation matrix reused while the geometry and the foci are unchanged
+pub(crate) struct PropagationCache<M = MatrixXc> {
+    pub(crate) cached: Option<(PropagationKey, M)>,
+}
+
+impl<M> Default for PropagationCache<M> {
+    fn default() -> Self {
+        Self { cached: None }
+    }
+}
+
+impl<M> PropagationCache<M> {
+    /// Return the cached matrix, generating it with `generate` if the geometry or the foci have changed
+    pub(crate) fn get_with<T: Transducer>(
+        &mut self,
+        geometry: &Geometry<T>,
+        foci: &[Vector3],
+        generate: fn(&Geometry<T>, &[Vector3]) -> M,
+    ) -> &M {
+        let key = (
+            geometry.version(),
+            geometry.attenuation,
+            geometry.sound_speed,
+            foci.to_vec(),
+        );
+        if !matches!(&self.cached, Some((k, _)) if *k == key) {
+            // release the old matrix before generating the new one
+            self.cached = None;
+            self.cached = Some((key, generate(geometry, foci)));
+        }
+        &self.cached.as_ref().unwrap().1
+    }
+
+    pub(crate) fn invalidate(&mut self) {
+        self.cached = None;
+    }
+}
+
+impl PropagationCache {
+    pub(crate) fn get<T: Transducer>(
+        &mut self,
+        geometry: &Geometry<T>,
+        foci: &[Vector3],
+    ) -> &MatrixXc {
+        self.get_with(geometry, foci, generate_propagation_matrix)
+    }
+}
+
+/// Maximum deviation of the ratio of the achieved amplitudes to the target amplitudes from its mean.
+pub fn relative_amp_error(gamma: &VectorXc, amps: &[f64]) -> f64 {
+    let ratio = gamma
+        .iter()
+        .zip(amps.iter())
+        .map(|(g, &a)| g.abs() / a)
+        .collect::<Vec<_>>();
+    let mean = ratio.iter().sum::<f64>() / ratio.len() as f64;
+    ratio
+        .iter()
+        .map(|r| (r / mean - 1.0).abs())
+        .fold(0.0, f64::max)
+}
+
+pub fn db_to_ratio(db: f64) -> f64 {
+    10f64.powf(db / 20.0)
+}
+
+/// Shift the phases of all drives so that the phase of the field at `focus` becomes zero.
+pub fn pin_phase<T: Transducer>(drives: &mut [Drive], geometry: &Geometry<T>, focus: Vector3) {
+    let g = generate_propagation_matrix(geometry, &[focus]);
+    let p = g
+        .iter()
+        .zip(drives.iter())
+        .map(|(g, d)| g * Complex::from_polar(d.amp, 2.0 * PI * (d.phase - 0.5)))
+        .sum::<Complex>();
+    let shift = p.arg() / (2.0 * PI);
+    drives
+        .iter_mut()
+        .for_each(|d| d.phase = (d.phase - shift).rem_euclid(1.0));
+}
+
+/// Compute the drives for a single focus directly by back-propagation, without the propagation matrix and the backend.
+pub fn single_focus<T: Transducer, C: Constraint>(
+    drives: &mut [Drive],
+    geometry: &Geometry<T>,
+    focus: Vector3,
+    amp: f64,
+    constraint: &C,
+) {
+    let sound_speed = geometry.sound_speed();
+    let q = geometry
+        .transducers()
+        .zip(geometry.positions())
+        .map(|(tr, pos)| {
+            let wavenum = tr.wavenumber(sound_speed);
+            propagate(pos, tr.z_direction(), geometry.attenuation, wavenum, focus).conj() * amp
+        })
+        .collect::<Vec<_>>();
+    let max_coefficient = q.iter().fold(0.0, |acc: f64, v| acc.max(v.abs()));
+    geometry.transducers().for_each(|tr| {
+        let v = q[tr.id()];
+        drives[tr.id()].amp = constraint.convert(v.abs(), max_coefficient);
+        drives[tr.id()].phase = v.argument() / (2.0 * PI) + 0.5;
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use autd3_core::utils::directivity_t4010a1;
+
+    use super::*;
+
+    #[test]
+    fn propagate_same_as_t4010a1() {
+        let source_pos = Vector3::new(10., 20., 0.);
+        let source_dir = Vector3::z();
+        let (atten, wavenum) = (0.1e-3, 2. * PI / 8.5);
+        [
+            Vector3::new(10., 20., 150.),
+            Vector3::new(60., -30., 100.),
+            Vector3::new(200., 20., 50.),
+        ]
+        .into_iter()
+        .for_each(|target| {
+            let diff = target - source_pos;
+            let dist = diff.norm();
+            let r = directivity_t4010a1(source_dir.angle(&diff)) * (-dist * atten).exp() / dist;
+            let expect = r * Complex::new(0., -wavenum * dist).exp();
+            assert_eq!(
+                propagate(&source_pos, &source_dir, atten, wavenum, target),
+                expect
+            );
+        });
+    }
 }
@@ -12,8 +12,9 @@
  */
 
 use crate::{
-    constraint::Constraint, macros::generate_propagation_matrix, Backend, Complex, MatrixXc,
-    Transpose, VectorXc,
+    constraint::Constraint,
+    macros::{generate_propagation_matrix, pin_phase, single_focus},
+    Backend, Complex, MatrixXc, Transpose, VectorXc,
 };
 use anyhow::Result;
 use autd3_core::{
@@ -38,6 +39,7 @@ pub struct SDP<B: Backend, T: Transducer, C: Constraint> {
     repeat: usize,
     backend: PhantomData<B>,
     constraint: C,
+    phase_reference: Option<usize>,
 }
 
 impl<B: Backend, T: Transducer, C: Constraint> SDP<B, T, C> {
@@ -45,6 +47,12 @@ impl<B: Backend, T: Transducer, C: Constraint> SDP<B, T, C> {
         Self::with_params(foci, amps, constraint, 1e-3, 0.9, 100)
     }
 
+    /// constructor from pairs of a focus and its amplitude
+    pub fn from_targets<I: IntoIterator<Item = (Vector3, f64)>>(targets: I, constraint: C) -> Self {
+        let (foci, amps) = targets.into_iter().unzip();
+        Self::new(foci, amps, constraint)
+    }
+
     pub fn with_params(
         foci: Vec<Vector3>,
         amps: Vec<f64>,
@@ -63,11 +71,34 @@ impl<B: Backend, T: Transducer, C: Constraint> SDP<B, T, C> {
             repeat,
             backend: PhantomData,
             constraint,
+            phase_reference: None,
+        }
+    }
+
+    /// Shift the phase of the solution so that the field at the `idx`-th focus has zero phase.
+    ///
+    /// Since the absolute phase does not affect the amplitudes, this makes the phase of the field deterministic across runs.
+    pub fn with_phase_reference(self, idx: usize) -> Self {
+        assert!(idx < self.foci.len());
+        Self {
+            phase_reference: Some(idx),
+            ..self
         }
     }
 }
 impl<B: Backend, T: Transducer, C: Constraint> IGain<T> for SDP<B, T, C> {
     fn calc(&mut self, geometry: &Geometry<T>) -> Result<()> {
+        if self.foci.len() == 1 {
+            single_focus(
+                &mut self.props.drives,
+                geometry,
+                self.foci[0],
+                self.amps[0],
+                &self.constraint,
+            );
+            return Ok(());
+        }
+
         let m = self.foci.len();
         let n = geometry.num_devices() * NUM_TRANS_IN_UNIT;
 
@@ -179,6 +210,10 @@ impl<B: Backend, T: Transducer, C: Constraint> IGain<T> for SDP<B, T, C> {
             self.props.drives[tr.id()].phase = phase;
         });
 
+        if let Some(idx) = self.phase_reference {
+            pin_phase(&mut self.props.drives, geometry, self.foci[idx]);
+        }
+
         Ok(())
     }
 }
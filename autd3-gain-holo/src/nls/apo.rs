@@ -0,0 +1,283 @@
+/*
+ * File: apo.rs
+ * Project: nls
+ * Created Date: 15/10/2022
+ * Author: Shun Suzuki
+ * -----
+ * Last Modified: 15/10/2022
+ * Modified By: Shun Suzuki (suzuki@hapis.k.u-tokyo.ac.jp)
+ * -----
+ * Copyright (c) 2022 Shun Suzuki. All rights reserved.
+ *
+ */
+
+use crate::{
+    constraint::Constraint,
+    error::HoloError,
+    macros::{
+        db_to_ratio, generate_propagation_matrix, pin_phase, relative_amp_error, single_focus,
+    },
+    Backend, Complex, MatrixXc, Transpose, VectorX, VectorXc,
+};
+use anyhow::Result;
+use autd3_core::{
+    gain::{Gain, GainProps, IGain},
+    geometry::{Geometry, Transducer, Vector3},
+    NUM_TRANS_IN_UNIT,
+};
+use autd3_traits::Gain;
+use nalgebra::ComplexField;
+use std::{f64::consts::PI, marker::PhantomData};
+
+/// Amplitude penalized optimization.
+///
+/// Minimizes |Gq - p|^2 + λ Σ w_i |q_i|^2, where the weights w_i are updated every iteration to grow with the amplitude of the previous solution.
+/// λ is normalized by the mean of the diagonal of GG^H.
+/// Hence, the penalty mainly acts on the largest amplitudes and the peak drive decreases as λ increases.
+#[derive(Gain)]
+pub struct APO<B: Backend, T: Transducer, C: Constraint> {
+    props: GainProps<T>,
+    foci: Vec<Vector3>,
+    amps: Vec<f64>,
+    lambda: f64,
+    repeat: usize,
+    eps: Option<f64>,
+    backend: PhantomData<B>,
+    constraint: C,
+    phase_reference: Option<usize>,
+}
+
+impl<B: Backend, T: Transducer, C: Constraint> APO<B, T, C> {
+    pub fn new(foci: Vec<Vector3>, amps: Vec<f64>, constraint: C) -> Self {
+        Self::with_params(foci, amps, constraint, 0.01, 20)
+    }
+
+    /// constructor from pairs of a focus and its amplitude
+    pub fn from_targets<I: IntoIterator<Item = (Vector3, f64)>>(targets: I, constraint: C) -> Self {
+        let (foci, amps) = targets.into_iter().unzip();
+        Self::new(foci, amps, constraint)
+    }
+
+    pub fn with_params(
+        foci: Vec<Vector3>,
+        amps: Vec<f64>,
+        constraint: C,
+        lambda: f64,
+        repeat: usize,
+    ) -> Self {
+        assert!(foci.len() == amps.len());
+        Self {
+            props: GainProps::default(),
+            foci,
+            amps,
+            lambda,
+            repeat,
+            eps: None,
+            backend: PhantomData,
+            constraint,
+            phase_reference: None,
+        }
+    }
+
+    pub fn with_power_penalty(self, lambda: f64) -> Self {
+        Self { lambda, ..self }
+    }
+
+    /// Stop the iteration when the relative error of the focal amplitudes falls below `db` decibels.
+    pub fn with_eps_db(self, db: f64) -> Self {
+        Self {
+            eps: Some(db_to_ratio(db)),
+            ..self
+        }
+    }
+
+    /// Shift the phase of the solution so that the field at the `idx`-th focus has zero phase.
+    ///
+    /// Since the absolute phase does not affect the amplitudes, this makes the phase of the field deterministic across runs.
+    pub fn with_phase_reference(self, idx: usize) -> Self {
+        assert!(idx < self.foci.len());
+        Self {
+            phase_reference: Some(idx),
+            ..self
+        }
+    }
+}
+
+impl<B: Backend, T: Transducer, C: Constraint> IGain<T> for APO<B, T, C> {
+    #[allow(clippy::many_single_char_names)]
+    fn calc(&mut self, geometry: &Geometry<T>) -> Result<()> {
+        if self.foci.len() == 1 {
+            single_focus(
+                &mut self.props.drives,
+                geometry,
+                self.foci[0],
+                self.amps[0],
+                &self.constraint,
+            );
+            return Ok(());
+        }
+
+        let m = self.foci.len();
+        let n = geometry.num_devices() * NUM_TRANS_IN_UNIT;
+
+        let g = generate_propagation_matrix(geometry, &self.foci);
+
+        let mut w = VectorX::from_element(n, 1.0);
+        let mut p = VectorXc::from_iterator(m, self.amps.iter().map(|&a| Complex::new(a, 0.)));
+        let mut q = VectorXc::zeros(n);
+        let mut gamma = VectorXc::zeros(m);
+        let mut a = MatrixXc::zeros(m, m);
+        for _ in 0..self.repeat {
+            let gw = MatrixXc::from_fn(m, n, |i, j| g[(i, j)] / w[j]);
+            B::matrix_mul(
+                Transpose::NoTrans,
+                Transpose::ConjTrans,
+                Complex::new(1., 0.),
+                &gw,
+                &g,
+                Complex::new(0., 0.),
+                &mut a,
+            );
+            let scale = (0..m).map(|i| a[(i, i)].re).sum::<f64>() / m as f64;
+            for i in 0..m {
+                a[(i, i)] += self.lambda * scale;
+            }
+
+            let mut x = p.clone();
+            if !B::solve_ch(a.clone(), &mut x) {
+                return Err(HoloError::SolveFailed.into());
+            }
+            B::matrix_mul_vec(
+                Transpose::ConjTrans,
+                Complex::new(1., 0.),
+                &gw,
+                &x,
+                Complex::new(0., 0.),
+                &mut q,
+            );
+
+            B::matrix_mul_vec(
+                Transpose::NoTrans,
+                Complex::new(1., 0.),
+                &g,
+                &q,
+                Complex::new(0., 0.),
+                &mut gamma,
+            );
+            if let Some(eps) = self.eps {
+                if relative_amp_error(&gamma, &self.amps) <= eps {
+                    break;
+                }
+            }
+            for i in 0..m {
+                p[i] = gamma[i] / gamma[i].abs() * self.amps[i];
+            }
+
+            let max_coefficient = B::max_coefficient_c(&q).abs();
+            for j in 0..n {
+                let r = q[j].abs() / max_coefficient;
+                w[j] = 1.0 + r * r * r * r;
+            }
+        }
+
+        let max_coefficient = B::max_coefficient_c(&q).abs();
+        geometry.transducers().for_each(|tr| {
+            let phase = q[tr.id()].argument() / (2.0 * PI) + 0.5;
+            let amp = self.constraint.convert(q[tr.id()].abs(), max_coefficient);
+            self.props.drives[tr.id()].amp = amp;
+            self.props.drives[tr.id()].phase = phase;
+        });
+
+        if let Some(idx) = self.phase_reference {
+            pin_phase(&mut self.props.drives, geometry, self.foci[idx]);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use autd3_core::{
+        geometry::{GeometryBuilder, LegacyTransducer},
+        Drive,
+    };
+
+    use super::*;
+    use crate::{DontCare, FieldEvaluator, NalgebraBackend, Normalize};
+
+    fn setup() -> (Geometry<LegacyTransducer>, Vec<Vector3>) {
+        let mut geometry = GeometryBuilder::new().legacy_mode().build();
+        geometry.add_device(Vector3::zeros(), Vector3::zeros());
+        geometry.add_device(Vector3::new(192., 0., 0.), Vector3::zeros());
+        let center = geometry.center() + Vector3::new(0., 0., 150.);
+        let foci = vec![
+            center + Vector3::new(30., 0., 0.),
+            center - Vector3::new(30., 0., 0.),
+            center + Vector3::new(0., 30., 0.),
+        ];
+        (geometry, foci)
+    }
+
+    fn focal_error(
+        geometry: &Geometry<LegacyTransducer>,
+        drives: &[Drive],
+        foci: &[Vector3],
+        amps: &[f64],
+    ) -> f64 {
+        let p = FieldEvaluator::new(geometry).eval(drives, foci);
+        relative_amp_error(&VectorXc::from_vec(p), amps)
+    }
+
+    #[test]
+    fn eps_db() {
+        let (geometry, foci) = setup();
+        let amps = vec![1.0, 0.8, 0.6];
+
+        let mut g = APO::<NalgebraBackend, _, _>::with_params(
+            foci.clone(),
+            amps.clone(),
+            Normalize {},
+            0.01,
+            100,
+        )
+        .with_eps_db(-20.);
+        g.build(&geometry).unwrap();
+
+        assert!(focal_error(&geometry, g.drives(), &foci, &amps) <= db_to_ratio(-20.));
+    }
+
+    #[test]
+    fn phase_reference() {
+        let (geometry, foci) = setup();
+
+        (0..foci.len()).for_each(|idx| {
+            let mut g = APO::<NalgebraBackend, _, _>::new(foci.clone(), vec![1.0; 3], Normalize {})
+                .with_phase_reference(idx);
+            g.build(&geometry).unwrap();
+
+            let p = FieldEvaluator::new(&geometry).eval(g.drives(), &foci[idx..idx + 1]);
+            assert!(p[0].arg().abs() < 1e-9);
+        });
+    }
+
+    #[test]
+    fn power_penalty() {
+        let (geometry, foci) = setup();
+        let amps = vec![1.0; 3];
+        let peak = |g: &APO<NalgebraBackend, LegacyTransducer, DontCare>| {
+            g.drives().iter().fold(0.0f64, |acc, d| acc.max(d.amp))
+        };
+
+        let mut small = APO::<NalgebraBackend, _, _>::new(foci.clone(), amps.clone(), DontCare {})
+            .with_power_penalty(0.01);
+        small.build(&geometry).unwrap();
+        let mut large = APO::<NalgebraBackend, _, _>::new(foci.clone(), amps.clone(), DontCare {})
+            .with_power_penalty(1.0);
+        large.build(&geometry).unwrap();
+
+        assert!(peak(&large) < peak(&small));
+        assert!(focal_error(&geometry, small.drives(), &foci, &amps) < 0.05);
+        assert!(focal_error(&geometry, large.drives(), &foci, &amps) < 0.05);
+    }
+}
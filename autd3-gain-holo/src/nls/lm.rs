@@ -12,8 +12,10 @@
  */
 
 use crate::{
-    constraint::Constraint, error::HoloError, macros::generate_propagation_matrix, Backend,
-    Complex, MatrixX, MatrixXc, Transpose, VectorX, VectorXc,
+    constraint::Constraint,
+    error::HoloError,
+    macros::{generate_propagation_matrix, pin_phase, single_focus},
+    Backend, Complex, MatrixX, MatrixXc, Transpose, VectorX, VectorXc,
 };
 use anyhow::Result;
 use autd3_core::{
@@ -41,6 +43,7 @@ pub struct LM<B: Backend, T: Transducer, C: Constraint> {
     initial: Vec<f64>,
     backend: PhantomData<B>,
     constraint: C,
+    phase_reference: Option<usize>,
 }
 
 impl<B: Backend, T: Transducer, C: Constraint> LM<B, T, C> {
@@ -48,6 +51,12 @@ impl<B: Backend, T: Transducer, C: Constraint> LM<B, T, C> {
         Self::with_param(foci, amps, constraint, 1e-8, 1e-8, 1e-3, 5, vec![])
     }
 
+    /// constructor from pairs of a focus and its amplitude
+    pub fn from_targets<I: IntoIterator<Item = (Vector3, f64)>>(targets: I, constraint: C) -> Self {
+        let (foci, amps) = targets.into_iter().unzip();
+        Self::new(foci, amps, constraint)
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub fn with_param(
         foci: Vec<Vector3>,
@@ -71,6 +80,7 @@ impl<B: Backend, T: Transducer, C: Constraint> LM<B, T, C> {
             initial,
             backend: PhantomData,
             constraint,
+            phase_reference: None,
         }
     }
 
@@ -114,12 +124,34 @@ impl<B: Backend, T: Transducer, C: Constraint> LM<B, T, C> {
             tth,
         );
     }
+
+    /// Shift the phase of the solution so that the field at the `idx`-th focus has zero phase.
+    ///
+    /// Since the absolute phase does not affect the amplitudes, this makes the phase of the field deterministic across runs.
+    pub fn with_phase_reference(self, idx: usize) -> Self {
+        assert!(idx < self.foci.len());
+        Self {
+            phase_reference: Some(idx),
+            ..self
+        }
+    }
 }
 
 impl<B: Backend, T: Transducer, C: Constraint> IGain<T> for LM<B, T, C> {
     #[allow(clippy::many_single_char_names)]
     #[allow(clippy::unnecessary_wraps)]
     fn calc(&mut self, geometry: &Geometry<T>) -> Result<()> {
+        if self.foci.len() == 1 {
+            single_focus(
+                &mut self.props.drives,
+                geometry,
+                self.foci[0],
+                self.amps[0],
+                &self.constraint,
+            );
+            return Ok(());
+        }
+
         let m = self.foci.len();
         let n = geometry.num_devices() * NUM_TRANS_IN_UNIT;
         let n_param = n + m;
@@ -176,7 +208,7 @@ impl<B: Backend, T: Transducer, C: Constraint> IGain<T> for LM<B, T, C> {
             if !B::solve_g(tmp_mat, &mut h_lm) {
                 return Err(HoloError::SolveFailed.into());
             }
-            if h_lm.norm() <= self.eps_2 * (x.norm() * self.eps_2) {
+            if h_lm.norm() <= self.eps_2 * (x.norm() + self.eps_2) {
                 break;
             }
 
@@ -201,6 +233,9 @@ impl<B: Backend, T: Transducer, C: Constraint> IGain<T> for LM<B, T, C> {
             B::vector_add(mu, &h_lm, &mut tmp_vec);
 
             let l0_lhlm = B::dot(&h_lm, &tmp_vec) / 2.0;
+            if l0_lhlm <= 0.0 {
+                break;
+            }
             let rho = (fx - fx_new) / l0_lhlm;
             fx = fx_new;
 
@@ -221,12 +256,16 @@ impl<B: Backend, T: Transducer, C: Constraint> IGain<T> for LM<B, T, C> {
         }
 
         geometry.transducers().for_each(|tr| {
-            let phase = x[tr.id()].argument() / (2.0 * PI) + 0.5;
+            let phase = x[tr.id()] / (2.0 * PI) + 0.5;
             let amp = self.constraint.convert(1.0, 1.0);
             self.props.drives[tr.id()].amp = amp;
             self.props.drives[tr.id()].phase = phase;
         });
 
+        if let Some(idx) = self.phase_reference {
+            pin_phase(&mut self.props.drives, geometry, self.foci[idx]);
+        }
+
         Ok(())
     }
 }
@@ -11,6 +11,8 @@
  *
  */
 
+mod apo;
 mod lm;
 
+pub use apo::*;
 pub use lm::*;
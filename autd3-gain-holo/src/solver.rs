@@ -0,0 +1,240 @@
+/*
+ * File: solver.rs
+ * Project: src
+ * Created Date: 15/10/2022
+ * Author: Shun Suzuki
+ * -----
+ * Last Modified: 15/10/2022
+ * Modified By: Shun Suzuki (suzuki@hapis.k.u-tokyo.ac.jp)
+ * -----
+ * Copyright (c) 2022 Shun Suzuki. All rights reserved.
+ *
+ */
+
+use std::{
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        Arc, Condvar, Mutex,
+    },
+    thread::{self, JoinHandle},
+};
+
+use anyhow::Result;
+use autd3_core::{
+    gain::Gain,
+    geometry::{Geometry, Transducer, Vector3},
+    Drive,
+};
+
+use crate::error::HoloError;
+
+type Request = (u64, Vec<Vector3>, Vec<f64>, Sender<Result<Vec<Drive>>>);
+
+struct State {
+    pending: Option<Request>,
+    latest: u64,
+    closed: bool,
+}
+
+/// Solver running holo gains on a pool of background threads
+///
+/// Only the latest request matters, e.g., while dragging foci interactively.
+/// When a new request arrives before the previous one is started or finished, the previous one is cancelled, and its receiver is disconnected without a result.
+/// A solve already started cannot be interrupted, so that the other workers of the pool start the new request without waiting for it.
+///
+/// If building the gain fails or panics, e.g., because the numbers of foci and amplitudes differ, the receiver gets an error, and the worker keeps serving the subsequent requests.
+pub struct HoloSolver {
+    state: Arc<(Mutex<State>, Condvar)>,
+    th: Vec<JoinHandle<()>>,
+}
+
+impl HoloSolver {
+    /// constructor with two worker threads
+    ///
+    /// # Arguments
+    ///
+    /// * `geometry` - Geometry
+    /// * `gain` - Function creating a gain from foci and amplitudes, e.g., `|foci, amps| GSPAT::<NalgebraBackend, _, _>::new(foci, amps, Normalize {})`
+    ///
+    pub fn new<T, G, F>(geometry: Geometry<T>, gain: F) -> Self
+    where
+        T: Transducer + Send + Sync + 'static,
+        G: Gain<T>,
+        F: Fn(Vec<Vector3>, Vec<f64>) -> G + Send + Sync + 'static,
+    {
+        Self::with_param(geometry, gain, 2)
+    }
+
+    /// constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `geometry` - Geometry
+    /// * `gain` - Function creating a gain from foci and amplitudes
+    /// * `num_workers` - Number of worker threads, at least 1
+    ///
+    pub fn with_param<T, G, F>(geometry: Geometry<T>, gain: F, num_workers: usize) -> Self
+    where
+        T: Transducer + Send + Sync + 'static,
+        G: Gain<T>,
+        F: Fn(Vec<Vector3>, Vec<f64>) -> G + Send + Sync + 'static,
+    {
+        let state = Arc::new((
+            Mutex::new(State {
+                pending: None,
+                latest: 0,
+                closed: false,
+            }),
+            Condvar::new(),
+        ));
+        let geometry = Arc::new(geometry);
+        let gain = Arc::new(gain);
+        let th = (0..num_workers.max(1))
+            .map(|_| {
+                let worker_state = state.clone();
+                let geometry = geometry.clone();
+                let gain = gain.clone();
+                thread::spawn(move || {
+                    let (lock, cvar) = &*worker_state;
+                    loop {
+                        let (id, foci, amps, tx) = {
+                            let mut state = lock.lock().unwrap();
+                            loop {
+                                if let Some(req) = state.pending.take() {
+                                    break req;
+                                }
+                                if state.closed {
+                                    return;
+                                }
+                                state = cvar.wait(state).unwrap();
+                            }
+                        };
+
+                        let res = catch_unwind(AssertUnwindSafe(|| {
+                            let mut g = gain(foci, amps);
+                            g.build(&geometry).map(|_| g.take_drives())
+                        }))
+                        .unwrap_or_else(|e| {
+                            let msg = e
+                                .downcast_ref::<&str>()
+                                .map(|s| s.to_string())
+                                .or_else(|| e.downcast_ref::<String>().cloned())
+                                .unwrap_or_default();
+                            Err(HoloError::SolverPanicked(msg).into())
+                        });
+
+                        if lock.lock().unwrap().latest == id {
+                            let _ = tx.send(res);
+                        }
+                    }
+                })
+            })
+            .collect();
+        Self { state, th }
+    }
+
+    /// Submit a request. The returned receiver gets the drives unless the request is superseded.
+    pub fn solve_async(&self, foci: Vec<Vector3>, amps: Vec<f64>) -> Receiver<Result<Vec<Drive>>> {
+        let (tx, rx) = channel();
+        let (lock, cvar) = &*self.state;
+        {
+            let mut state = lock.lock().unwrap();
+            state.latest += 1;
+            let id = state.latest;
+            state.pending = Some((id, foci, amps, tx));
+        }
+        cvar.notify_one();
+        rx
+    }
+}
+
+impl Drop for HoloSolver {
+    fn drop(&mut self) {
+        let (lock, cvar) = &*self.state;
+        {
+            let mut state = lock.lock().unwrap();
+            state.pending = None;
+            state.closed = true;
+        }
+        cvar.notify_all();
+        self.th.drain(..).for_each(|th| {
+            let _ = th.join();
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use autd3_core::geometry::{GeometryBuilder, LegacyTransducer};
+
+    use super::*;
+    use crate::{NalgebraBackend, Normalize, GSPAT};
+
+    fn geometry() -> Geometry<LegacyTransducer> {
+        let mut geometry = GeometryBuilder::new().legacy_mode().build();
+        geometry.add_device(Vector3::zeros(), Vector3::zeros());
+        geometry
+    }
+
+    fn foci(x: f64) -> Vec<Vector3> {
+        let center = geometry().center() + Vector3::new(x, 0., 150.);
+        vec![
+            center + Vector3::new(20., 0., 0.),
+            center - Vector3::new(20., 0., 0.),
+        ]
+    }
+
+    fn solver(num_workers: usize) -> HoloSolver {
+        HoloSolver::with_param(
+            geometry(),
+            |foci, amps| {
+                // make sure that the first request is still running when the second one arrives
+                thread::sleep(Duration::from_millis(50));
+                GSPAT::<NalgebraBackend, _, _>::new(foci, amps, Normalize {})
+            },
+            num_workers,
+        )
+    }
+
+    #[test]
+    fn only_latest_delivered() {
+        let mut expect = GSPAT::<NalgebraBackend, _, _>::new(foci(10.), vec![1., 1.], Normalize {});
+        expect.build(&geometry()).unwrap();
+
+        [1, 2].into_iter().for_each(|num_workers| {
+            let solver = solver(num_workers);
+            let first = solver.solve_async(foci(0.), vec![1., 1.]);
+            let second = solver.solve_async(foci(10.), vec![1., 1.]);
+
+            let drives = second.recv().unwrap().unwrap();
+            assert!(first.recv().is_err());
+            drives
+                .iter()
+                .zip(expect.drives().iter())
+                .for_each(|(a, b)| {
+                    assert_eq!(a.amp, b.amp);
+                    assert_eq!(a.phase, b.phase);
+                });
+        });
+    }
+
+    #[test]
+    fn panic_reported_as_error() {
+        let solver = solver(1);
+        let res = solver.solve_async(foci(0.), vec![1.]).recv().unwrap();
+        assert!(matches!(
+            res.unwrap_err().downcast_ref::<HoloError>(),
+            Some(HoloError::SolverPanicked(_))
+        ));
+
+        // the worker is still alive
+        assert!(solver
+            .solve_async(foci(0.), vec![1., 1.])
+            .recv()
+            .unwrap()
+            .is_ok());
+    }
+}
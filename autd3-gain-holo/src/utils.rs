@@ -0,0 +1,776 @@
+/*
+ * File: utils.rs
+ * Project: src
+ * Created Date: 15/10/2022
+ * Author: Shun Suzuki
+ * -----
+ * Last Modified: 15/10/2022
+ * Modified By: Shun Suzuki (suzuki@hapis.k.u-tokyo.ac.jp)
+ * -----
+ * Copyright (c) 2022 Shun Suzuki. All rights reserved.
+ *
+ */
+
+use std::{f64::consts::PI, path::Path};
+
+use anyhow::Result;
+use autd3_core::{
+    geometry::{Geometry, Transducer, Vector3},
+    Drive,
+};
+
+use crate::{error::HoloError, macros::generate_propagation_matrix, Complex, VectorXc};
+
+/// Check that the requested focal amplitudes can be produced by the array.
+///
+/// The capacity of each focus is estimated as the amplitude obtained when all transducers drive it in phase with full amplitude.
+/// Since the transducers are shared among foci, the requested amplitudes are feasible only when the sum of the ratios to the capacities does not exceed 1.
+pub fn check_feasible<T: Transducer>(
+    foci: &[Vector3],
+    amps: &[f64],
+    geometry: &Geometry<T>,
+) -> Result<()> {
+    assert!(foci.len() == amps.len());
+
+    let g = generate_propagation_matrix(geometry, foci);
+    let ratio = g
+        .row_iter()
+        .zip(amps.iter())
+        .map(|(row, &amp)| amp / row.iter().map(|v| v.norm()).sum::<f64>())
+        .sum::<f64>();
+
+    if ratio > 1.0 {
+        let requested = amps.iter().sum::<f64>();
+        return Err(HoloError::Infeasible {
+            requested,
+            max: requested / ratio,
+        }
+        .into());
+    }
+
+    Ok(())
+}
+
+/// Estimate the amplitudes at the foci with the Naive back-projection.
+///
+/// The drives are scaled so that the maximum amplitude of the transducers is 1.
+/// Hence, the targets are likely to be infeasible if the returned amplitudes are far below them.
+pub fn preview_field<T: Transducer>(
+    foci: &[Vector3],
+    amps: &[f64],
+    geometry: &Geometry<T>,
+) -> Vec<f64> {
+    assert!(foci.len() == amps.len());
+
+    let g = generate_propagation_matrix(geometry, foci);
+    let p = VectorXc::from_iterator(amps.len(), amps.iter().map(|&a| Complex::new(a, 0.)));
+    let q = g.adjoint() * p;
+    // camax of complex vectors is the maximum of |re| + |im|, not of the amplitude
+    let max = q.iter().map(|v| v.norm()).fold(0.0, f64::max);
+    if max == 0.0 {
+        return vec![0.0; foci.len()];
+    }
+    (g * q.unscale(max)).iter().map(|v| v.norm()).collect()
+}
+
+/// Evaluate the uniformity of the amplitudes at the foci produced by the drives.
+///
+/// The returned value is the ratio of the minimum amplitude to the maximum one, where 1 means perfectly uniform.
+pub fn focus_uniformity<T: Transducer>(
+    drives: &[Drive],
+    geometry: &Geometry<T>,
+    foci: &[Vector3],
+) -> f64 {
+    let g = generate_propagation_matrix(geometry, foci);
+    let q = VectorXc::from_iterator(
+        drives.len(),
+        drives
+            .iter()
+            .map(|d| Complex::from_polar(d.amp, 2.0 * PI * d.phase)),
+    );
+    let p = g * q;
+    let (min, max) = p
+        .iter()
+        .map(|v| v.norm())
+        .fold((f64::INFINITY, 0.0f64), |(min, max), v| {
+            (min.min(v), max.max(v))
+        });
+    if max == 0.0 {
+        return 0.0;
+    }
+    min / max
+}
+
+/// Estimate the acoustic radiation force on a small rigid sphere at `point` from the Gor'kov potential.
+///
+/// The potential is U ∝ |p|^2 - 3/(2k^2) |∇p|^2, and the force is -∇U evaluated with central differences.
+/// The returned force is in arbitrary units since the propagation model is not calibrated in pascals, so only its direction and relative magnitude are meaningful.
+pub fn radiation_force<T: Transducer>(
+    drives: &[Drive],
+    geometry: &Geometry<T>,
+    point: Vector3,
+) -> Vector3 {
+    let wavenum = match geometry.transducers().next() {
+        Some(tr) => tr.wavenumber(geometry.sound_speed()),
+        None => return Vector3::zeros(),
+    };
+    let h = 0.01 * 2.0 * PI / wavenum;
+    let axes = [Vector3::x(), Vector3::y(), Vector3::z()];
+
+    let centers = axes
+        .iter()
+        .flat_map(|&e| [point + e * h, point - e * h])
+        .collect::<Vec<_>>();
+    let points = centers
+        .iter()
+        .flat_map(|&c| {
+            std::iter::once(c).chain(axes.iter().flat_map(move |&e| [c + e * h, c - e * h]))
+        })
+        .collect::<Vec<_>>();
+
+    let g = generate_propagation_matrix(geometry, &points);
+    let q = VectorXc::from_iterator(
+        drives.len(),
+        drives
+            .iter()
+            .map(|d| Complex::from_polar(d.amp, 2.0 * PI * d.phase)),
+    );
+    let p = g * q;
+
+    let potential = p
+        .as_slice()
+        .chunks(7)
+        .map(|v| {
+            let grad = (0..3)
+                .map(|i| ((v[1 + 2 * i] - v[2 + 2 * i]) / (2.0 * h)).norm_sqr())
+                .sum::<f64>();
+            v[0].norm_sqr() - 1.5 / (wavenum * wavenum) * grad
+        })
+        .collect::<Vec<_>>();
+
+    Vector3::from_iterator((0..3).map(|i| -(potential[2 * i] - potential[2 * i + 1]) / (2.0 * h)))
+}
+
+/// Estimate the direction of the beam emitted with the drives, e.g., to check a plane wave gain.
+///
+/// The transducers are assumed to lie on a plane facing the same direction.
+/// Since the transducer spacing exceeds half the wavelength, the phases are first unwrapped with the directions maximizing the far field, searched within the hemisphere in front of the array.
+/// Then, the direction is obtained from a least-squares fit of the phase gradient across the array, weighted by the amplitudes.
+/// For the same reason, the grating lobes cannot be distinguished from the main lobe by the phases, and the one closest to the normal of the array is returned.
+pub fn beam_direction<T: Transducer>(drives: &[Drive], geometry: &Geometry<T>) -> Vector3 {
+    const GRID: usize = 100;
+
+    let wavenum = match geometry.transducers().next() {
+        Some(tr) => tr.wavenumber(geometry.sound_speed()),
+        None => return Vector3::zeros(),
+    };
+    let normal = geometry
+        .transducers()
+        .map(|tr| tr.z_direction())
+        .sum::<Vector3>()
+        .normalize();
+    let e1 = if normal.x.abs() < 0.9 {
+        Vector3::x()
+    } else {
+        Vector3::y()
+    };
+    let e1 = (e1 - normal * normal.dot(&e1)).normalize();
+    let e2 = normal.cross(&e1);
+
+    let center = geometry.center();
+    let samples = geometry
+        .transducers()
+        .zip(geometry.positions())
+        .map(|(tr, pos)| {
+            let d = &drives[tr.id()];
+            let p = pos - center;
+            (p.dot(&e1), p.dot(&e2), 2.0 * PI * (d.phase - 0.5), d.amp)
+        })
+        .filter(|&(_, _, _, amp)| amp > 0.0)
+        .collect::<Vec<_>>();
+    if samples.is_empty() {
+        return normal;
+    }
+
+    let far_field = |s: f64, t: f64| {
+        samples
+            .iter()
+            .map(|&(x, y, theta, amp)| Complex::from_polar(amp, theta + wavenum * (s * x + t * y)))
+            .sum::<Complex>()
+    };
+    let coarse = (0..=GRID)
+        .flat_map(|i| (0..=GRID).map(move |j| (i, j)))
+        .map(|(i, j)| {
+            (
+                2.0 * i as f64 / GRID as f64 - 1.0,
+                2.0 * j as f64 / GRID as f64 - 1.0,
+            )
+        })
+        .filter(|&(s, t)| s * s + t * t <= 1.0)
+        .map(|(s, t)| (s, t, far_field(s, t).norm()))
+        .collect::<Vec<_>>();
+    let coarse_max = coarse.iter().map(|v| v.2).fold(0.0, f64::max);
+
+    // fit theta = -k (s x + t y) + c with the phases unwrapped around the coarse estimate
+    let refine = |s0: f64, t0: f64| {
+        let c0 = far_field(s0, t0).arg();
+        let mut ata = nalgebra::Matrix3::<f64>::zeros();
+        let mut atb = Vector3::zeros();
+        samples.iter().for_each(|&(x, y, theta, amp)| {
+            let predicted = -wavenum * (s0 * x + t0 * y) + c0;
+            let residual = (theta - predicted + PI).rem_euclid(2.0 * PI) - PI;
+            let row = Vector3::new(x, y, 1.0);
+            ata += row * row.transpose() * amp;
+            atb += row * (predicted + residual) * amp;
+        });
+        match ata.try_inverse() {
+            Some(inv) => {
+                let g = inv * atb;
+                (-g.x / wavenum, -g.y / wavenum)
+            }
+            None => (s0, t0),
+        }
+    };
+
+    // grating lobes fit the phases equally well, so take the one closest to the normal
+    let refined = coarse
+        .iter()
+        .filter(|v| v.2 >= 0.5 * coarse_max)
+        .map(|&(s0, t0, _)| {
+            let (s, t) = refine(s0, t0);
+            (s, t, far_field(s, t).norm())
+        })
+        .collect::<Vec<_>>();
+    let refined_max = refined.iter().map(|v| v.2).fold(0.0, f64::max);
+    let (s, t, _) = refined
+        .iter()
+        .filter(|v| v.2 >= 0.99 * refined_max)
+        .map(|&(s, t, _)| (s, t))
+        .fold((0.0, 0.0, f64::INFINITY), |acc, (s, t)| {
+            let transverse = s * s + t * t;
+            if transverse < acc.2 {
+                (s, t, transverse)
+            } else {
+                acc
+            }
+        });
+
+    let transverse = s * s + t * t;
+    if transverse >= 1.0 {
+        return (e1 * s + e2 * t).normalize();
+    }
+    e1 * s + e2 * t + normal * (1.0 - transverse).sqrt()
+}
+
+/// Render the amplitude of the field on a plane as ASCII art.
+///
+/// Each character represents the amplitude at a point normalized by the maximum on the plane, from ' ' (0) to '@' (max).
+/// The first row is the far side of `plane.2`, so that the output looks like the plane viewed with `plane.1` rightward and `plane.2` upward.
+///
+/// # Arguments
+///
+/// * `drives` - Drives
+/// * `geometry` - Geometry
+/// * `plane` - Origin of the plane and two vectors spanning its width and height, which must be non-zero
+/// * `resolution` - Sampling interval in mm, which must be positive
+///
+pub fn field_ascii<T: Transducer>(
+    drives: &[Drive],
+    geometry: &Geometry<T>,
+    plane: (Vector3, Vector3, Vector3),
+    resolution: f64,
+) -> Result<String> {
+    const CHARS: &[u8] = b" .:-=+*#%@";
+
+    let (origin, u, v) = plane;
+    if !(resolution.is_finite() && resolution > 0.0) {
+        return Err(HoloError::InvalidPlane(format!(
+            "resolution ({}) must be positive",
+            resolution
+        ))
+        .into());
+    }
+    if !(u.norm().is_normal() && v.norm().is_normal()) {
+        return Err(HoloError::InvalidPlane("spanning vectors must be non-zero".to_owned()).into());
+    }
+    let cols = (u.norm() / resolution).floor() as usize + 1;
+    let rows = (v.norm() / resolution).floor() as usize + 1;
+    let du = u.normalize() * resolution;
+    let dv = v.normalize() * resolution;
+
+    let points = (0..rows)
+        .rev()
+        .flat_map(|r| (0..cols).map(move |c| origin + du * c as f64 + dv * r as f64))
+        .collect::<Vec<_>>();
+
+    let g = generate_propagation_matrix(geometry, &points);
+    let q = VectorXc::from_iterator(
+        drives.len(),
+        drives
+            .iter()
+            .map(|d| Complex::from_polar(d.amp, 2.0 * PI * d.phase)),
+    );
+    let p = (g * q).map(|v| v.norm());
+    let max = p.max();
+
+    Ok(p.as_slice()
+        .chunks(cols)
+        .map(|row| {
+            row.iter()
+                .map(|&a| {
+                    let level = if max > 0.0 { a / max } else { 0.0 };
+                    CHARS
+                        [((level * (CHARS.len() - 1) as f64).round() as usize).min(CHARS.len() - 1)]
+                        as char
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+/// Print the amplitude of the field on a plane to stdout as ASCII art. See [field_ascii] for the details.
+pub fn print_field_ascii<T: Transducer>(
+    drives: &[Drive],
+    geometry: &Geometry<T>,
+    plane: (Vector3, Vector3, Vector3),
+    resolution: f64,
+) -> Result<()> {
+    println!("{}", field_ascii(drives, geometry, plane, resolution)?);
+    Ok(())
+}
+
+/// Convert a grayscale image into foci and their amplitudes.
+///
+/// The image is binarized by Floyd-Steinberg dithering, and each remaining pixel becomes a focus whose amplitude is its normalized brightness.
+/// If there are more than `max_foci` pixels, the brightest ones are taken.
+/// An empty or broken image results in [HoloError::InvalidImage].
+///
+/// # Arguments
+///
+/// * `path` - Path to the image in PGM (P2 or P5) format
+/// * `plane` - Center of the image and the directions of its rows (rightward) and columns (downward in the file), which must be non-zero, e.g., `(center, Vector3::x(), Vector3::y())` for the plane parallel to the xy-plane
+/// * `resolution` - Size of a pixel in mm, which must be positive
+/// * `max_foci` - Maximum number of foci
+///
+pub fn foci_from_image<P: AsRef<Path>>(
+    path: P,
+    plane: (Vector3, Vector3, Vector3),
+    resolution: f64,
+    max_foci: usize,
+) -> Result<(Vec<Vector3>, Vec<f64>)> {
+    let (center, u, v) = plane;
+    if !(resolution.is_finite() && resolution > 0.0) {
+        return Err(HoloError::InvalidPlane(format!(
+            "resolution ({}) must be positive",
+            resolution
+        ))
+        .into());
+    }
+    if !(u.norm().is_normal() && v.norm().is_normal()) {
+        return Err(HoloError::InvalidPlane("axes must be non-zero".to_owned()).into());
+    }
+    let (du, dv) = (u.normalize() * resolution, v.normalize() * resolution);
+
+    let (width, height, pixels) = read_pgm(&std::fs::read(path)?)?;
+
+    let mut buf = pixels.clone();
+    let mut lit = Vec::new();
+    for y in 0..height {
+        for x in 0..width {
+            let idx = x + y * width;
+            let old = buf[idx];
+            let new = if old >= 0.5 { 1.0 } else { 0.0 };
+            if new > 0.0 {
+                lit.push(idx);
+            }
+            let err = old - new;
+            let mut diffuse = |dx: isize, dy: usize, w: f64| {
+                let nx = x as isize + dx;
+                let ny = y + dy;
+                if 0 <= nx && (nx as usize) < width && ny < height {
+                    buf[nx as usize + ny * width] += err * w;
+                }
+            };
+            diffuse(1, 0, 7.0 / 16.0);
+            diffuse(-1, 1, 3.0 / 16.0);
+            diffuse(0, 1, 5.0 / 16.0);
+            diffuse(1, 1, 1.0 / 16.0);
+        }
+    }
+
+    lit.sort_by(|&a, &b| pixels[b].partial_cmp(&pixels[a]).unwrap());
+    lit.truncate(max_foci);
+
+    let origin = center - du * (width - 1) as f64 / 2.0 - dv * (height - 1) as f64 / 2.0;
+    Ok(lit
+        .into_iter()
+        .map(|idx| {
+            let (x, y) = (idx % width, idx / width);
+            (origin + du * x as f64 + dv * y as f64, pixels[idx])
+        })
+        .unzip())
+}
+
+fn read_pgm(data: &[u8]) -> Result<(usize, usize, Vec<f64>)> {
+    let invalid = |msg: &str| HoloError::InvalidImage(msg.to_owned());
+
+    let mut pos = 0;
+    let mut next_token = || -> Option<String> {
+        loop {
+            while pos < data.len() && data[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+            if pos < data.len() && data[pos] == b'#' {
+                while pos < data.len() && data[pos] != b'\n' {
+                    pos += 1;
+                }
+                continue;
+            }
+            break;
+        }
+        let start = pos;
+        while pos < data.len() && !data[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        (start < pos).then(|| String::from_utf8_lossy(&data[start..pos]).into_owned())
+    };
+
+    let magic = next_token().ok_or_else(|| invalid("empty file"))?;
+    let mut header = [0usize; 3];
+    for v in header.iter_mut() {
+        *v = next_token()
+            .and_then(|t| t.parse().ok())
+            .ok_or_else(|| invalid("broken header"))?;
+    }
+    let [width, height, max_val] = header;
+    if width == 0 || height == 0 {
+        return Err(invalid("empty image").into());
+    }
+    if max_val == 0 || max_val > u16::MAX as usize {
+        return Err(invalid("max value must be in 1 to 65535").into());
+    }
+
+    let num = width * height;
+    let values: Vec<usize> = match magic.as_str() {
+        "P2" => (0..num)
+            .map(|_| next_token().and_then(|t| t.parse().ok()))
+            .collect::<Option<_>>()
+            .ok_or_else(|| invalid("insufficient pixel data"))?,
+        "P5" => {
+            let body = &data[(pos + 1).min(data.len())..];
+            let bytes = if max_val < 256 { 1 } else { 2 };
+            if body.len() < num * bytes {
+                return Err(invalid("insufficient pixel data").into());
+            }
+            body.chunks(bytes)
+                .take(num)
+                .map(|c| c.iter().fold(0, |acc, &b| (acc << 8) | b as usize))
+                .collect()
+        }
+        _ => return Err(invalid("only PGM (P2 or P5) is supported").into()),
+    };
+
+    Ok((
+        width,
+        height,
+        values
+            .into_iter()
+            .map(|v| v.min(max_val) as f64 / max_val as f64)
+            .collect(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use autd3_core::{gain::Gain, geometry::GeometryBuilder};
+
+    use super::*;
+    use crate::{FieldEvaluator, Naive, NalgebraBackend, Normalize, GSPAT};
+
+    #[test]
+    fn check_feasible_against_capacity() {
+        let mut geometry = GeometryBuilder::new().legacy_mode().build();
+        geometry.add_device(Vector3::zeros(), Vector3::zeros());
+        let center = geometry.center() + Vector3::new(0., 0., 150.);
+        let foci = [
+            center + Vector3::new(20., 0., 0.),
+            center - Vector3::new(20., 0., 0.),
+        ];
+        let capacity = generate_propagation_matrix(&geometry, &foci)
+            .row_iter()
+            .map(|row| row.iter().map(|v| v.norm()).sum::<f64>())
+            .collect::<Vec<_>>();
+
+        // each focus takes 40 % of the array
+        let amps = capacity.iter().map(|c| 0.4 * c).collect::<Vec<_>>();
+        assert!(check_feasible(&foci, &amps, &geometry).is_ok());
+
+        // each focus takes 60 % of the array
+        let amps = capacity.iter().map(|c| 0.6 * c).collect::<Vec<_>>();
+        let requested = amps.iter().sum::<f64>();
+        match check_feasible(&foci, &amps, &geometry)
+            .unwrap_err()
+            .downcast_ref::<HoloError>()
+        {
+            Some(&HoloError::Infeasible { requested: r, max }) => {
+                assert_eq!(r, requested);
+                assert!((max - requested / 1.2).abs() < 1e-9 * requested);
+            }
+            _ => panic!("expected HoloError::Infeasible"),
+        }
+    }
+
+    #[test]
+    fn beam_direction_of_plane_wave() {
+        let mut geometry = GeometryBuilder::new().legacy_mode().build();
+        geometry.add_device(Vector3::zeros(), Vector3::zeros());
+        geometry.add_device(Vector3::new(192., 0., 0.), Vector3::zeros());
+
+        [
+            Vector3::z(),
+            Vector3::new(0.3, 0.2, 1.0).normalize(),
+            Vector3::new(-0.2, 0.4, 1.0).normalize(),
+        ]
+        .into_iter()
+        .for_each(|dir| {
+            // the phases of a focus placed far away in the direction of `dir`
+            let drives = geometry
+                .transducers()
+                .map(|tr| Drive {
+                    phase: tr.align_phase_at(-dir.dot(tr.position()), geometry.sound_speed()),
+                    amp: 1.0,
+                    cycle: tr.cycle(),
+                })
+                .collect::<Vec<_>>();
+            let estimated = beam_direction(&drives, &geometry);
+            assert!(estimated.angle(&dir).to_degrees() < 1.0);
+        });
+    }
+
+    #[test]
+    fn field_ascii_brightest_at_focus() {
+        let mut geometry = GeometryBuilder::new().legacy_mode().build();
+        geometry.add_device(Vector3::zeros(), Vector3::zeros());
+        let focus = geometry.center() + Vector3::new(0., 0., 150.);
+        let drives = geometry
+            .transducers()
+            .map(|tr| Drive {
+                phase: tr.align_phase_at((focus - tr.position()).norm(), geometry.sound_speed()),
+                amp: 1.0,
+                cycle: tr.cycle(),
+            })
+            .collect::<Vec<_>>();
+
+        let origin = focus - Vector3::new(40., 40., 0.);
+        let plane = (origin, Vector3::new(80., 0., 0.), Vector3::new(0., 80., 0.));
+        let field = field_ascii(&drives, &geometry, plane, 4.0).unwrap();
+
+        let rows = field.lines().collect::<Vec<_>>();
+        assert_eq!(rows.len(), 21);
+        assert!(rows.iter().all(|r| r.len() == 21));
+        assert_eq!(rows[10].as_bytes()[10], b'@');
+        rows.iter().enumerate().for_each(|(r, row)| {
+            row.bytes().enumerate().for_each(|(c, ch)| {
+                if ch == b'@' {
+                    assert!(r.abs_diff(10) <= 1 && c.abs_diff(10) <= 1);
+                }
+            })
+        });
+        assert_eq!(rows[0].as_bytes()[0], b' ');
+    }
+
+    #[test]
+    fn field_ascii_invalid_plane() {
+        let mut geometry = GeometryBuilder::new().legacy_mode().build();
+        geometry.add_device(Vector3::zeros(), Vector3::zeros());
+        let drives = geometry
+            .transducers()
+            .map(|tr| Drive {
+                phase: 0.0,
+                amp: 1.0,
+                cycle: tr.cycle(),
+            })
+            .collect::<Vec<_>>();
+
+        let (x, y) = (Vector3::x() * 10., Vector3::y() * 10.);
+        [
+            ((Vector3::zeros(), x, y), 0.0),
+            ((Vector3::zeros(), x, y), -1.0),
+            ((Vector3::zeros(), x, y), f64::NAN),
+            ((Vector3::zeros(), Vector3::zeros(), y), 1.0),
+            ((Vector3::zeros(), x, Vector3::zeros()), 1.0),
+        ]
+        .into_iter()
+        .for_each(|(plane, resolution)| {
+            let res = field_ascii(&drives, &geometry, plane, resolution);
+            assert!(matches!(
+                res.unwrap_err().downcast_ref::<HoloError>(),
+                Some(HoloError::InvalidPlane(_))
+            ));
+        });
+    }
+
+    fn foci_from_pgm_on(
+        name: &str,
+        pgm: &str,
+        plane: (Vector3, Vector3, Vector3),
+        resolution: f64,
+        max_foci: usize,
+    ) -> Result<(Vec<Vector3>, Vec<f64>)> {
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, pgm).unwrap();
+        let res = foci_from_image(&path, plane, resolution, max_foci);
+        std::fs::remove_file(&path).unwrap();
+        res
+    }
+
+    fn foci_from_pgm(name: &str, pgm: &str, max_foci: usize) -> Result<(Vec<Vector3>, Vec<f64>)> {
+        let plane = (Vector3::zeros(), Vector3::x(), Vector3::y());
+        foci_from_pgm_on(name, pgm, plane, 10.0, max_foci)
+    }
+
+    #[test]
+    fn foci_from_fixture_image() {
+        const PGM: &str = "P2\n# fixture\n3 2\n255\n255 0 128\n0 200 0\n";
+
+        let (foci, amps) = foci_from_pgm("autd3_holo_foci_test.pgm", PGM, 10).unwrap();
+        assert_eq!(
+            foci,
+            vec![
+                Vector3::new(-10., -5., 0.),
+                Vector3::new(0., 5., 0.),
+                Vector3::new(10., -5., 0.)
+            ]
+        );
+        assert_eq!(amps, vec![1.0, 200. / 255., 128. / 255.]);
+
+        let (foci, amps) = foci_from_pgm("autd3_holo_foci_max_test.pgm", PGM, 2).unwrap();
+        assert_eq!(
+            foci,
+            vec![Vector3::new(-10., -5., 0.), Vector3::new(0., 5., 0.)]
+        );
+        assert_eq!(amps, vec![1.0, 200. / 255.]);
+    }
+
+    #[test]
+    fn foci_from_empty_image() {
+        ["P2\n0 0\n255\n", "P2\n3 0\n255\n", "P5\n0 2\n255\n"]
+            .into_iter()
+            .for_each(|pgm| {
+                let res = foci_from_pgm("autd3_holo_foci_empty_test.pgm", pgm, 10);
+                assert!(matches!(
+                    res.unwrap_err().downcast_ref::<HoloError>(),
+                    Some(HoloError::InvalidImage(_))
+                ));
+            });
+    }
+
+    #[test]
+    fn foci_from_image_on_plane() {
+        const PGM: &str = "P2\n3 2\n255\n255 0 128\n0 200 0\n";
+
+        // rows along y and columns along -z around (0, 0, 100)
+        let plane = (Vector3::new(0., 0., 100.), Vector3::y() * 2., -Vector3::z());
+        let (foci, amps) =
+            foci_from_pgm_on("autd3_holo_foci_plane_test.pgm", PGM, plane, 10.0, 10).unwrap();
+        assert_eq!(
+            foci,
+            vec![
+                Vector3::new(0., -10., 105.),
+                Vector3::new(0., 0., 95.),
+                Vector3::new(0., 10., 105.)
+            ]
+        );
+        assert_eq!(amps, vec![1.0, 200. / 255., 128. / 255.]);
+
+        [
+            ((Vector3::zeros(), Vector3::zeros(), Vector3::y()), 10.0),
+            ((Vector3::zeros(), Vector3::x(), Vector3::zeros()), 10.0),
+            ((Vector3::zeros(), Vector3::x(), Vector3::y()), 0.0),
+        ]
+        .into_iter()
+        .for_each(|(plane, resolution)| {
+            let res = foci_from_pgm_on(
+                "autd3_holo_foci_invalid_plane_test.pgm",
+                PGM,
+                plane,
+                resolution,
+                10,
+            );
+            assert!(matches!(
+                res.unwrap_err().downcast_ref::<HoloError>(),
+                Some(HoloError::InvalidPlane(_))
+            ));
+        });
+    }
+
+    #[test]
+    fn preview_field_tracks_gspat() {
+        let mut geometry = GeometryBuilder::new().legacy_mode().build();
+        geometry.add_device(Vector3::zeros(), Vector3::zeros());
+        geometry.add_device(Vector3::new(192., 0., 0.), Vector3::zeros());
+        let focus = geometry.center() + Vector3::new(10., 5., 150.);
+
+        let preview = preview_field(&[focus], &[1.0], &geometry);
+
+        let mut g = GSPAT::<NalgebraBackend, _, _>::new(vec![focus], vec![1.0], Normalize {});
+        g.build(&geometry).unwrap();
+        let p = FieldEvaluator::new(&geometry).eval(g.drives(), &[focus]);
+
+        assert_eq!(preview.len(), 1);
+        assert!((preview[0] - p[0].norm()).abs() < 1e-9 * p[0].norm());
+    }
+
+    #[test]
+    fn gspat_more_uniform_than_naive() {
+        let mut geometry = GeometryBuilder::new().legacy_mode().build();
+        geometry.add_device(Vector3::zeros(), Vector3::zeros());
+        geometry.add_device(Vector3::new(192., 0., 0.), Vector3::zeros());
+        let center = geometry.center() + Vector3::new(0., 0., 150.);
+        let foci = vec![
+            center + Vector3::new(40., 0., 0.),
+            center - Vector3::new(20., 10., 0.),
+            center + Vector3::new(0., 30., 20.),
+        ];
+        let amps = vec![1.0; 3];
+
+        let mut gspat =
+            GSPAT::<NalgebraBackend, _, _>::new(foci.clone(), amps.clone(), Normalize {});
+        gspat.build(&geometry).unwrap();
+        let mut naive = Naive::<NalgebraBackend, _, _>::new(foci.clone(), amps, Normalize {});
+        naive.build(&geometry).unwrap();
+
+        let gspat = focus_uniformity(gspat.drives(), &geometry, &foci);
+        let naive = focus_uniformity(naive.drives(), &geometry, &foci);
+        assert!(gspat > 0.95);
+        assert!(gspat > naive);
+    }
+
+    #[test]
+    fn twin_trap_restoring_force() {
+        let mut geometry = GeometryBuilder::new().legacy_mode().build();
+        geometry.add_device(Vector3::zeros(), Vector3::zeros());
+        geometry.add_device(Vector3::new(192., 0., 0.), Vector3::zeros());
+        let trap = geometry.center() + Vector3::new(0., 0., 150.);
+
+        // focus with the phases of the half of the array on the -x side shifted by π
+        let drives = geometry
+            .transducers()
+            .map(|tr| Drive {
+                phase: tr.align_phase_at((trap - tr.position()).norm(), geometry.sound_speed())
+                    + if tr.position().x < trap.x { 0.5 } else { 0.0 },
+                amp: 1.0,
+                cycle: tr.cycle(),
+            })
+            .collect::<Vec<_>>();
+
+        [Vector3::x(), Vector3::y()].into_iter().for_each(|dir| {
+            let offset = dir * 1.0;
+            let force = radiation_force(&drives, &geometry, trap + offset);
+            assert!(force.dot(&dir) < 0.);
+            let force = radiation_force(&drives, &geometry, trap - offset);
+            assert!(force.dot(&dir) > 0.);
+        });
+    }
+}
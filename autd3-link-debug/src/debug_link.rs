@@ -20,12 +20,16 @@ use autd3_firmware_emulator::Emulator;
 
 pub struct Debug {
     emulator: Emulator,
+    is_open: bool,
+    labels: Vec<String>,
 }
 
 impl Debug {
     pub fn new() -> Self {
         Self {
             emulator: Emulator::new(),
+            is_open: false,
+            labels: vec![],
         }
     }
 }
@@ -37,11 +41,18 @@ impl Link for Debug {
         self.emulator.init(geometry.num_devices());
         log::info!("Initialize emulator");
 
+        self.labels = (0..geometry.num_devices())
+            .map(|i| geometry.device_label(i).to_owned())
+            .collect();
+
+        self.is_open = true;
+
         Ok(())
     }
 
     fn close(&mut self) -> anyhow::Result<()> {
         log::info!("Close Debug link");
+        self.is_open = false;
         Ok(())
     }
 
@@ -53,7 +64,10 @@ impl Link for Debug {
         self.emulator.send(tx);
 
         self.emulator.cpus().iter().for_each(|cpu| {
-            log::info!("Status: {}", cpu.id());
+            match self.labels.get(cpu.id()) {
+                Some(label) => log::info!("Status: {}", label),
+                None => log::info!("Status: {}", cpu.id()),
+            }
             let fpga = cpu.fpga();
             if fpga.is_stm_mode() {
                 if fpga.is_stm_gain_mode() {
@@ -139,7 +153,7 @@ impl Link for Debug {
     }
 
     fn is_open(&self) -> bool {
-        true
+        self.is_open
     }
 }
 
@@ -0,0 +1,164 @@
+/*
+ * File: async_remote_link.rs
+ * Project: src
+ * Created Date: 15/10/2022
+ * Author: Shun Suzuki
+ * -----
+ * Last Modified: 15/10/2022
+ * Modified By: Shun Suzuki (suzuki@hapis.k.u-tokyo.ac.jp)
+ * -----
+ * Copyright (c) 2022 Shun Suzuki. All rights reserved.
+ *
+ */
+
+use std::{
+    future::Future,
+    io::{self, ErrorKind},
+    net::SocketAddr,
+    time::Duration,
+};
+
+use anyhow::Result;
+use autd3_core::{
+    async_link::AsyncLink,
+    error::AUTDInternalError,
+    geometry::{Geometry, Transducer},
+    RxDatagram, TxDatagram,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+use crate::{error::RemoteError, protocol::*};
+
+/// Asynchronous version of [Remote](crate::Remote) on tokio, speaking the same protocol
+///
+/// The timeout requires the time driver of the tokio runtime to be enabled.
+pub struct AsyncRemote {
+    addr: SocketAddr,
+    cycle_ticks: u16,
+    timeout: Duration,
+    stream: Option<TcpStream>,
+    num_devices: usize,
+}
+
+async fn timeout<R, F: Future<Output = io::Result<R>>>(duration: Duration, f: F) -> Result<R> {
+    match tokio::time::timeout(duration, f).await {
+        Ok(Ok(r)) => Ok(r),
+        Ok(Err(e)) if e.kind() == ErrorKind::UnexpectedEof => Err(RemoteError::Disconnected.into()),
+        Ok(Err(e)) => Err(e.into()),
+        Err(_) => Err(io::Error::from(ErrorKind::TimedOut).into()),
+    }
+}
+
+impl AsyncRemote {
+    /// constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - Address of the server
+    /// * `cycle_ticks` - Cycle of EtherCAT communication on the server in units of [EC_CYCLE_TIME_BASE_NANO_SEC](autd3_core::EC_CYCLE_TIME_BASE_NANO_SEC)
+    ///
+    pub fn new(addr: SocketAddr, cycle_ticks: u16) -> Self {
+        Self {
+            addr,
+            cycle_ticks,
+            timeout: Duration::from_secs(5),
+            stream: None,
+            num_devices: 0,
+        }
+    }
+
+    /// Set the timeout of connecting to the server, which is also applied to each read and write. Default is 5 seconds.
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        Self { timeout, ..self }
+    }
+
+    pub fn cycle_ticks(&self) -> u16 {
+        self.cycle_ticks
+    }
+
+    async fn connect(&mut self) -> Result<()> {
+        let mut stream = timeout(self.timeout, TcpStream::connect(self.addr)).await?;
+        stream.set_nodelay(true)?;
+
+        let req = handshake_request(self.num_devices, self.cycle_ticks);
+        timeout(self.timeout, stream.write_all(&req)).await?;
+        let mut res = [0x00; HANDSHAKE_RESPONSE_SIZE];
+        timeout(self.timeout, stream.read_exact(&mut res)).await?;
+        check_handshake_response(&res)?;
+
+        self.stream = Some(stream);
+        Ok(())
+    }
+
+    async fn request_send(stream: &mut TcpStream, duration: Duration, req: &[u8]) -> Result<bool> {
+        timeout(duration, stream.write_all(req)).await?;
+
+        let mut res = [0x00];
+        timeout(duration, stream.read_exact(&mut res)).await?;
+        parse_status(res[0])
+    }
+
+    async fn request_receive(
+        stream: &mut TcpStream,
+        duration: Duration,
+        num_devices: usize,
+        rx: &mut RxDatagram,
+    ) -> Result<bool> {
+        timeout(duration, stream.write_all(&[REQ_RECEIVE])).await?;
+
+        let mut res = [0x00];
+        timeout(duration, stream.read_exact(&mut res)).await?;
+        if !parse_status(res[0])? {
+            return Ok(false);
+        }
+
+        let mut buf = vec![0x00; num_devices * 2];
+        timeout(duration, stream.read_exact(&mut buf)).await?;
+        copy_rx(&buf, rx);
+        Ok(true)
+    }
+}
+
+impl AsyncLink for AsyncRemote {
+    fn open<T: Transducer>(
+        &mut self,
+        geometry: &Geometry<T>,
+    ) -> impl Future<Output = Result<()>> + Send {
+        self.num_devices = geometry.num_devices();
+        self.connect()
+    }
+
+    async fn close(&mut self) -> Result<()> {
+        if let Some(mut stream) = self.stream.take() {
+            timeout(self.timeout, stream.write_all(&[REQ_CLOSE])).await?;
+            timeout(self.timeout, stream.shutdown()).await?;
+        }
+        Ok(())
+    }
+
+    async fn send(&mut self, tx: &TxDatagram) -> Result<bool> {
+        let req = send_request(tx);
+        let stream = self.stream.as_mut().ok_or(AUTDInternalError::LinkClosed)?;
+        let res = Self::request_send(stream, self.timeout, &req).await;
+        if res.is_err() {
+            self.stream = None;
+        }
+        res
+    }
+
+    async fn receive(&mut self, rx: &mut RxDatagram) -> Result<bool> {
+        let stream = self.stream.as_mut().ok_or(AUTDInternalError::LinkClosed)?;
+        let res = Self::request_receive(stream, self.timeout, self.num_devices, rx).await;
+        if res.is_err() {
+            self.stream = None;
+        }
+        res
+    }
+
+    fn is_open(&self) -> bool {
+        self.stream.is_some()
+    }
+}
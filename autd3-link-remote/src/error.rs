@@ -0,0 +1,32 @@
+/*
+ * File: error.rs
+ * Project: src
+ * Created Date: 15/10/2022
+ * Author: Shun Suzuki
+ * -----
+ * Last Modified: 15/10/2022
+ * Modified By: Shun Suzuki (suzuki@hapis.k.u-tokyo.ac.jp)
+ * -----
+ * Copyright (c) 2022 Shun Suzuki. All rights reserved.
+ *
+ */
+
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum RemoteError {
+    #[error("The server is not an AUTD server")]
+    InvalidServer,
+    #[error("The server refused the connection (status: {0})")]
+    Refused(u8),
+    #[error("The connection was closed by the server")]
+    Disconnected,
+    #[error("Unexpected response from the server: {0}")]
+    UnexpectedResponse(u8),
+    #[error("The client is not an AUTD client")]
+    InvalidClient,
+    #[error("Unexpected request from the client: {0}")]
+    UnexpectedRequest(u8),
+    #[error("Invalid size of the data from the client: {0}")]
+    InvalidSize(usize),
+}
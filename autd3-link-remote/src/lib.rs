@@ -0,0 +1,25 @@
+/*
+ * File: lib.rs
+ * Project: src
+ * Created Date: 15/10/2022
+ * Author: Shun Suzuki
+ * -----
+ * Last Modified: 15/10/2022
+ * Modified By: Shun Suzuki (suzuki@hapis.k.u-tokyo.ac.jp)
+ * -----
+ * Copyright (c) 2022 Shun Suzuki. All rights reserved.
+ *
+ */
+
+#[cfg(feature = "async")]
+mod async_remote_link;
+mod error;
+mod protocol;
+mod remote_link;
+mod server;
+
+#[cfg(feature = "async")]
+pub use async_remote_link::AsyncRemote;
+pub use error::RemoteError;
+pub use remote_link::Remote;
+pub use server::RemoteServer;
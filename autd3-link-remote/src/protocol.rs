@@ -0,0 +1,74 @@
+/*
+ * File: protocol.rs
+ * Project: src
+ * Created Date: 15/10/2022
+ * Author: Shun Suzuki
+ * -----
+ * Last Modified: 15/10/2022
+ * Modified By: Shun Suzuki (suzuki@hapis.k.u-tokyo.ac.jp)
+ * -----
+ * Copyright (c) 2022 Shun Suzuki. All rights reserved.
+ *
+ */
+
+use anyhow::Result;
+use autd3_core::{RxDatagram, TxDatagram};
+
+use crate::error::RemoteError;
+
+pub(crate) const MAGIC: &[u8; 4] = b"AUTD";
+
+pub(crate) const HANDSHAKE_REQUEST_SIZE: usize = 8;
+pub(crate) const HANDSHAKE_RESPONSE_SIZE: usize = 5;
+
+pub(crate) const STATUS_OK: u8 = 0x00;
+pub(crate) const STATUS_DEVICE_MISMATCH: u8 = 0x01;
+
+pub(crate) const REQ_CLOSE: u8 = 0x00;
+pub(crate) const REQ_SEND: u8 = 0x01;
+pub(crate) const REQ_RECEIVE: u8 = 0x02;
+
+pub(crate) fn handshake_request(num_devices: usize, cycle_ticks: u16) -> Vec<u8> {
+    let mut req = MAGIC.to_vec();
+    req.extend_from_slice(&(num_devices as u16).to_le_bytes());
+    req.extend_from_slice(&cycle_ticks.to_le_bytes());
+    req
+}
+
+pub(crate) fn check_handshake_response(res: &[u8; HANDSHAKE_RESPONSE_SIZE]) -> Result<()> {
+    if &res[..4] != MAGIC {
+        return Err(RemoteError::InvalidServer.into());
+    }
+    if res[4] != STATUS_OK {
+        return Err(RemoteError::Refused(res[4]).into());
+    }
+    Ok(())
+}
+
+pub(crate) fn send_request(tx: &TxDatagram) -> Vec<u8> {
+    let data = &tx.data()[..tx.size()];
+    let mut req = Vec::with_capacity(1 + std::mem::size_of::<u32>() + data.len());
+    req.push(REQ_SEND);
+    req.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    req.extend_from_slice(data);
+    req
+}
+
+/// Parse the status of the response to send or receive requests
+pub(crate) fn parse_status(status: u8) -> Result<bool> {
+    match status {
+        0 => Ok(false),
+        1 => Ok(true),
+        v => Err(RemoteError::UnexpectedResponse(v).into()),
+    }
+}
+
+pub(crate) fn copy_rx(buf: &[u8], rx: &mut RxDatagram) {
+    rx.messages_mut()
+        .iter_mut()
+        .zip(buf.chunks_exact(2))
+        .for_each(|(msg, b)| {
+            msg.ack = b[0];
+            msg.msg_id = b[1];
+        });
+}
@@ -0,0 +1,161 @@
+/*
+ * File: remote_link.rs
+ * Project: src
+ * Created Date: 15/10/2022
+ * Author: Shun Suzuki
+ * -----
+ * Last Modified: 15/10/2022
+ * Modified By: Shun Suzuki (suzuki@hapis.k.u-tokyo.ac.jp)
+ * -----
+ * Copyright (c) 2022 Shun Suzuki. All rights reserved.
+ *
+ */
+
+use std::{
+    io::{ErrorKind, Read, Write},
+    net::{Shutdown, SocketAddr, TcpStream},
+    time::Duration,
+};
+
+use anyhow::Result;
+use autd3_core::{
+    error::AUTDInternalError,
+    geometry::{Geometry, Transducer},
+    link::Link,
+    RxDatagram, TxDatagram,
+};
+
+use crate::{error::RemoteError, protocol::*};
+
+/// Link to control the devices via a server on the PC connected to them over TCP
+///
+/// The server, e.g., [RemoteServer](crate::RemoteServer), is expected to speak the following protocol, where the integers are little endian.
+///
+/// * Open: the client sends `"AUTD"`, the number of devices (u16), and the cycle ticks (u16), and the server replies `"AUTD"` and the status (u8), which is 0 on success, or 1 if the number of devices differs.
+/// * Send: the client sends 0x01, the size (u32), and the bytes of [TxDatagram], and the server replies 1 if sent, or 0 otherwise.
+/// * Receive: the client sends 0x02, and the server replies 1 followed by ack and msg_id of each device, or 0 alone if there is no data.
+/// * Close: the client sends 0x00 and shuts down the connection.
+///
+/// When the connection is lost, e.g., reset by the server, [send](Link::send) and [receive](Link::receive) return the error, and the link is closed.
+pub struct Remote {
+    addr: SocketAddr,
+    cycle_ticks: u16,
+    timeout: Duration,
+    stream: Option<TcpStream>,
+    num_devices: usize,
+}
+
+fn read_exact(stream: &mut TcpStream, buf: &mut [u8]) -> Result<()> {
+    stream.read_exact(buf).map_err(|e| match e.kind() {
+        ErrorKind::UnexpectedEof => RemoteError::Disconnected.into(),
+        _ => e.into(),
+    })
+}
+
+impl Remote {
+    /// constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - Address of the server
+    /// * `cycle_ticks` - Cycle of EtherCAT communication on the server in units of [EC_CYCLE_TIME_BASE_NANO_SEC](autd3_core::EC_CYCLE_TIME_BASE_NANO_SEC)
+    ///
+    pub fn new(addr: SocketAddr, cycle_ticks: u16) -> Self {
+        Self {
+            addr,
+            cycle_ticks,
+            timeout: Duration::from_secs(5),
+            stream: None,
+            num_devices: 0,
+        }
+    }
+
+    /// Set the timeout of connecting to the server, which is also applied to each read and write. Default is 5 seconds.
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        Self { timeout, ..self }
+    }
+
+    pub fn cycle_ticks(&self) -> u16 {
+        self.cycle_ticks
+    }
+
+    fn handshake(&self, stream: &mut TcpStream) -> Result<()> {
+        stream.write_all(&handshake_request(self.num_devices, self.cycle_ticks))?;
+
+        let mut res = [0x00; HANDSHAKE_RESPONSE_SIZE];
+        read_exact(stream, &mut res)?;
+        check_handshake_response(&res)
+    }
+
+    fn request_send(stream: &mut TcpStream, tx: &TxDatagram) -> Result<bool> {
+        stream.write_all(&send_request(tx))?;
+
+        let mut res = [0x00];
+        read_exact(stream, &mut res)?;
+        parse_status(res[0])
+    }
+
+    fn request_receive(
+        stream: &mut TcpStream,
+        num_devices: usize,
+        rx: &mut RxDatagram,
+    ) -> Result<bool> {
+        stream.write_all(&[REQ_RECEIVE])?;
+
+        let mut res = [0x00];
+        read_exact(stream, &mut res)?;
+        if !parse_status(res[0])? {
+            return Ok(false);
+        }
+
+        let mut buf = vec![0x00; num_devices * 2];
+        read_exact(stream, &mut buf)?;
+        copy_rx(&buf, rx);
+        Ok(true)
+    }
+}
+
+impl Link for Remote {
+    fn open<T: Transducer>(&mut self, geometry: &Geometry<T>) -> Result<()> {
+        let mut stream = TcpStream::connect_timeout(&self.addr, self.timeout)?;
+        stream.set_read_timeout(Some(self.timeout))?;
+        stream.set_write_timeout(Some(self.timeout))?;
+        stream.set_nodelay(true)?;
+
+        self.num_devices = geometry.num_devices();
+        self.handshake(&mut stream)?;
+        self.stream = Some(stream);
+
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<()> {
+        if let Some(mut stream) = self.stream.take() {
+            stream.write_all(&[REQ_CLOSE])?;
+            stream.shutdown(Shutdown::Both)?;
+        }
+        Ok(())
+    }
+
+    fn send(&mut self, tx: &TxDatagram) -> Result<bool> {
+        let stream = self.stream.as_mut().ok_or(AUTDInternalError::LinkClosed)?;
+        let res = Self::request_send(stream, tx);
+        if res.is_err() {
+            self.stream = None;
+        }
+        res
+    }
+
+    fn receive(&mut self, rx: &mut RxDatagram) -> Result<bool> {
+        let stream = self.stream.as_mut().ok_or(AUTDInternalError::LinkClosed)?;
+        let res = Self::request_receive(stream, self.num_devices, rx);
+        if res.is_err() {
+            self.stream = None;
+        }
+        res
+    }
+
+    fn is_open(&self) -> bool {
+        self.stream.is_some()
+    }
+}
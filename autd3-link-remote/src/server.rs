@@ -0,0 +1,239 @@
+/*
+ * File: server.rs
+ * Project: src
+ * Created Date: 15/10/2022
+ * Author: Shun Suzuki
+ * -----
+ * Last Modified: 15/10/2022
+ * Modified By: Shun Suzuki (suzuki@hapis.k.u-tokyo.ac.jp)
+ * -----
+ * Copyright (c) 2022 Shun Suzuki. All rights reserved.
+ *
+ */
+
+use std::{
+    io::{ErrorKind, Read, Write},
+    net::{TcpListener, TcpStream},
+};
+
+use anyhow::Result;
+use autd3_core::{link::Link, Body, GlobalHeader, RxDatagram, TxDatagram};
+
+use crate::{error::RemoteError, protocol::*};
+
+/// Server for [Remote](crate::Remote), which forwards the requests of a client to a link on the PC connected to the devices
+///
+/// The link must be opened in advance. The cycle ticks requested by the client is ignored, since it is the setting of the link on this side.
+pub struct RemoteServer<L: Link> {
+    link: L,
+    num_devices: usize,
+}
+
+impl<L: Link> RemoteServer<L> {
+    /// constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `link` - Opened link to the devices
+    /// * `num_devices` - Number of the devices connected to `link`. The client with a different number is refused.
+    ///
+    pub fn new(link: L, num_devices: usize) -> Self {
+        Self { link, num_devices }
+    }
+
+    pub fn link(&self) -> &L {
+        &self.link
+    }
+
+    pub fn link_mut(&mut self) -> &mut L {
+        &mut self.link
+    }
+
+    pub fn into_link(self) -> L {
+        self.link
+    }
+
+    /// Accept a client on `listener` and serve it until it closes the connection
+    pub fn serve(&mut self, listener: &TcpListener) -> Result<()> {
+        let (stream, _) = listener.accept()?;
+        self.handle(stream)
+    }
+
+    /// Serve the client connected with `stream` until it closes the connection
+    pub fn handle(&mut self, mut stream: TcpStream) -> Result<()> {
+        stream.set_nodelay(true)?;
+        if !self.handshake(&mut stream)? {
+            return Ok(());
+        }
+        loop {
+            let mut req = [0x00];
+            match stream.read_exact(&mut req) {
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(()),
+                r => r?,
+            }
+            match req[0] {
+                REQ_CLOSE => return Ok(()),
+                REQ_SEND => self.send(&mut stream)?,
+                REQ_RECEIVE => self.receive(&mut stream)?,
+                v => return Err(RemoteError::UnexpectedRequest(v).into()),
+            }
+        }
+    }
+
+    fn handshake(&mut self, stream: &mut TcpStream) -> Result<bool> {
+        let mut req = [0x00; HANDSHAKE_REQUEST_SIZE];
+        stream.read_exact(&mut req)?;
+        if &req[..4] != MAGIC {
+            return Err(RemoteError::InvalidClient.into());
+        }
+        let num_devices = u16::from_le_bytes([req[4], req[5]]) as usize;
+        let status = if num_devices == self.num_devices {
+            STATUS_OK
+        } else {
+            STATUS_DEVICE_MISMATCH
+        };
+        stream.write_all(MAGIC)?;
+        stream.write_all(&[status])?;
+        Ok(status == STATUS_OK)
+    }
+
+    fn send(&mut self, stream: &mut TcpStream) -> Result<()> {
+        let mut len = [0x00; 4];
+        stream.read_exact(&mut len)?;
+        let len = u32::from_le_bytes(len) as usize;
+
+        let header_len = std::mem::size_of::<GlobalHeader>();
+        let max_len = header_len + std::mem::size_of::<Body>() * self.num_devices;
+        if len < header_len || len > max_len {
+            return Err(RemoteError::InvalidSize(len).into());
+        }
+        let num_bodies = (len - header_len) / std::mem::size_of::<Body>();
+        if header_len + num_bodies * std::mem::size_of::<Body>() != len {
+            return Err(RemoteError::InvalidSize(len).into());
+        }
+
+        let mut tx = TxDatagram::new(self.num_devices);
+        stream.read_exact(&mut tx.data_mut()[..len])?;
+        tx.num_bodies = num_bodies;
+
+        let res = self.link.send(&tx)?;
+        stream.write_all(&[res as u8])?;
+        Ok(())
+    }
+
+    fn receive(&mut self, stream: &mut TcpStream) -> Result<()> {
+        let mut rx = RxDatagram::new(self.num_devices);
+        if !self.link.receive(&mut rx)? {
+            stream.write_all(&[0])?;
+            return Ok(());
+        }
+        let mut res = Vec::with_capacity(1 + self.num_devices * 2);
+        res.push(1);
+        rx.messages()
+            .iter()
+            .for_each(|msg| res.extend_from_slice(&[msg.ack, msg.msg_id]));
+        stream.write_all(&res)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex},
+        thread,
+    };
+
+    use autd3::prelude::*;
+    use autd3_firmware_emulator::Emulator;
+
+    use super::*;
+    use crate::Remote;
+
+    struct EmulatorLink {
+        emulator: Arc<Mutex<Emulator>>,
+    }
+
+    impl Link for EmulatorLink {
+        fn open<T: Transducer>(&mut self, _geometry: &Geometry<T>) -> Result<()> {
+            Ok(())
+        }
+
+        fn close(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn send(&mut self, tx: &TxDatagram) -> Result<bool> {
+            self.emulator.lock().unwrap().send(tx);
+            Ok(true)
+        }
+
+        fn receive(&mut self, rx: &mut RxDatagram) -> Result<bool> {
+            self.emulator.lock().unwrap().read(rx);
+            Ok(true)
+        }
+
+        fn is_open(&self) -> bool {
+            true
+        }
+    }
+
+    fn spawn_server(num_devices: usize) -> (std::net::SocketAddr, Arc<Mutex<Emulator>>) {
+        let mut emulator = Emulator::new();
+        emulator.init(num_devices);
+        let emulator = Arc::new(Mutex::new(emulator));
+        let link = EmulatorLink {
+            emulator: emulator.clone(),
+        };
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let mut server = RemoteServer::new(link, num_devices);
+            server.serve(&listener).unwrap();
+        });
+        (addr, emulator)
+    }
+
+    #[test]
+    fn controller_through_server() {
+        const NUM_DEVICES: usize = 2;
+        let (addr, emulator) = spawn_server(NUM_DEVICES);
+
+        let mut geometry = GeometryBuilder::new().legacy_mode().build();
+        geometry.add_device(Vector3::zeros(), Vector3::zeros());
+        geometry.add_device(Vector3::new(192., 0., 0.), Vector3::zeros());
+        let center = geometry.center() + Vector3::new(0., 0., 150.);
+
+        let mut autd = Controller::open(geometry, Remote::new(addr, 2)).unwrap();
+        let infos = autd.firmware_infos().unwrap();
+        assert_eq!(infos.len(), NUM_DEVICES);
+        autd.send(&mut Sine::new(150))
+            .send(&mut Focus::new(center))
+            .unwrap();
+        // close only the link to keep the output of the devices
+        autd.link_mut().close().unwrap();
+        drop(autd);
+
+        let emulator = emulator.lock().unwrap();
+        (0..NUM_DEVICES).for_each(|i| {
+            assert!(emulator.fpga(i).modulation().0.iter().any(|&m| m != 0));
+            assert!(emulator.fpga(i).drives()[0].0.iter().any(|d| d.duty != 0));
+        });
+    }
+
+    #[test]
+    fn refuse_mismatched_devices() {
+        let (addr, _) = spawn_server(2);
+
+        let mut geometry = GeometryBuilder::new().legacy_mode().build();
+        geometry.add_device(Vector3::zeros(), Vector3::zeros());
+
+        let mut link = Remote::new(addr, 2);
+        let res = link.open(&geometry);
+        assert!(matches!(
+            res.unwrap_err().downcast_ref::<RemoteError>(),
+            Some(RemoteError::Refused(STATUS_DEVICE_MISMATCH))
+        ));
+    }
+}
@@ -70,6 +70,7 @@ fn main() {
         .header("3rdparty/SOEM/soem/ethercatconfig.h")
         .allowlist_function("ec_init")
         .allowlist_function("ec_find_adapters")
+        .allowlist_function("ec_free_adapters")
         .allowlist_function("ec_send_processdata")
         .allowlist_function("ec_receive_processdata")
         .allowlist_function("ec_config_init")
@@ -130,6 +131,7 @@ fn main() {
         .header("3rdparty/SOEM/soem/ethercatconfig.h")
         .allowlist_function("ec_init")
         .allowlist_function("ec_find_adapters")
+        .allowlist_function("ec_free_adapters")
         .allowlist_function("ec_send_processdata")
         .allowlist_function("ec_receive_processdata")
         .allowlist_function("ec_config_init")
@@ -190,6 +192,7 @@ fn main() {
         .header("3rdparty/SOEM/soem/ethercatconfig.h")
         .allowlist_function("ec_init")
         .allowlist_function("ec_find_adapters")
+        .allowlist_function("ec_free_adapters")
         .allowlist_function("ec_send_processdata")
         .allowlist_function("ec_receive_processdata")
         .allowlist_function("ec_config_init")
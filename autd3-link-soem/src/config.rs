@@ -11,12 +11,13 @@
  *
  */
 
-use crate::SyncMode;
+use crate::{SyncMode, TimerBackend};
 
 pub struct Config {
     pub sync0_cycle: u16,
     pub send_cycle: u16,
     pub high_precision_timer: bool,
+    pub timer_backend: TimerBackend,
     pub sync_mode: SyncMode,
     pub ifname: String,
 }
@@ -33,6 +34,7 @@ impl Default for Config {
             sync0_cycle: 1,
             send_cycle: 1,
             high_precision_timer: false,
+            timer_backend: TimerBackend::Native,
             sync_mode: SyncMode::DC,
             ifname: String::new(),
         }
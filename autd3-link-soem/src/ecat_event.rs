@@ -0,0 +1,64 @@
+/*
+ * File: ecat_event.rs
+ * Project: src
+ * Created Date: 15/10/2022
+ * Author: Shun Suzuki
+ * -----
+ * Last Modified: 15/10/2022
+ * Modified By: Shun Suzuki (suzuki@hapis.k.u-tokyo.ac.jp)
+ * -----
+ * Copyright (c) 2022 Shun Suzuki. All rights reserved.
+ *
+ */
+
+use std::fmt;
+
+use crate::native_methods::*;
+
+/// Event of the state of the slaves detected by the EtherCAT thread
+///
+/// The index of the slave starts from 1, as in SOEM.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EcatEvent {
+    SlaveLost(usize),
+    /// The lost slave is recovered
+    SlaveRecovered(usize),
+    /// The lost slave responds again without recovery
+    SlaveFound(usize),
+    SlaveReconfigured(usize),
+    /// The slave is not OPERATIONAL, and a state change is requested
+    StateError {
+        slave: usize,
+        state: u16,
+    },
+}
+
+impl fmt::Display for EcatEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::SlaveLost(i) => write!(f, "ERROR : slave {} lost", i),
+            Self::SlaveRecovered(i) => write!(f, "MESSAGE : slave {} recovered", i),
+            Self::SlaveFound(i) => write!(f, "MESSAGE : slave {} found", i),
+            Self::SlaveReconfigured(i) => write!(f, "MESSAGE : slave {} reconfigured", i),
+            Self::StateError { slave, state }
+                if state == ec_state_EC_STATE_SAFE_OP as u16 + ec_state_EC_STATE_ERROR as u16 =>
+            {
+                write!(
+                    f,
+                    "ERROR : slave {} is in SAFE_OP + ERROR, attempting ack",
+                    slave
+                )
+            }
+            Self::StateError { slave, state } if state == ec_state_EC_STATE_SAFE_OP as u16 => {
+                write!(
+                    f,
+                    "ERROR : slave {} is in SAFE_OP, change to OPERATIONAL",
+                    slave
+                )
+            }
+            Self::StateError { slave, state } => {
+                write!(f, "ERROR : slave {} is in state 0x{:02X}", slave, state)
+            }
+        }
+    }
+}
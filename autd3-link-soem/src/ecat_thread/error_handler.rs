@@ -11,20 +11,35 @@
  *
  */
 
-use std::fmt::Write as _;
+use std::{
+    fmt::Write as _,
+    sync::{Arc, Mutex},
+};
 
-use crate::native_methods::*;
+use crate::{native_methods::*, EcatEvent};
 
 pub struct EcatErrorHandler<F: Fn(&str)> {
     pub error_handle: Option<F>,
+    pub event_handle: Option<Box<dyn Fn(EcatEvent) + Send>>,
+    pub last_error: Arc<Mutex<Option<String>>>,
 }
 
 impl<F: Fn(&str)> EcatErrorHandler<F> {
+    pub fn set_last_error(&self, msg: &str) {
+        *self.last_error.lock().unwrap() = Some(msg.to_owned());
+    }
+
     pub fn handle(&self) -> bool {
         unsafe {
             ec_group[0].docheckstate = 0;
             ec_readstate();
             let mut msg = String::new();
+            let mut notify = |event: EcatEvent| {
+                writeln!(msg, "{}", event).unwrap();
+                if let Some(f) = &self.event_handle {
+                    f(event);
+                }
+            };
             ec_slave
                 .iter_mut()
                 .enumerate()
@@ -36,28 +51,24 @@ impl<F: Fn(&str)> EcatErrorHandler<F> {
                         if slave.state
                             == ec_state_EC_STATE_SAFE_OP as u16 + ec_state_EC_STATE_ERROR as u16
                         {
-                            writeln!(
-                                msg,
-                                "ERROR : slave {} is in SAFE_OP + ERROR, attempting ack",
-                                i
-                            )
-                            .unwrap();
+                            notify(EcatEvent::StateError {
+                                slave: i,
+                                state: slave.state,
+                            });
                             slave.state =
                                 ec_state_EC_STATE_SAFE_OP as u16 + ec_state_EC_STATE_ACK as u16;
                             ec_writestate(i as _);
                         } else if slave.state == ec_state_EC_STATE_SAFE_OP as _ {
-                            writeln!(
-                                msg,
-                                "ERROR : slave {} is in SAFE_OP, change to OPERATIONAL",
-                                i
-                            )
-                            .unwrap();
+                            notify(EcatEvent::StateError {
+                                slave: i,
+                                state: slave.state,
+                            });
                             slave.state = ec_state_EC_STATE_OPERATIONAL as _;
                             ec_writestate(i as _);
                         } else if slave.state > ec_state_EC_STATE_NONE as _ {
                             if ec_reconfig_slave(i as _, 500) != 0 {
                                 slave.islost = 0;
-                                writeln!(msg, "MESSAGE : slave {} reconfigured", i).unwrap();
+                                notify(EcatEvent::SlaveReconfigured(i));
                             }
                         } else if slave.islost == 0 {
                             ec_statecheck(
@@ -67,7 +78,7 @@ impl<F: Fn(&str)> EcatErrorHandler<F> {
                             );
                             if slave.state == ec_state_EC_STATE_NONE as _ {
                                 slave.islost = 1;
-                                writeln!(msg, "ERROR : slave {} lost", i).unwrap();
+                                notify(EcatEvent::SlaveLost(i));
                             }
                         }
                     }
@@ -75,11 +86,11 @@ impl<F: Fn(&str)> EcatErrorHandler<F> {
                         if slave.state == ec_state_EC_STATE_NONE as _ {
                             if ec_recover_slave(i as _, 500) != 0 {
                                 slave.islost = 0;
-                                writeln!(msg, "MESSAGE : slave {} recovered", i).unwrap();
+                                notify(EcatEvent::SlaveRecovered(i));
                             }
                         } else {
                             slave.islost = 0;
-                            writeln!(msg, "MESSAGE : slave {} found", i).unwrap();
+                            notify(EcatEvent::SlaveFound(i));
                         }
                     }
                 });
@@ -88,6 +99,10 @@ impl<F: Fn(&str)> EcatErrorHandler<F> {
                 return true;
             }
 
+            if !msg.is_empty() {
+                self.set_last_error(&msg);
+            }
+
             if let Some(f) = &self.error_handle {
                 for slave in ec_slave.iter().take(ec_slavecount as usize + 1).skip(1) {
                     if slave.islost != 0 {
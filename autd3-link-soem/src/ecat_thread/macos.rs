@@ -15,23 +15,97 @@ use std::{
     marker::PhantomData,
     sync::{
         atomic::{AtomicBool, Ordering},
-        Arc,
+        Arc, Mutex,
     },
 };
 
 use autd3_core::{RxDatagram, TxDatagram};
 use crossbeam_channel::{Receiver, Sender};
-use libc::{gettimeofday, timespec, timeval};
+use libc::{
+    gettimeofday, kern_return_t, mach_absolute_time, mach_thread_self, mach_timebase_info,
+    thread_policy_set, thread_policy_t, thread_time_constraint_policy, timespec, timeval,
+    KERN_SUCCESS, THREAD_TIME_CONSTRAINT_POLICY, THREAD_TIME_CONSTRAINT_POLICY_COUNT,
+};
 
-use crate::{iomap::IOMap, native_methods::*};
+use crate::{iomap::IOMap, native_methods::*, TimerStats};
 
 use super::{error_handler::EcatErrorHandler, utils::*};
 
-pub trait Waiter {}
+extern "C" {
+    fn mach_wait_until(deadline: u64) -> kern_return_t;
+}
+
+pub trait Waiter {
+    /// Called once on the EtherCAT thread before the loop starts
+    fn init(_cycletime: i64) {}
+    fn timed_wait(abs_time: &timespec);
+}
 pub struct NormalWaiter {}
+/// Waiter sleeping with `mach_wait_until` on a thread of the Mach time-constraint (real-time) policy
+///
+/// With the policy, the wake-up latency is typically a few tens of microseconds, whereas [NormalWaiter] may oversleep by up to about a millisecond when the system is busy.
+/// macOS does not guarantee the latency, so a period of 1 ms can still be missed occasionally, e.g., under heavy load or on battery power.
 pub struct HighPrecisionWaiter {}
-impl Waiter for NormalWaiter {}
-impl Waiter for HighPrecisionWaiter {}
+pub struct SoftwareWaiter {}
+
+impl Waiter for NormalWaiter {
+    fn timed_wait(abs_time: &timespec) {
+        let sleep = remaining(abs_time);
+        if sleep > 0 {
+            std::thread::sleep(std::time::Duration::from_nanos(sleep as _));
+        }
+    }
+}
+
+impl Waiter for HighPrecisionWaiter {
+    fn init(cycletime: i64) {
+        let period = ns_to_abs(cycletime);
+        let mut policy = thread_time_constraint_policy {
+            period: period as _,
+            computation: (period / 2) as _,
+            constraint: period as _,
+            preemptible: 1,
+        };
+        unsafe {
+            // falls back to the normal scheduling if the policy is rejected
+            let _ = thread_policy_set(
+                mach_thread_self(),
+                THREAD_TIME_CONSTRAINT_POLICY as _,
+                &mut policy as *mut _ as thread_policy_t,
+                THREAD_TIME_CONSTRAINT_POLICY_COUNT,
+            );
+        }
+    }
+
+    fn timed_wait(abs_time: &timespec) {
+        let sleep = remaining(abs_time);
+        if sleep > 0 {
+            unsafe {
+                if mach_wait_until(mach_absolute_time() + ns_to_abs(sleep)) != KERN_SUCCESS {
+                    NormalWaiter::timed_wait(abs_time);
+                }
+            }
+        }
+    }
+}
+
+/// Convert nanoseconds into the units of `mach_absolute_time`
+fn ns_to_abs(ns: i64) -> u64 {
+    let mut info = mach_timebase_info { numer: 0, denom: 0 };
+    unsafe {
+        mach_timebase_info(&mut info as *mut _);
+    }
+    if info.numer == 0 {
+        return ns as u64;
+    }
+    (ns as u128 * info.denom as u128 / info.numer as u128) as u64
+}
+
+impl Waiter for SoftwareWaiter {
+    fn timed_wait(abs_time: &timespec) {
+        while spin_sleep(remaining(abs_time)) {}
+    }
+}
 
 fn add_timespec(ts: &mut timespec, addtime: i64) {
     let nsec = addtime % 1000000000;
@@ -45,7 +119,7 @@ fn add_timespec(ts: &mut timespec, addtime: i64) {
     }
 }
 
-fn timed_wait(abs_time: &timespec) {
+fn remaining(abs_time: &timespec) -> i64 {
     let mut tp = timeval {
         tv_sec: 0,
         tv_usec: 0,
@@ -54,12 +128,13 @@ fn timed_wait(abs_time: &timespec) {
         gettimeofday(&mut tp as *mut _ as *mut _, std::ptr::null_mut() as *mut _);
     }
 
-    let sleep = (abs_time.tv_sec - tp.tv_sec as i64) * 1000000000
-        + (abs_time.tv_nsec - tp.tv_usec as i64 * 1000) as i64;
+    (abs_time.tv_sec - tp.tv_sec as i64) * 1000000000
+        + (abs_time.tv_nsec - tp.tv_usec as i64 * 1000) as i64
+}
 
-    if sleep > 0 {
-        std::thread::sleep(std::time::Duration::from_nanos(sleep as _));
-    }
+/// Nanoseconds elapsed since `abs_time`
+fn elapsed(abs_time: &timespec) -> i64 {
+    -remaining(abs_time)
 }
 
 pub struct EcatThreadHandler<F: Fn(&str), W: Waiter> {
@@ -69,11 +144,15 @@ pub struct EcatThreadHandler<F: Fn(&str), W: Waiter> {
     pub sender: Sender<RxDatagram>,
     pub expected_wkc: i32,
     pub cycletime: i64,
+    pub timeout_us: i32,
+    pub retries: usize,
     pub error_handler: EcatErrorHandler<F>,
+    pub stats: Arc<Mutex<TimerStats>>,
     _phantom_data: PhantomData<W>,
 }
 
 impl<F: Fn(&str) + Send, W: Waiter> EcatThreadHandler<F, W> {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         io_map: Box<IOMap>,
         is_running: Arc<AtomicBool>,
@@ -81,7 +160,10 @@ impl<F: Fn(&str) + Send, W: Waiter> EcatThreadHandler<F, W> {
         sender: Sender<RxDatagram>,
         expected_wkc: i32,
         cycletime: i64,
+        timeout_us: i32,
+        retries: usize,
         error_handler: EcatErrorHandler<F>,
+        stats: Arc<Mutex<TimerStats>>,
     ) -> Self {
         Self {
             io_map,
@@ -90,7 +172,10 @@ impl<F: Fn(&str) + Send, W: Waiter> EcatThreadHandler<F, W> {
             sender,
             expected_wkc,
             cycletime,
+            timeout_us,
+            retries,
             error_handler,
+            stats,
             _phantom_data: PhantomData,
         }
     }
@@ -107,11 +192,14 @@ impl<F: Fn(&str) + Send, W: Waiter> EcatThreadHandler<F, W> {
             let ht = ((ts.tv_nsec / self.cycletime) + 1) * self.cycletime;
             ts.tv_nsec = ht;
 
+            W::init(self.cycletime);
+
             let mut toff = 0;
             while self.is_running.load(Ordering::Acquire) {
                 add_timespec(&mut ts, self.cycletime + toff);
 
-                timed_wait(&ts);
+                W::timed_wait(&ts);
+                let latency = elapsed(&ts);
 
                 if ec_slave[0].state == ec_state_EC_STATE_SAFE_OP as _ {
                     ec_slave[0].state = ec_state_EC_STATE_OPERATIONAL as _;
@@ -122,8 +210,8 @@ impl<F: Fn(&str) + Send, W: Waiter> EcatThreadHandler<F, W> {
                     self.io_map.copy_from(tx);
                 }
 
-                ec_send_processdata();
-                if ec_receive_processdata(EC_TIMEOUTRET as i32) != self.expected_wkc
+                if exchange_processdata(self.timeout_us, self.retries, self.expected_wkc)
+                    != self.expected_wkc
                     && !self.error_handler.handle()
                 {
                     return;
@@ -132,6 +220,11 @@ impl<F: Fn(&str) + Send, W: Waiter> EcatThreadHandler<F, W> {
                 self.sender.send(self.io_map.input()).unwrap();
 
                 ec_sync(ec_DCtime, self.cycletime, &mut toff);
+
+                self.stats
+                    .lock()
+                    .unwrap()
+                    .record(latency, elapsed(&ts), self.cycletime);
             }
         }
     }
@@ -15,7 +15,7 @@ use std::{
     marker::PhantomData,
     sync::{
         atomic::{AtomicBool, Ordering},
-        Arc,
+        Arc, Mutex,
     },
 };
 
@@ -23,15 +23,58 @@ use autd3_core::{RxDatagram, TxDatagram};
 use crossbeam_channel::{Receiver, Sender};
 use libc::{clock_gettime, clock_nanosleep, timespec, CLOCK_MONOTONIC, TIMER_ABSTIME};
 
-use crate::{iomap::IOMap, native_methods::*};
+use crate::{iomap::IOMap, native_methods::*, TimerStats};
 
 use super::{error_handler::EcatErrorHandler, utils::*};
 
-pub trait Waiter {}
+pub trait Waiter {
+    fn timed_wait(abs_time: &timespec);
+}
 pub struct NormalWaiter {}
 pub struct HighPrecisionWaiter {}
-impl Waiter for NormalWaiter {}
-impl Waiter for HighPrecisionWaiter {}
+pub struct SoftwareWaiter {}
+
+impl Waiter for NormalWaiter {
+    fn timed_wait(abs_time: &timespec) {
+        let mut tleft = timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        };
+        unsafe {
+            clock_nanosleep(
+                CLOCK_MONOTONIC,
+                TIMER_ABSTIME,
+                abs_time,
+                &mut tleft as *mut _,
+            );
+        }
+    }
+}
+
+impl Waiter for HighPrecisionWaiter {
+    fn timed_wait(abs_time: &timespec) {
+        NormalWaiter::timed_wait(abs_time)
+    }
+}
+
+impl Waiter for SoftwareWaiter {
+    fn timed_wait(abs_time: &timespec) {
+        let mut tp = timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        };
+        loop {
+            unsafe {
+                clock_gettime(CLOCK_MONOTONIC, &mut tp as *mut _);
+            }
+            let sleep =
+                (abs_time.tv_sec - tp.tv_sec) * 1000000000 + (abs_time.tv_nsec - tp.tv_nsec);
+            if !spin_sleep(sleep) {
+                break;
+            }
+        }
+    }
+}
 
 fn add_timespec(ts: &mut timespec, addtime: i64) {
     let nsec = addtime % 1000000000;
@@ -45,6 +88,18 @@ fn add_timespec(ts: &mut timespec, addtime: i64) {
     }
 }
 
+/// Nanoseconds elapsed since `abs_time`
+fn elapsed(abs_time: &timespec) -> i64 {
+    let mut tp = timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    unsafe {
+        clock_gettime(CLOCK_MONOTONIC, &mut tp as *mut _);
+    }
+    (tp.tv_sec - abs_time.tv_sec) * 1000000000 + (tp.tv_nsec - abs_time.tv_nsec)
+}
+
 pub struct EcatThreadHandler<F: Fn(&str), W: Waiter> {
     pub io_map: Box<IOMap>,
     pub is_running: Arc<AtomicBool>,
@@ -52,11 +107,15 @@ pub struct EcatThreadHandler<F: Fn(&str), W: Waiter> {
     pub sender: Sender<RxDatagram>,
     pub expected_wkc: i32,
     pub cycletime: i64,
+    pub timeout_us: i32,
+    pub retries: usize,
     pub error_handler: EcatErrorHandler<F>,
+    pub stats: Arc<Mutex<TimerStats>>,
     _phantom_data: PhantomData<W>,
 }
 
 impl<F: Fn(&str) + Send, W: Waiter> EcatThreadHandler<F, W> {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         io_map: Box<IOMap>,
         is_running: Arc<AtomicBool>,
@@ -64,7 +123,10 @@ impl<F: Fn(&str) + Send, W: Waiter> EcatThreadHandler<F, W> {
         sender: Sender<RxDatagram>,
         expected_wkc: i32,
         cycletime: i64,
+        timeout_us: i32,
+        retries: usize,
         error_handler: EcatErrorHandler<F>,
+        stats: Arc<Mutex<TimerStats>>,
     ) -> Self {
         Self {
             io_map,
@@ -73,7 +135,10 @@ impl<F: Fn(&str) + Send, W: Waiter> EcatThreadHandler<F, W> {
             sender,
             expected_wkc,
             cycletime,
+            timeout_us,
+            retries,
             error_handler,
+            stats,
             _phantom_data: PhantomData,
         }
     }
@@ -84,11 +149,6 @@ impl<F: Fn(&str) + Send, W: Waiter> EcatThreadHandler<F, W> {
                 tv_sec: 0,
                 tv_nsec: 0,
             };
-            let mut tleft = timespec {
-                tv_sec: 0,
-                tv_nsec: 0,
-            };
-
             clock_gettime(CLOCK_MONOTONIC, &mut ts as *mut _);
 
             let ht = ((ts.tv_nsec / self.cycletime) + 1) * self.cycletime;
@@ -98,7 +158,8 @@ impl<F: Fn(&str) + Send, W: Waiter> EcatThreadHandler<F, W> {
             while self.is_running.load(Ordering::Acquire) {
                 add_timespec(&mut ts, self.cycletime + toff);
 
-                clock_nanosleep(CLOCK_MONOTONIC, TIMER_ABSTIME, &ts, &mut tleft as *mut _);
+                W::timed_wait(&ts);
+                let latency = elapsed(&ts);
 
                 if ec_slave[0].state == ec_state_EC_STATE_SAFE_OP as _ {
                     ec_slave[0].state = ec_state_EC_STATE_OPERATIONAL as _;
@@ -109,8 +170,8 @@ impl<F: Fn(&str) + Send, W: Waiter> EcatThreadHandler<F, W> {
                     self.io_map.copy_from(tx);
                 }
 
-                ec_send_processdata();
-                if ec_receive_processdata(EC_TIMEOUTRET as i32) != self.expected_wkc
+                if exchange_processdata(self.timeout_us, self.retries, self.expected_wkc)
+                    != self.expected_wkc
                     && !self.error_handler.handle()
                 {
                     return;
@@ -119,7 +180,48 @@ impl<F: Fn(&str) + Send, W: Waiter> EcatThreadHandler<F, W> {
                 self.sender.send(self.io_map.input()).unwrap();
 
                 ec_sync(ec_DCtime, self.cycletime, &mut toff);
+
+                self.stats
+                    .lock()
+                    .unwrap()
+                    .record(latency, elapsed(&ts), self.cycletime);
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn software_waiter_wakes_up_after_deadline() {
+        let mut deadline = timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        };
+        [100000, 1000000, 5000000].into_iter().for_each(|wait_ns| {
+            unsafe {
+                clock_gettime(CLOCK_MONOTONIC, &mut deadline as *mut _);
+            }
+            add_timespec(&mut deadline, wait_ns);
+            SoftwareWaiter::timed_wait(&deadline);
+            let latency = elapsed(&deadline);
+            assert!(latency >= 0);
+            assert!(latency < 1000000, "latency {} ns", latency);
+        });
+    }
+
+    #[test]
+    fn software_waiter_returns_after_passed_deadline() {
+        let mut deadline = timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        };
+        unsafe {
+            clock_gettime(CLOCK_MONOTONIC, &mut deadline as *mut _);
+        }
+        SoftwareWaiter::timed_wait(&deadline);
+        assert!(elapsed(&deadline) >= 0);
+    }
+}
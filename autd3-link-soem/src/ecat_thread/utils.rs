@@ -11,6 +11,8 @@
  *
  */
 
+use crate::native_methods::*;
+
 pub fn ec_sync(reftime: i64, cycletime: i64, integral: &mut i64) -> i64 {
     let mut delta = (reftime - 50000) % cycletime;
     if delta > (cycletime / 2) {
@@ -24,3 +26,36 @@ pub fn ec_sync(reftime: i64, cycletime: i64, integral: &mut i64) -> i64 {
     }
     -(delta / 100) - (*integral / 20)
 }
+
+const SPIN_THRESHOLD_NS: i64 = 200000;
+
+/// Wait a part of `remaining` nanoseconds. Returns false if the deadline has already passed.
+/// It sleeps while the deadline is far away and spins just before the deadline.
+pub fn spin_sleep(remaining: i64) -> bool {
+    if remaining <= 0 {
+        return false;
+    }
+    if remaining > SPIN_THRESHOLD_NS {
+        std::thread::sleep(std::time::Duration::from_nanos(
+            (remaining - SPIN_THRESHOLD_NS) as _,
+        ));
+    } else {
+        std::hint::spin_loop();
+    }
+    true
+}
+
+/// Exchange the process data, and retry up to `retries` times while the working counter is not `expected_wkc`.
+/// Since the frame is released when receiving times out, each retry sends the process data again.
+/// Returns the last working counter.
+pub unsafe fn exchange_processdata(timeout_us: i32, retries: usize, expected_wkc: i32) -> i32 {
+    let mut wkc = 0;
+    for _ in 0..=retries {
+        ec_send_processdata();
+        wkc = ec_receive_processdata(timeout_us);
+        if wkc == expected_wkc {
+            break;
+        }
+    }
+    wkc
+}
@@ -15,7 +15,7 @@ use std::{
     marker::PhantomData,
     sync::{
         atomic::{AtomicBool, Ordering},
-        Arc,
+        Arc, Mutex,
     },
 };
 
@@ -33,7 +33,7 @@ use windows::Win32::{
     },
 };
 
-use crate::{iomap::IOMap, native_methods::*};
+use crate::{iomap::IOMap, native_methods::*, TimerStats};
 
 use super::{error_handler::EcatErrorHandler, utils::*};
 
@@ -90,6 +90,7 @@ pub trait Waiter {
 }
 pub struct NormalWaiter {}
 pub struct HighPrecisionWaiter {}
+pub struct SoftwareWaiter {}
 
 impl Waiter for NormalWaiter {
     fn timed_wait(abs_time: &timespec) {
@@ -127,6 +128,38 @@ impl Waiter for HighPrecisionWaiter {
     }
 }
 
+impl Waiter for SoftwareWaiter {
+    fn timed_wait(abs_time: &timespec) {
+        let mut tp = timeval {
+            tv_sec: 0,
+            tv_usec: 0,
+        };
+        loop {
+            unsafe {
+                osal_gettimeofday(&mut tp as *mut _);
+            }
+            let sleep = (abs_time.tv_sec - tp.tv_sec as i64) * 1000000000
+                + (abs_time.tv_nsec - tp.tv_usec * 1000) as i64;
+            if !spin_sleep(sleep) {
+                break;
+            }
+        }
+    }
+}
+
+/// Nanoseconds elapsed since `abs_time`
+fn elapsed(abs_time: &timespec) -> i64 {
+    let mut tp = timeval {
+        tv_sec: 0,
+        tv_usec: 0,
+    };
+    unsafe {
+        osal_gettimeofday(&mut tp as *mut _);
+    }
+    (tp.tv_sec as i64 - abs_time.tv_sec) * 1000000000
+        + (tp.tv_usec * 1000 - abs_time.tv_nsec) as i64
+}
+
 pub struct EcatThreadHandler<F: Fn(&str), W: Waiter> {
     io_map: Box<IOMap>,
     is_running: Arc<AtomicBool>,
@@ -134,11 +167,15 @@ pub struct EcatThreadHandler<F: Fn(&str), W: Waiter> {
     sender: Sender<RxDatagram>,
     expected_wkc: i32,
     cycletime: i64,
+    timeout_us: i32,
+    retries: usize,
     error_handler: EcatErrorHandler<F>,
+    stats: Arc<Mutex<TimerStats>>,
     _phantom_data: PhantomData<W>,
 }
 
 impl<F: Fn(&str) + Send, W: Waiter> EcatThreadHandler<F, W> {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         io_map: Box<IOMap>,
         is_running: Arc<AtomicBool>,
@@ -146,7 +183,10 @@ impl<F: Fn(&str) + Send, W: Waiter> EcatThreadHandler<F, W> {
         sender: Sender<RxDatagram>,
         expected_wkc: i32,
         cycletime: i64,
+        timeout_us: i32,
+        retries: usize,
         error_handler: EcatErrorHandler<F>,
+        stats: Arc<Mutex<TimerStats>>,
     ) -> Self {
         Self {
             io_map,
@@ -155,7 +195,10 @@ impl<F: Fn(&str) + Send, W: Waiter> EcatThreadHandler<F, W> {
             sender,
             expected_wkc,
             cycletime,
+            timeout_us,
+            retries,
             error_handler,
+            stats,
             _phantom_data: PhantomData,
         }
     }
@@ -184,6 +227,7 @@ impl<F: Fn(&str) + Send, W: Waiter> EcatThreadHandler<F, W> {
                 add_timespec(&mut ts, self.cycletime + toff);
 
                 W::timed_wait(&ts);
+                let latency = elapsed(&ts);
 
                 if ec_slave[0].state == ec_state_EC_STATE_SAFE_OP as _ {
                     eprintln!("WARN: SAFE_OP");
@@ -195,8 +239,8 @@ impl<F: Fn(&str) + Send, W: Waiter> EcatThreadHandler<F, W> {
                     self.io_map.copy_from(tx);
                 }
 
-                ec_send_processdata();
-                if ec_receive_processdata(EC_TIMEOUTRET as i32) != self.expected_wkc
+                if exchange_processdata(self.timeout_us, self.retries, self.expected_wkc)
+                    != self.expected_wkc
                     && !self.error_handler.handle()
                 {
                     return;
@@ -205,6 +249,11 @@ impl<F: Fn(&str) + Send, W: Waiter> EcatThreadHandler<F, W> {
                 let _ = self.sender.send(self.io_map.input());
 
                 ec_sync(ec_DCtime, self.cycletime, &mut toff);
+
+                self.stats
+                    .lock()
+                    .unwrap()
+                    .record(latency, elapsed(&ts), self.cycletime);
             }
         }
     }
@@ -20,18 +20,44 @@ use std::ffi::CStr;
 use std::ops::Index;
 use std::slice;
 
-#[derive(Copy, Clone)]
-pub struct EthernetAdapter<'a> {
-    pub desc: &'a str,
-    pub name: &'a str,
+/// Network interface found by SOEM
+#[derive(Clone, Debug)]
+pub struct EthernetAdapter {
+    /// Human-readable description
+    pub desc: String,
+    /// Name of the interface passed to SOEM, e.g., `eth0` on Linux or `\Device\NPF_{GUID}` on Windows
+    pub name: String,
 }
 
 #[derive(Clone)]
-pub struct EthernetAdapters<'a> {
-    adapters: Vec<EthernetAdapter<'a>>,
+pub struct EthernetAdapters {
+    adapters: Vec<EthernetAdapter>,
 }
 
-impl<'a> EthernetAdapters<'a> {
+/// List the network interfaces, e.g., to let the user choose `ifname` of [Config](crate::Config)
+///
+/// This can be called without opening the link.
+pub fn enumerate_adapters() -> Vec<EthernetAdapter> {
+    let mut adapters = Vec::new();
+    unsafe {
+        let head = native_methods::ec_find_adapters();
+        let mut adapter = head;
+        while !adapter.is_null() {
+            let desc = CStr::from_ptr(((*adapter).desc).as_ptr())
+                .to_string_lossy()
+                .into_owned();
+            let name = CStr::from_ptr(((*adapter).name).as_ptr())
+                .to_string_lossy()
+                .into_owned();
+            adapters.push(EthernetAdapter { desc, name });
+            adapter = (*adapter).next;
+        }
+        native_methods::ec_free_adapters(head);
+    }
+    adapters
+}
+
+impl EthernetAdapters {
     pub fn len(&self) -> usize {
         self.adapters.len()
     }
@@ -41,39 +67,31 @@ impl<'a> EthernetAdapters<'a> {
     }
 }
 
-impl<'a> Default for EthernetAdapters<'a> {
+impl Default for EthernetAdapters {
     fn default() -> Self {
-        let mut adapters = Vec::new();
-        unsafe {
-            let mut adapter = native_methods::ec_find_adapters();
-            while !adapter.is_null() {
-                let desc = CStr::from_ptr(((*adapter).desc).as_ptr()).to_str().unwrap();
-                let name = CStr::from_ptr(((*adapter).name).as_ptr()).to_str().unwrap();
-                adapters.push(EthernetAdapter { desc, name });
-                adapter = (*adapter).next;
-            }
-            EthernetAdapters { adapters }
+        Self {
+            adapters: enumerate_adapters(),
         }
     }
 }
 
-impl<'a> Index<usize> for EthernetAdapters<'a> {
-    type Output = EthernetAdapter<'a>;
+impl Index<usize> for EthernetAdapters {
+    type Output = EthernetAdapter;
     fn index(&self, index: usize) -> &Self::Output {
         &self.adapters[index]
     }
 }
 
-impl<'a> IntoIterator for &'a EthernetAdapters<'a> {
-    type Item = &'a EthernetAdapter<'a>;
-    type IntoIter = slice::Iter<'a, EthernetAdapter<'a>>;
+impl<'a> IntoIterator for &'a EthernetAdapters {
+    type Item = &'a EthernetAdapter;
+    type IntoIter = slice::Iter<'a, EthernetAdapter>;
 
-    fn into_iter(self) -> slice::Iter<'a, EthernetAdapter<'a>> {
+    fn into_iter(self) -> slice::Iter<'a, EthernetAdapter> {
         self.adapters.iter()
     }
 }
 
-impl<'a> fmt::Display for EthernetAdapter<'a> {
+impl fmt::Display for EthernetAdapter {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}, {}", self.desc, self.name)
     }
@@ -12,6 +12,7 @@
  */
 
 mod config;
+mod ecat_event;
 mod ecat_thread;
 mod error;
 mod ethernet_adapters;
@@ -19,8 +20,11 @@ mod iomap;
 mod link_soem;
 mod native_methods;
 mod sync_mode;
+mod timer_backend;
 
 pub use config::Config;
-pub use ethernet_adapters::EthernetAdapters;
-pub use link_soem::SOEM;
-pub use sync_mode::SyncMode;
\ No newline at end of file
+pub use ecat_event::EcatEvent;
+pub use ethernet_adapters::{enumerate_adapters, EthernetAdapter, EthernetAdapters};
+pub use link_soem::{detect_device_num, SOEM};
+pub use sync_mode::SyncMode;
+pub use timer_backend::{TimerBackend, TimerStats};
@@ -33,11 +33,13 @@ use autd3_core::{
 };
 
 use crate::{
-    ecat_thread::{EcatErrorHandler, EcatThreadHandler, HighPrecisionWaiter, NormalWaiter},
+    ecat_thread::{
+        EcatErrorHandler, EcatThreadHandler, HighPrecisionWaiter, NormalWaiter, SoftwareWaiter,
+    },
     error::SOEMError,
     iomap::IOMap,
     native_methods::*,
-    Config, EthernetAdapters, SyncMode,
+    Config, EcatEvent, EthernetAdapters, SyncMode, TimerBackend, TimerStats,
 };
 
 const SEND_BUF_SIZE: usize = 32;
@@ -45,12 +47,17 @@ const SEND_BUF_SIZE: usize = 32;
 pub struct SOEM<F: Fn(&str) + Send> {
     ecatth_handle: Option<JoinHandle<()>>,
     error_handle: Option<F>,
+    event_handle: Option<Box<dyn Fn(EcatEvent) + Send>>,
+    timeout: Option<Duration>,
+    retries: usize,
     is_open: bool,
     config: Config,
     sender: Option<Sender<TxDatagram>>,
     recv_thread: Option<JoinHandle<()>>,
     thread_running: Arc<AtomicBool>,
     rx: Arc<Mutex<RxDatagram>>,
+    last_error: Arc<Mutex<Option<String>>>,
+    timer_stats: Arc<Mutex<TimerStats>>,
     ec_sync0_cycle_time_ns: u32,
     ec_send_cycle_time_ns: u32,
 }
@@ -62,9 +69,14 @@ impl<F: Fn(&str) + Send> SOEM<F> {
         Self {
             ecatth_handle: None,
             error_handle: Some(error_handle),
+            event_handle: None,
+            timeout: None,
+            retries: 0,
             is_open: false,
             sender: None,
             rx: Arc::new(Mutex::new(RxDatagram::new(0))),
+            last_error: Arc::new(Mutex::new(None)),
+            timer_stats: Arc::new(Mutex::new(TimerStats::default())),
             recv_thread: None,
             thread_running: Arc::new(AtomicBool::new(false)),
             config,
@@ -72,6 +84,38 @@ impl<F: Fn(&str) + Send> SOEM<F> {
             ec_send_cycle_time_ns,
         }
     }
+
+    /// Set the handler called with each event of the slave state detected by the EtherCAT thread, e.g., to react to a lost slave programmatically
+    ///
+    /// Unlike `error_handle` of [new](Self::new), which is called with the formatted messages only when a slave is lost, this handler is called for every event including recoveries.
+    pub fn with_event_handler<H: Fn(EcatEvent) + Send + 'static>(mut self, handler: H) -> Self {
+        self.event_handle = Some(Box::new(handler));
+        self
+    }
+
+    /// Set the timeout of receiving the process data in each cycle. Default is `EC_TIMEOUTRET` of SOEM, i.e., 2 ms.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Set the number of retries of exchanging the process data in a cycle when the working counter is wrong, e.g., due to noise, before the error is handled. Default is 0.
+    ///
+    /// Note that the retries may make the cycle overrun, which can be checked by [timer_stats](Self::timer_stats).
+    pub fn with_retries(mut self, retries: usize) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Returns the most recent error message reported by the EtherCAT thread, if any.
+    pub fn last_error(&self) -> Option<String> {
+        self.last_error.lock().unwrap().clone()
+    }
+
+    /// Returns the timing statistics of the EtherCAT cycles since open, e.g., to detect that the host cannot keep up with the cycle before the devices drop to SAFE_OP.
+    pub fn timer_stats(&self) -> TimerStats {
+        *self.timer_stats.lock().unwrap()
+    }
 }
 
 fn lookup_autd() -> anyhow::Result<String> {
@@ -83,22 +127,20 @@ fn lookup_autd() -> anyhow::Result<String> {
             return false;
         }
         let wc = ec_config_init(0);
-        if wc <= 0 {
-            return false;
-        }
-        let slave_name = String::from_utf8(
-            ec_slave[1]
-                .name
-                .iter()
-                .take_while(|&&c| c != 0)
-                .map(|&c| c as u8)
-                .collect(),
-        )
-        .unwrap();
-        if slave_name == "AUTD" {
-            return true;
-        }
-        false
+        let found = wc > 0 && {
+            let slave_name = String::from_utf8(
+                ec_slave[1]
+                    .name
+                    .iter()
+                    .take_while(|&&c| c != 0)
+                    .map(|&c| c as u8)
+                    .collect(),
+            )
+            .unwrap();
+            slave_name == "AUTD"
+        };
+        ec_close();
+        found
     }) {
         Ok(adapter.name.to_owned())
     } else {
@@ -106,6 +148,37 @@ fn lookup_autd() -> anyhow::Result<String> {
     }
 }
 
+/// Count the slaves on the interface, e.g., to build the geometry with the correct number of devices before opening the link.
+///
+/// SOEM is initialized and closed in this function, so that it does not interfere with the subsequent [open](Link::open).
+///
+/// # Arguments
+///
+/// * `ifname` - Interface name. If empty, the interface to which AUTD is connected is searched.
+///
+pub fn detect_device_num(ifname: &str) -> anyhow::Result<u16> {
+    let ifname = if ifname.is_empty() {
+        lookup_autd()?
+    } else {
+        ifname.to_owned()
+    };
+    let ifname = std::ffi::CString::new(ifname).unwrap();
+
+    unsafe {
+        if ec_init(ifname.as_ptr()) <= 0 {
+            return Err(SOEMError::NoSocketConnection(ifname.to_str().unwrap().to_string()).into());
+        }
+
+        let wc = ec_config_init(0);
+        ec_close();
+
+        if wc <= 0 {
+            return Err(SOEMError::NoDeviceFound.into());
+        }
+        Ok(wc as u16)
+    }
+}
+
 unsafe extern "C" fn dc_config(context: *mut ecx_contextt, slave: u16) -> i32 {
     let cyc_time = *((*context).userdata as *mut u32);
     ec_dcsync0(slave, 1, cyc_time, 0);
@@ -182,12 +255,39 @@ impl<F: 'static + Fn(&str) + Send> Link for SOEM<F> {
 
             let expected_wkc = (ec_group[0].outputsWKC * 2 + ec_group[0].inputsWKC) as i32;
             let cycletime = self.ec_send_cycle_time_ns as i64;
+            let timeout_us = self
+                .timeout
+                .map_or(EC_TIMEOUTRET as i32, |t| t.as_micros() as i32);
+            let retries = self.retries;
             let error_handle = self.error_handle.take();
+            let event_handle = self.event_handle.take();
+            let last_error = self.last_error.clone();
+            *self.timer_stats.lock().unwrap() = TimerStats::default();
+            let timer_stats = self.timer_stats.clone();
             let thread_running = self.thread_running.clone();
             let is_high_precision = self.config.high_precision_timer;
+            let timer_backend = self.config.timer_backend;
             self.ecatth_handle = Some(std::thread::spawn(move || {
-                let error_handler = EcatErrorHandler { error_handle };
-                if is_high_precision {
+                let error_handler = EcatErrorHandler {
+                    error_handle,
+                    event_handle,
+                    last_error,
+                };
+                if timer_backend == TimerBackend::Software {
+                    let mut callback = EcatThreadHandler::<_, SoftwareWaiter>::new(
+                        io_map,
+                        thread_running,
+                        tx_receiver,
+                        rx_sender,
+                        expected_wkc,
+                        cycletime,
+                        timeout_us,
+                        retries,
+                        error_handler,
+                        timer_stats,
+                    );
+                    callback.run();
+                } else if is_high_precision {
                     let mut callback = EcatThreadHandler::<_, HighPrecisionWaiter>::new(
                         io_map,
                         thread_running,
@@ -195,7 +295,10 @@ impl<F: 'static + Fn(&str) + Send> Link for SOEM<F> {
                         rx_sender,
                         expected_wkc,
                         cycletime,
+                        timeout_us,
+                        retries,
                         error_handler,
+                        timer_stats,
                     );
                     callback.run();
                 } else {
@@ -206,7 +309,10 @@ impl<F: 'static + Fn(&str) + Send> Link for SOEM<F> {
                         rx_sender,
                         expected_wkc,
                         cycletime,
+                        timeout_us,
+                        retries,
                         error_handler,
+                        timer_stats,
                     );
                     callback.run();
                 }
@@ -303,3 +409,24 @@ impl<F: 'static + Fn(&str) + Send> Link for SOEM<F> {
         self.is_open
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn last_error() {
+        let soem = SOEM::new(Config::default(), |_: &str| {});
+        assert_eq!(soem.last_error(), None);
+
+        let handler = EcatErrorHandler::<fn(&str)> {
+            error_handle: None,
+            event_handle: None,
+            last_error: soem.last_error.clone(),
+        };
+        handler.set_last_error("slave 1 lost");
+        assert_eq!(soem.last_error(), Some("slave 1 lost".to_owned()));
+        handler.set_last_error("slave 1 recovered");
+        assert_eq!(soem.last_error(), Some("slave 1 recovered".to_owned()));
+    }
+}
@@ -0,0 +1,42 @@
+/*
+ * File: timer_backend.rs
+ * Project: src
+ * Created Date: 15/10/2022
+ * Author: Shun Suzuki
+ * -----
+ * Last Modified: 15/10/2022
+ * Modified By: Shun Suzuki (suzuki@hapis.k.u-tokyo.ac.jp)
+ * -----
+ * Copyright (c) 2022 Shun Suzuki. All rights reserved.
+ *
+ */
+
+/// Timer used to drive the EtherCAT cycle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimerBackend {
+    /// OS native timer
+    Native,
+    /// Sleep and spin loop with the standard library, which works without real-time privileges but with more jitter
+    Software,
+}
+
+/// Statistics of the timing of the EtherCAT cycles, obtained by [SOEM::timer_stats](crate::SOEM::timer_stats)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TimerStats {
+    /// Number of the cycles
+    pub count: u64,
+    /// Number of the cycles whose processing did not finish within the cycle time, i.e., the next cycle was delayed
+    pub overruns: u64,
+    /// Maximum delay of waking up from the scheduled time of a cycle
+    pub max_latency_ns: u64,
+}
+
+impl TimerStats {
+    pub(crate) fn record(&mut self, latency_ns: i64, elapsed_ns: i64, cycletime_ns: i64) {
+        self.count += 1;
+        if elapsed_ns > cycletime_ns {
+            self.overruns += 1;
+        }
+        self.max_latency_ns = self.max_latency_ns.max(latency_ns.max(0) as u64);
+    }
+}
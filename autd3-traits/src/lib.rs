@@ -24,6 +24,11 @@ fn impl_modulation_macro(ast: &syn::DeriveInput) -> TokenStream {
     let name = &ast.ident;
     let generics = &ast.generics;
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let mut sendable_generics = generics.clone();
+    sendable_generics
+        .params
+        .push(syn::parse_quote!(T: autd3_core::geometry::Transducer));
+    let (sendable_impl_generics, _, _) = sendable_generics.split_for_impl();
     let gen = quote! {
         impl #impl_generics Modulation for #name #ty_generics #where_clause {
             fn build(&mut self) -> anyhow::Result<()> {
@@ -32,6 +37,14 @@ fn impl_modulation_macro(ast: &syn::DeriveInput) -> TokenStream {
                 }
 
                 self.calc()?;
+                self.props.apply_transforms();
+                self.props.apply_freq_response(self.sampling_freq())?;
+                self.props.apply_smooth()?;
+                if !self.props.buffer.is_empty() {
+                    let len = self.props.buffer.len();
+                    let shift = (self.props.phase_offset * len as f64).round() as isize;
+                    self.props.buffer.rotate_left(shift.rem_euclid(len as isize) as usize);
+                }
                 if self.buffer().len() > autd3_core::MOD_BUF_SIZE_MAX {
                     return Err(autd3_core::FPGAError::ModulationOutOfBuffer(self.buffer().len()).into());
                 }
@@ -59,6 +72,12 @@ fn impl_modulation_macro(ast: &syn::DeriveInput) -> TokenStream {
             }
         }
 
+        impl #impl_generics autd3_core::modulation::ModulationOptions for #name #ty_generics #where_clause {
+            fn props_mut(&mut self) -> &mut autd3_core::modulation::ModProps {
+                &mut self.props
+            }
+        }
+
         impl #impl_generics autd3_core::interface::DatagramHeader for #name #ty_generics #where_clause {
             fn init(&mut self) -> anyhow::Result<()> {
                 self.build()?;
@@ -72,7 +91,7 @@ fn impl_modulation_macro(ast: &syn::DeriveInput) -> TokenStream {
                 tx: &mut autd3_core::TxDatagram,
             ) -> anyhow::Result<()> {
                 let is_first_frame = self.props.sent == 0;
-                let max_size = if is_first_frame {autd3_core::MOD_HEAD_DATA_SIZE} else {autd3_core::MOD_BODY_DATA_SIZE};
+                let max_size = if is_first_frame {autd3_core::MOD_HEAD_CHUNK_SIZE} else {autd3_core::MOD_BODY_CHUNK_SIZE};
                 let mod_size = (self.buffer().len() - self.props.sent).min(max_size);
                 let is_last_frame = self.props.sent + mod_size == self.buffer().len();
                 autd3_core::modulation(msg_id, &self.buffer()[self.props.sent..(self.props.sent + mod_size)], is_first_frame, self.props.freq_div, is_last_frame, tx)?;
@@ -87,7 +106,7 @@ fn impl_modulation_macro(ast: &syn::DeriveInput) -> TokenStream {
             }
         }
 
-        impl <T: autd3_core::geometry::Transducer> autd3_core::interface::Sendable<T> for #name #ty_generics #where_clause {
+        impl #sendable_impl_generics autd3_core::interface::Sendable<T> for #name #ty_generics #where_clause {
             type H = autd3_core::interface::Filled;
             type B = autd3_core::interface::Empty;
 
@@ -132,6 +151,7 @@ fn impl_gain_macro(ast: syn::DeriveInput) -> TokenStream {
                 self.props.init(geometry);
 
                 autd3_core::gain::IGain::calc(self, geometry)?;
+                self.props.apply_duty_map();
 
                 self.props.built = true;
 
@@ -156,6 +176,12 @@ fn impl_gain_macro(ast: syn::DeriveInput) -> TokenStream {
             }
         }
 
+        impl #impl_generics autd3_core::gain::GainOptions<T> for #name #ty_generics #where_clause {
+            fn props_mut(&mut self) -> &mut autd3_core::gain::GainProps<T> {
+                &mut self.props
+            }
+        }
+
         impl #impl_generics autd3_core::interface::DatagramBody<T> for #name #ty_generics #where_clause {
             fn init(&mut self) -> anyhow::Result<()> {
                 self.props.phase_sent = false;
@@ -173,13 +199,26 @@ fn impl_gain_macro(ast: syn::DeriveInput) -> TokenStream {
                     return Ok(());
                 }
                 self.build(geometry)?;
-                self.props.pack_body(tx)?;
+                self.props.pack_body(geometry, tx)?;
                 Ok(())
             }
 
             fn is_finished(&self) -> bool {
                 self.props.phase_sent && self.props.duty_sent
             }
+
+            fn total_power(&self) -> Option<f64> {
+                Some(self.props.total_power())
+            }
+
+            fn set_amplitude_scale(&mut self, scale: f64) -> bool {
+                self.props.amp_scale = scale;
+                true
+            }
+
+            fn target_kind(&self) -> Option<autd3_core::geometry::TransducerKind> {
+                Some(Gain::transducer_kind(self))
+            }
         }
 
 
@@ -203,6 +242,18 @@ fn impl_gain_macro(ast: syn::DeriveInput) -> TokenStream {
             fn is_finished(&self) -> bool {
                 autd3_core::interface::DatagramBody::<T>::is_finished(self)
             }
+
+            fn total_power(&self) -> Option<f64> {
+                autd3_core::interface::DatagramBody::<T>::total_power(self)
+            }
+
+            fn set_amplitude_scale(&mut self, scale: f64) -> bool {
+                autd3_core::interface::DatagramBody::<T>::set_amplitude_scale(self, scale)
+            }
+
+            fn target_kind(&self) -> Option<autd3_core::geometry::TransducerKind> {
+                autd3_core::interface::DatagramBody::<T>::target_kind(self)
+            }
         }
     };
     gen.into()
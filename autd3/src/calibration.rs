@@ -0,0 +1,296 @@
+/*
+ * File: calibration.rs
+ * Project: src
+ * Created Date: 15/10/2022
+ * Author: Shun Suzuki
+ * -----
+ * Last Modified: 15/10/2022
+ * Modified By: Shun Suzuki (suzuki@hapis.k.u-tokyo.ac.jp)
+ * -----
+ * Copyright (c) 2022 Shun Suzuki. All rights reserved.
+ *
+ */
+
+use anyhow::Result;
+
+use autd3_core::{
+    geometry::{Transducer, Vector3},
+    link::Link,
+    NUM_TRANS_IN_UNIT,
+};
+
+use crate::{controller::Controller, gain::TransducerTest};
+
+/// Find the phase offset of each transducer by sweeping its phase while focusing at `focus`
+///
+/// The transducers are swept one by one, and the phase maximizing `measure` is kept for the subsequent transducers.
+/// The whole sweep is done twice so that the transducers swept first are refined after the others are aligned.
+/// The returned offsets are normalized by 2π, i.e., in the same unit as the phase of [Drive](autd3_core::Drive), and should be added to the phase of each transducer.
+/// Since the absolute phase cannot be measured, the offsets are relative to that of the first transducer.
+///
+/// # Arguments
+///
+/// * `cnt` - Controller
+/// * `focus` - Position of the focus where the measurement is taken
+/// * `steps` - Number of phase steps in a cycle
+/// * `measure` - Callback returning the measured amplitude at the focus with the drives currently sent
+///
+pub fn calibrate_phases<L: Link, T: Transducer, F: FnMut() -> f64>(
+    cnt: &mut Controller<L, T>,
+    focus: Vector3,
+    steps: usize,
+    mut measure: F,
+) -> Result<Vec<f64>> {
+    let steps = steps.max(1);
+    let sound_speed = cnt.geometry().sound_speed();
+    let phases = cnt
+        .geometry()
+        .transducers()
+        .map(|tr| tr.align_phase_at((focus - tr.position()).norm(), sound_speed))
+        .collect::<Vec<_>>();
+    let mut offsets = vec![0.0; phases.len()];
+
+    for i in (0..phases.len()).chain(0..phases.len()) {
+        let mut best = (f64::NEG_INFINITY, 0.0);
+        for k in 0..steps {
+            offsets[i] = k as f64 / steps as f64;
+            let mut g = TransducerTest::new();
+            phases
+                .iter()
+                .zip(offsets.iter())
+                .enumerate()
+                .for_each(|(id, (p, o))| g.set(id, p + o, 1.0));
+            cnt.send(&mut g).flush()?;
+            let v = measure();
+            if v > best.0 {
+                best = (v, offsets[i]);
+            }
+        }
+        offsets[i] = best.1;
+    }
+
+    let base = offsets[0];
+    Ok(offsets.iter().map(|o| (o - base).rem_euclid(1.0)).collect())
+}
+
+/// Find the phase offset of each device caused by the timing skew between the devices, and set it to the controller
+///
+/// The devices are added one by one to the focus at `focus`, and the phase offset of the added device maximizing `measure` is kept.
+/// `measure` takes the controller so that the amplitude at the focus can be read back, e.g., through the link to which a sensor is connected.
+/// The offsets are relative to the first device, whose offset is set to 0, and are applied to every gain sent afterwards, see [Geometry::set_device_phase_offset](autd3_core::geometry::Geometry::set_device_phase_offset).
+/// Returns the offsets, normalized by 2π.
+///
+/// # Arguments
+///
+/// * `cnt` - Controller
+/// * `focus` - Position of the focus where the measurement is taken
+/// * `steps` - Number of phase steps in a cycle
+/// * `measure` - Callback returning the measured amplitude at the focus with the drives currently sent
+///
+pub fn calibrate_device_phases<L: Link, T: Transducer, F>(
+    cnt: &mut Controller<L, T>,
+    focus: Vector3,
+    steps: usize,
+    mut measure: F,
+) -> Result<Vec<f64>>
+where
+    F: FnMut(&mut Controller<L, T>) -> Result<f64>,
+{
+    let steps = steps.max(1);
+    let sound_speed = cnt.geometry().sound_speed();
+    let phases = cnt
+        .geometry()
+        .transducers()
+        .map(|tr| tr.align_phase_at((focus - tr.position()).norm(), sound_speed))
+        .collect::<Vec<_>>();
+    let num_devices = cnt.geometry().num_devices();
+
+    cnt.set_device_phase_offset(0, 0.0);
+    for dev in 1..num_devices {
+        // only the devices up to `dev` emit
+        let mut g = TransducerTest::new();
+        phases
+            .iter()
+            .take((dev + 1) * NUM_TRANS_IN_UNIT)
+            .enumerate()
+            .for_each(|(id, &p)| g.set(id, p, 1.0));
+
+        let mut best = (f64::NEG_INFINITY, 0.0);
+        for k in 0..steps {
+            let offset = k as f64 / steps as f64;
+            cnt.set_device_phase_offset(dev, offset);
+            cnt.send(&mut g).flush()?;
+            let v = measure(cnt)?;
+            if v > best.0 {
+                best = (v, offset);
+            }
+        }
+        cnt.set_device_phase_offset(dev, best.1);
+    }
+
+    Ok((0..num_devices)
+        .map(|dev| cnt.geometry().device_phase_offset(dev))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        f64::consts::PI,
+        sync::{Arc, Mutex},
+    };
+
+    use autd3_core::{
+        geometry::{Geometry, GeometryBuilder},
+        LegacyDrive, RxDatagram, TxDatagram, NUM_TRANS_IN_UNIT,
+    };
+
+    use super::*;
+
+    /// Link keeping the phases and the amplitudes of the last sent drives
+    struct PhaseLink {
+        drives: Arc<Mutex<Vec<(f64, f64)>>>,
+        msg_id: u8,
+    }
+
+    impl PhaseLink {
+        fn new(num_devices: usize) -> Self {
+            Self {
+                drives: Arc::new(Mutex::new(vec![(0., 0.); num_devices * NUM_TRANS_IN_UNIT])),
+                msg_id: 0,
+            }
+        }
+
+        /// Amplitude at the focus where the phase `align` of each transducer is aligned, with the phase error `skew` of each transducer
+        fn amp(&self, align: &[f64], skew: &[f64]) -> f64 {
+            let (re, im) = self
+                .drives
+                .lock()
+                .unwrap()
+                .iter()
+                .zip(align.iter().zip(skew.iter()))
+                .map(|(&(p, amp), (a, s))| (amp, 2. * PI * (p - a + s)))
+                .fold((0., 0.), |(re, im), (amp, theta)| {
+                    (re + amp * theta.cos(), im + amp * theta.sin())
+                });
+            (re * re + im * im).sqrt()
+        }
+    }
+
+    impl Link for PhaseLink {
+        fn open<T: Transducer>(&mut self, _geometry: &Geometry<T>) -> Result<()> {
+            Ok(())
+        }
+
+        fn close(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn send(&mut self, tx: &TxDatagram) -> Result<bool> {
+            self.msg_id = tx.header().msg_id;
+            let mut drives = self.drives.lock().unwrap();
+            tx.body()
+                .iter()
+                .flat_map(|b| b.data.iter())
+                .zip(drives.iter_mut())
+                .for_each(|(&d, drive)| {
+                    *drive = LegacyDrive {
+                        phase: (d & 0xFF) as u8,
+                        duty: (d >> 8) as u8,
+                    }
+                    .to_phase_amp();
+                });
+            Ok(true)
+        }
+
+        fn receive(&mut self, rx: &mut RxDatagram) -> Result<bool> {
+            rx.messages_mut()
+                .iter_mut()
+                .for_each(|msg| msg.msg_id = self.msg_id);
+            Ok(true)
+        }
+
+        fn is_open(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn find_skew_of_transducers() {
+        const STEPS: usize = 8;
+
+        let mut geometry = GeometryBuilder::new().legacy_mode().build();
+        geometry.add_device(Vector3::zeros(), Vector3::zeros());
+        let focus = geometry.center() + Vector3::new(0., 0., 150.);
+        let align = geometry
+            .transducers()
+            .map(|tr| tr.align_phase_at((focus - tr.position()).norm(), geometry.sound_speed()))
+            .collect::<Vec<_>>();
+        // unknown phase error of each transducer, which is a multiple of the step
+        let skew = (0..NUM_TRANS_IN_UNIT)
+            .map(|i| (i * 5 % STEPS) as f64 / STEPS as f64)
+            .collect::<Vec<_>>();
+
+        let link = PhaseLink::new(1);
+        let sensor = PhaseLink {
+            drives: link.drives.clone(),
+            msg_id: 0,
+        };
+        let mut autd = Controller::open(geometry, link).unwrap();
+
+        let offsets =
+            calibrate_phases(&mut autd, focus, STEPS, || sensor.amp(&align, &skew)).unwrap();
+
+        assert_eq!(offsets[0], 0.);
+        offsets.iter().zip(skew.iter()).for_each(|(o, s)| {
+            let diff = (o + s - skew[0]).rem_euclid(1.0);
+            assert!(diff.min(1.0 - diff) < 1e-9, "{} {}", o, s);
+        });
+    }
+
+    #[test]
+    fn find_skew_of_devices() {
+        const STEPS: usize = 8;
+        const NUM_DEVICES: usize = 3;
+
+        let mut geometry = GeometryBuilder::new().legacy_mode().build();
+        (0..NUM_DEVICES).for_each(|i| {
+            geometry.add_device(Vector3::new(192. * i as f64, 0., 0.), Vector3::zeros());
+        });
+        let focus = geometry.center() + Vector3::new(0., 0., 150.);
+        let align = geometry
+            .transducers()
+            .map(|tr| tr.align_phase_at((focus - tr.position()).norm(), geometry.sound_speed()))
+            .collect::<Vec<_>>();
+        // unknown timing skew of each device
+        let device_skew = [0.125, 0.375, 0.75];
+        let skew = device_skew
+            .iter()
+            .flat_map(|&s| std::iter::repeat_n(s, NUM_TRANS_IN_UNIT))
+            .collect::<Vec<_>>();
+
+        let mut autd = Controller::open(geometry, PhaseLink::new(NUM_DEVICES)).unwrap();
+        let offsets = calibrate_device_phases(&mut autd, focus, STEPS, |autd| {
+            // read back the amplitude at the focus through the link
+            Ok(autd.link().amp(&align, &skew))
+        })
+        .unwrap();
+
+        assert_eq!(offsets[0], 0.);
+        offsets
+            .iter()
+            .zip(device_skew.iter())
+            .enumerate()
+            .for_each(|(dev, (o, s))| {
+                let diff = (o + s - device_skew[0]).rem_euclid(1.0);
+                assert!(diff.min(1.0 - diff) < 1e-9, "{} {}", o, s);
+                assert_eq!(autd.geometry().device_phase_offset(dev), *o);
+            });
+
+        // the focus of all devices is sharpened by the offsets
+        let mut g = crate::gain::Focus::new(focus);
+        autd.send(&mut g).flush().unwrap();
+        let calibrated = autd.link().amp(&align, &skew);
+        assert!((calibrated - (NUM_DEVICES * NUM_TRANS_IN_UNIT) as f64).abs() < 1.);
+    }
+}
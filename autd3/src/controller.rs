@@ -20,15 +20,19 @@ use anyhow::{Ok, Result};
 use itertools::Itertools;
 
 use autd3_core::{
-    geometry::{Geometry, LegacyTransducer, NormalPhaseTransducer, NormalTransducer, Transducer},
+    geometry::{
+        Geometry, LegacyTransducer, NormalPhaseTransducer, NormalTransducer, Transducer,
+        TransducerKind,
+    },
     interface::{DatagramBody, DatagramHeader, Empty, Filled, NullBody, NullHeader, Sendable},
     is_msg_processed,
     link::Link,
+    next_msg_id,
     silencer_config::SilencerConfig,
-    FirmwareInfo, RxDatagram, TxDatagram, MSG_BEGIN, MSG_END, NUM_TRANS_IN_UNIT,
+    FirmwareInfo, RxDatagram, TxDatagram, MSG_BEGIN, NUM_TRANS_IN_UNIT,
 };
 
-use crate::prelude::Null;
+use crate::{error::AUTDError, prelude::Null};
 
 static MSG_ID: AtomicU8 = AtomicU8::new(MSG_BEGIN);
 
@@ -54,21 +58,44 @@ impl<'a, 'b, L: Link, T: Transducer, S: Sendable<T>> Sender<'a, 'b, L, T, S, Emp
 
 impl<'a, 'b, L: Link, T: Transducer, S: Sendable<T>> Sender<'a, 'b, L, T, S, Filled, Empty> {
     pub fn send<B: DatagramBody<T>>(mut self, b: &'b mut B) -> Result<bool> {
+        // nothing is sent on drop even if this fails, e.g., by the power limit
+        self.sent = true;
+
+        let res = self.send_frames(b);
+        b.set_amplitude_scale(1.0);
+        res
+    }
+
+    fn send_frames<B: DatagramBody<T>>(&mut self, b: &mut B) -> Result<bool> {
+        self.cnt.check_transducer_kind(b.target_kind())?;
+        self.cnt.soft_start(b)?;
+
         self.buf.init()?;
         b.init()?;
 
         autd3_core::force_fan(&mut self.cnt.tx_buf, self.cnt.force_fan);
         autd3_core::reads_fpga_info(&mut self.cnt.tx_buf, self.cnt.reads_fpga_info);
 
+        self.cnt.power_scale = 1.0;
+        let mut scaled = false;
         loop {
             let msg_id = self.cnt.get_id();
             self.buf
                 .pack(msg_id, &self.cnt.geometry, &mut self.cnt.tx_buf)?;
             b.pack(&self.cnt.geometry, &mut self.cnt.tx_buf)?;
-            self.cnt.link.send(&self.cnt.tx_buf)?;
+            if !scaled {
+                scaled = self
+                    .cnt
+                    .limit_power(b.total_power(), |scale| b.set_amplitude_scale(scale))?;
+                if scaled {
+                    b.init()?;
+                    b.pack(&self.cnt.geometry, &mut self.cnt.tx_buf)?;
+                }
+            }
+            self.cnt.tx_buf.validate()?;
+            self.cnt.send_tx()?;
             let trials = self.cnt.wait_msg_processed(self.cnt.check_trials)?;
             if (self.cnt.check_trials != 0) && (trials == self.cnt.check_trials) {
-                self.sent = true;
                 return Ok(false);
             }
             if self.buf.is_finished() && b.is_finished() {
@@ -78,7 +105,6 @@ impl<'a, 'b, L: Link, T: Transducer, S: Sendable<T>> Sender<'a, 'b, L, T, S, Fil
                 self.cnt.send_interval as u64 * autd3_core::EC_CYCLE_TIME_BASE_MICRO_SEC as u64,
             ));
         }
-        self.sent = true;
         Ok(true)
     }
 
@@ -90,21 +116,45 @@ impl<'a, 'b, L: Link, T: Transducer, S: Sendable<T>> Sender<'a, 'b, L, T, S, Fil
 
 impl<'a, 'b, L: Link, T: Transducer, S: Sendable<T>> Sender<'a, 'b, L, T, S, Empty, Filled> {
     pub fn send<H: DatagramHeader>(mut self, b: &'b mut H) -> Result<bool> {
+        // nothing is sent on drop even if this fails, e.g., by the power limit
+        self.sent = true;
+
+        let res = self.send_frames(b);
+        self.buf.set_amplitude_scale(1.0);
+        res
+    }
+
+    fn send_frames<H: DatagramHeader>(&mut self, b: &mut H) -> Result<bool> {
+        self.cnt.check_transducer_kind(self.buf.target_kind())?;
+        self.cnt.soft_start(&mut SendableBody(&mut *self.buf))?;
+
         b.init()?;
         self.buf.init()?;
 
         autd3_core::force_fan(&mut self.cnt.tx_buf, self.cnt.force_fan);
         autd3_core::reads_fpga_info(&mut self.cnt.tx_buf, self.cnt.reads_fpga_info);
 
+        self.cnt.power_scale = 1.0;
+        let mut scaled = false;
         loop {
             let msg_id = self.cnt.get_id();
             b.pack(msg_id, &mut self.cnt.tx_buf)?;
             self.buf
                 .pack(msg_id, &self.cnt.geometry, &mut self.cnt.tx_buf)?;
-            self.cnt.link.send(&self.cnt.tx_buf)?;
+            if !scaled {
+                scaled = self.cnt.limit_power(self.buf.total_power(), |scale| {
+                    self.buf.set_amplitude_scale(scale)
+                })?;
+                if scaled {
+                    self.buf.init()?;
+                    self.buf
+                        .pack(msg_id, &self.cnt.geometry, &mut self.cnt.tx_buf)?;
+                }
+            }
+            self.cnt.tx_buf.validate()?;
+            self.cnt.send_tx()?;
             let trials = self.cnt.wait_msg_processed(self.cnt.check_trials)?;
             if (self.cnt.check_trials != 0) && (trials == self.cnt.check_trials) {
-                self.sent = true;
                 return Ok(false);
             }
             if self.buf.is_finished() && b.is_finished() {
@@ -114,7 +164,6 @@ impl<'a, 'b, L: Link, T: Transducer, S: Sendable<T>> Sender<'a, 'b, L, T, S, Emp
                 self.cnt.send_interval as u64 * autd3_core::EC_CYCLE_TIME_BASE_MICRO_SEC as u64,
             ));
         }
-        self.sent = true;
         Ok(true)
     }
 
@@ -124,10 +173,45 @@ impl<'a, 'b, L: Link, T: Transducer, S: Sendable<T>> Sender<'a, 'b, L, T, S, Emp
     }
 }
 
+/// [DatagramBody] view of a [Sendable] consisting only of a body, e.g., a gain sent by [Sender::flush]
+struct SendableBody<'a, S>(&'a mut S);
+
+impl<'a, T: Transducer, S: Sendable<T>> DatagramBody<T> for SendableBody<'a, S> {
+    fn init(&mut self) -> Result<()> {
+        self.0.init()
+    }
+
+    fn pack(&mut self, geometry: &Geometry<T>, tx: &mut TxDatagram) -> Result<()> {
+        // msg_id is packed by the header
+        self.0.pack(0, geometry, tx)
+    }
+
+    fn is_finished(&self) -> bool {
+        self.0.is_finished()
+    }
+
+    fn total_power(&self) -> Option<f64> {
+        self.0.total_power()
+    }
+
+    fn set_amplitude_scale(&mut self, scale: f64) -> bool {
+        self.0.set_amplitude_scale(scale)
+    }
+
+    fn target_kind(&self) -> Option<TransducerKind> {
+        self.0.target_kind()
+    }
+}
+
 impl<'a, 'b, L: Link, T: Transducer, S: Sendable<T>, H, B> Drop for Sender<'a, 'b, L, T, S, H, B> {
     fn drop(&mut self) {
         if !self.sent {
-            if self.buf.init().is_err() {
+            if self
+                .cnt
+                .check_transducer_kind(self.buf.target_kind())
+                .is_err()
+                || self.buf.init().is_err()
+            {
                 return;
             }
 
@@ -143,7 +227,7 @@ impl<'a, 'b, L: Link, T: Transducer, S: Sendable<T>, H, B> Drop for Sender<'a, '
                 {
                     return;
                 }
-                if self.cnt.link.send(&self.cnt.tx_buf).is_err() {
+                if self.cnt.send_tx().is_err() {
                     return;
                 }
                 if (self
@@ -170,8 +254,24 @@ pub struct Controller<L: Link, T: Transducer> {
     rx_buf: RxDatagram,
     pub check_trials: usize,
     pub send_interval: usize,
+    /// Interval between polls of the receive data while waiting for the devices to process a message
+    ///
+    /// If `None`, `send_interval` times the EtherCAT cycle time is used.
+    /// This should be kept small relative to the send cycle, otherwise it adds latency to every handshake.
+    pub poll_interval: Option<std::time::Duration>,
+    /// Upper limit of the total power, i.e., the sum of the squared amplitudes, of the gains to be sent
+    ///
+    /// Gains exceeding the limit are rejected before sending, unless `scales_power` is set.
+    pub power_limit: Option<f64>,
+    /// If true, gains exceeding `power_limit` are scaled down uniformly so that the total power equals the limit, instead of being rejected.
+    /// Only the sent data are scaled, and the drives of the gain itself are left as is. The applied scale factor can be obtained by [power_scale](Self::power_scale).
+    pub scales_power: bool,
+    power_scale: f64,
     pub force_fan: bool,
     pub reads_fpga_info: bool,
+    captured: Option<Vec<Vec<u8>>>,
+    soft_start: Option<std::time::Duration>,
+    soft_start_tick: Box<dyn FnMut(std::time::Duration) + Send>,
 }
 
 impl<L: Link, T: Transducer> Controller<L, T> {
@@ -186,10 +286,38 @@ impl<L: Link, T: Transducer> Controller<L, T> {
             rx_buf: RxDatagram::new(num_devices),
             check_trials: 0,
             send_interval: 1,
+            poll_interval: None,
+            power_limit: None,
+            scales_power: false,
+            power_scale: 1.0,
             force_fan: false,
             reads_fpga_info: false,
+            captured: None,
+            soft_start: None,
+            soft_start_tick: Box::new(std::thread::sleep),
         })
     }
+
+    /// Ramp the amplitude of the next gain, i.e., the first gain after open, from 0 to the full over `duration`, to avoid the inrush of the transducers
+    ///
+    /// The gain is sent repeatedly with the amplitude scaled up linearly, once per `send_interval` times the EtherCAT cycle.
+    /// Since each step is a whole gain, the ramp takes longer if the gain is sent in multiple frames, e.g., in normal mode.
+    /// Data other than gains do not consume the soft start.
+    pub fn with_soft_start(mut self, duration: std::time::Duration) -> Self {
+        self.soft_start = Some(duration);
+        self
+    }
+
+    /// Replace the wait between the frames of the soft start ramp, which is [std::thread::sleep] by default
+    ///
+    /// `tick` is called with the interval of the frames after each frame of the ramp is sent.
+    pub fn with_soft_start_tick<F: FnMut(std::time::Duration) + Send + 'static>(
+        mut self,
+        tick: F,
+    ) -> Self {
+        self.soft_start_tick = Box::new(tick);
+        self
+    }
 }
 
 impl<L: Link, T: Transducer> Controller<L, T> {
@@ -197,6 +325,19 @@ impl<L: Link, T: Transducer> Controller<L, T> {
         &self.geometry
     }
 
+    pub fn link(&self) -> &L {
+        &self.link
+    }
+
+    /// Set the phase offset of a device applied on sending, see [Geometry::set_device_phase_offset]
+    pub fn set_device_phase_offset(&mut self, device_idx: usize, offset: f64) {
+        self.geometry.set_device_phase_offset(device_idx, offset);
+    }
+
+    pub fn link_mut(&mut self) -> &mut L {
+        &mut self.link
+    }
+
     /// Send header and body to the devices
     ///
     /// # Arguments
@@ -214,7 +355,7 @@ impl<L: Link, T: Transducer> Controller<L, T> {
     /// Clear all data
     pub fn clear(&mut self) -> Result<bool> {
         autd3_core::clear(&mut self.tx_buf);
-        self.link.send(&self.tx_buf)?;
+        self.send_tx()?;
         let success = self.wait_msg_processed(200)? != 200;
         Ok(success)
     }
@@ -236,14 +377,20 @@ impl<L: Link, T: Transducer> Controller<L, T> {
 
         autd3_core::sync(msg_id, &cycles, &mut self.tx_buf)?;
 
-        self.link.send(&self.tx_buf)?;
+        self.send_tx()?;
         Ok(self.wait_msg_processed(200)? != 200)
     }
 
     /// Return firmware information of the devices
+    ///
+    /// Use [FirmwareInfo::mismatched_devices] to find the devices whose firmware versions are inconsistent.
     pub fn firmware_infos(&mut self) -> Result<Vec<FirmwareInfo>> {
+        if !self.supports_read() {
+            return Err(AUTDError::ReadNotSupported.into());
+        }
+
         autd3_core::cpu_version(&mut self.tx_buf);
-        self.link.send(&self.tx_buf)?;
+        self.send_tx()?;
         self.wait_msg_processed(200)?;
         let cpu_versions = self
             .rx_buf
@@ -253,7 +400,7 @@ impl<L: Link, T: Transducer> Controller<L, T> {
             .collect::<Vec<_>>();
 
         autd3_core::fpga_version(&mut self.tx_buf);
-        self.link.send(&self.tx_buf)?;
+        self.send_tx()?;
         self.wait_msg_processed(200)?;
         let fpga_versions = self
             .rx_buf
@@ -263,7 +410,7 @@ impl<L: Link, T: Transducer> Controller<L, T> {
             .collect::<Vec<_>>();
 
         autd3_core::fpga_functions(&mut self.tx_buf);
-        self.link.send(&self.tx_buf)?;
+        self.send_tx()?;
         self.wait_msg_processed(200)?;
         let fpga_functions = self
             .rx_buf
@@ -273,42 +420,199 @@ impl<L: Link, T: Transducer> Controller<L, T> {
             .collect::<Vec<_>>();
 
         Ok((0..self.geometry.num_devices())
-            .map(|i| FirmwareInfo::new(0, cpu_versions[i], fpga_versions[i], fpga_functions[i]))
+            .map(|i| FirmwareInfo::new(i, cpu_versions[i], fpga_versions[i], fpga_functions[i]))
             .collect())
     }
 }
 
 impl<L: Link, T: Transducer> Controller<L, T> {
     pub fn get_id(&self) -> u8 {
-        if MSG_ID
-            .compare_exchange(
-                MSG_END,
-                MSG_BEGIN,
-                atomic::Ordering::SeqCst,
-                atomic::Ordering::SeqCst,
-            )
-            .is_err()
-        {
-            MSG_ID.fetch_add(1, atomic::Ordering::SeqCst);
+        let prev = MSG_ID
+            .fetch_update(atomic::Ordering::SeqCst, atomic::Ordering::SeqCst, |id| {
+                Some(next_msg_id(id))
+            })
+            .unwrap();
+        next_msg_id(prev)
+    }
+
+    /// Run `f` with the link replaced by a capture, and return the bytes of every datagram sent in `f` in order.
+    ///
+    /// The datagrams are not sent to the devices, and all of them are regarded as processed immediately.
+    /// Note that closing the controller in `f` closes the actual link.
+    pub fn capture<F: FnOnce(&mut Self)>(&mut self, f: F) -> Vec<Vec<u8>> {
+        let outer = self.captured.replace(vec![]);
+        f(self);
+        let captured = std::mem::replace(&mut self.captured, outer).unwrap_or_default();
+        if let Some(outer) = &mut self.captured {
+            outer.extend(captured.iter().cloned());
+        }
+        captured
+    }
+
+    /// Number of steps of the soft start ramp
+    fn soft_start_steps(&self, duration: std::time::Duration) -> usize {
+        let frame = std::time::Duration::from_micros(
+            self.send_interval as u64 * autd3_core::EC_CYCLE_TIME_BASE_MICRO_SEC as u64,
+        );
+        ((duration.as_secs_f64() / frame.as_secs_f64()).ceil() as usize).max(1)
+    }
+
+    /// Send `body` repeatedly with the amplitude ramped up from 0 if the soft start is pending and `body` supports scaling.
+    /// The ramp ends just below the full amplitude, which is then sent by the caller as usual.
+    /// The soft start remains pending if the ramp fails.
+    fn soft_start<B: DatagramBody<T>>(&mut self, body: &mut B) -> Result<()> {
+        let steps = match self.soft_start {
+            Some(duration) => self.soft_start_steps(duration),
+            None => return Ok(()),
+        };
+
+        body.init()?;
+        body.pack(&self.geometry, &mut self.tx_buf)?;
+        if !body.set_amplitude_scale(1.0) {
+            return Ok(());
+        }
+
+        self.limit_power(body.total_power(), |_| true)?;
+        let full = self.power_scale;
+        self.power_scale = 1.0;
+
+        let res = self.soft_start_ramp(body, steps, full);
+        body.set_amplitude_scale(1.0);
+        res?;
+
+        self.soft_start = None;
+        Ok(())
+    }
+
+    /// Send the steps of the soft start ramp except the last one, up to the scale of `full`
+    fn soft_start_ramp<B: DatagramBody<T>>(
+        &mut self,
+        body: &mut B,
+        steps: usize,
+        full: f64,
+    ) -> Result<()> {
+        let interval = std::time::Duration::from_micros(
+            self.send_interval as u64 * autd3_core::EC_CYCLE_TIME_BASE_MICRO_SEC as u64,
+        );
+        for step in 1..steps {
+            body.set_amplitude_scale(full * step as f64 / steps as f64);
+
+            let mut header = NullHeader::new();
+            body.init()?;
+            autd3_core::force_fan(&mut self.tx_buf, self.force_fan);
+            autd3_core::reads_fpga_info(&mut self.tx_buf, self.reads_fpga_info);
+            loop {
+                let msg_id = self.get_id();
+                header.pack(msg_id, &mut self.tx_buf)?;
+                body.pack(&self.geometry, &mut self.tx_buf)?;
+                self.tx_buf.validate()?;
+                self.send_tx()?;
+                self.wait_msg_processed(self.check_trials)?;
+                (self.soft_start_tick)(interval);
+                if body.is_finished() {
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn send_tx(&mut self) -> Result<bool> {
+        #[cfg(feature = "checksum")]
+        autd3_core::finalize_checksum(&mut self.tx_buf)?;
+        match &mut self.captured {
+            Some(captured) => {
+                captured.push(self.tx_buf.data()[..self.tx_buf.size()].to_vec());
+                Ok(true)
+            }
+            None => self.link.send(&self.tx_buf),
+        }
+    }
+
+    fn receive_rx(&mut self) -> Result<bool> {
+        if self.captured.is_none() {
+            return self.link.receive(&mut self.rx_buf);
+        }
+        let msg_id = self.tx_buf.header().msg_id;
+        self.rx_buf
+            .messages_mut()
+            .iter_mut()
+            .for_each(|msg| msg.msg_id = msg_id);
+        Ok(true)
+    }
+
+    fn supports_read(&self) -> bool {
+        self.captured.is_some() || self.link.supports_read()
+    }
+
+    /// Scale factor applied to the amplitudes of the last data sent to satisfy `power_limit`. 1 if not scaled.
+    pub fn power_scale(&self) -> f64 {
+        self.power_scale
+    }
+
+    /// Check the total power against `power_limit`, and scale the amplitudes with `scale` if `scales_power` is set.
+    /// Returns whether the amplitudes are scaled.
+    fn limit_power<F: FnOnce(f64) -> bool>(
+        &mut self,
+        power: Option<f64>,
+        scale: F,
+    ) -> Result<bool> {
+        match (power, self.power_limit) {
+            (Some(power), Some(limit)) if power > limit => {
+                let factor = (limit / power).sqrt();
+                if self.scales_power && scale(factor) {
+                    self.power_scale = factor;
+                    return Ok(true);
+                }
+                Err(AUTDError::PowerLimitExceeded(power, limit).into())
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Check that the data to be sent is made for the transducers of the geometry
+    fn check_transducer_kind(&self, kind: Option<TransducerKind>) -> Result<()> {
+        match kind {
+            Some(kind) if kind != self.geometry.transducer_kind() => {
+                Err(AUTDError::TransducerKindMismatch(kind, self.geometry.transducer_kind()).into())
+            }
+            _ => Ok(()),
         }
-        MSG_ID.load(atomic::Ordering::SeqCst)
     }
 
     fn wait_msg_processed(&mut self, max_trial: usize) -> Result<usize> {
+        if !self.supports_read() {
+            return Ok(0);
+        }
+
         let msg_id = self.tx_buf.header().msg_id;
-        let wait = self.send_interval as u64 * autd3_core::EC_CYCLE_TIME_BASE_MICRO_SEC as u64;
+        let wait = self.poll_interval.unwrap_or_else(|| {
+            std::time::Duration::from_micros(
+                self.send_interval as u64 * autd3_core::EC_CYCLE_TIME_BASE_MICRO_SEC as u64,
+            )
+        });
         let mut i = 0;
         for _ in 0..max_trial {
-            if self.link.receive(&mut self.rx_buf)? && is_msg_processed(msg_id, &self.rx_buf) {
+            if self.receive_rx()? && is_msg_processed(msg_id, &self.rx_buf) {
                 break;
             }
-            std::thread::sleep(std::time::Duration::from_micros(wait));
+            std::thread::sleep(wait);
             i += 1;
         }
         Ok(i)
     }
 }
 
+impl<L: Link, T: Transducer> Drop for Controller<L, T> {
+    fn drop(&mut self) {
+        if !self.link.is_open() {
+            return;
+        }
+        let _ = self.clear();
+        let _ = self.link.close();
+    }
+}
+
 impl<L: Link> Controller<L, LegacyTransducer> {
     /// Stop outputting
     pub fn stop(&mut self) -> Result<bool> {
@@ -374,3 +678,561 @@ impl<L: Link> Controller<L, NormalPhaseTransducer> {
         Ok(res)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicBool, AtomicUsize},
+        Arc, Mutex,
+    };
+
+    use autd3_core::{
+        gain::{Gain, IGain},
+        geometry::{GeometryBuilder, Vector3},
+        modulation::Modulation,
+        stm::{GainSTM, PointSTM, STM},
+        CPUControlFlags, FPGAControlFlags, RxMessage,
+    };
+    use autd3_firmware_emulator::Emulator;
+
+    use super::*;
+    use crate::{gain::Focus, link::NullLink, modulation::Sine, prelude::Static};
+
+    macro_rules! send_all {
+        ($geometry:expr) => {{
+            let mut geometry = $geometry;
+            geometry.add_device(Vector3::zeros(), Vector3::zeros());
+            geometry.add_device(Vector3::new(192., 0., 0.), Vector3::zeros());
+            let mut autd = Controller::open(geometry, NullLink::new()).unwrap();
+            let center = autd.geometry().center() + Vector3::new(0., 0., 150.);
+
+            autd.send(&mut SilencerConfig::default()).flush().unwrap();
+            autd.send(&mut Focus::new(center)).flush().unwrap();
+            autd.send(&mut Sine::new(150)).flush().unwrap();
+            autd.send(&mut Sine::new(150))
+                .send(&mut Focus::new(center))
+                .unwrap();
+
+            let mut stm = PointSTM::new();
+            (0..10).for_each(|i| {
+                stm.add(center + Vector3::new(i as f64, 0., 0.), 0).unwrap();
+            });
+            autd.send(&mut Static::new(0xFF)).send(&mut stm).unwrap();
+
+            let mut stm = GainSTM::new();
+            (0..10).for_each(|i| {
+                stm.add(
+                    Focus::new(center + Vector3::new(i as f64, 0., 0.)),
+                    autd.geometry(),
+                )
+                .unwrap();
+            });
+            autd.send(&mut stm).flush().unwrap();
+
+            // after STM, ordinary frames must still pass the validation
+            autd.send(&mut SilencerConfig::none()).flush().unwrap();
+            autd.send(&mut Focus::new(center)).flush().unwrap();
+            autd.send(&mut Sine::new(200)).flush().unwrap();
+
+            autd.close().unwrap();
+        }};
+    }
+
+    #[cfg(feature = "checksum")]
+    #[test]
+    fn send_with_checksum() {
+        let mut geometry = GeometryBuilder::new().legacy_mode().build();
+        geometry.add_device(Vector3::zeros(), Vector3::zeros());
+        let mut autd = Controller::open(geometry, NullLink::new()).unwrap();
+        let center = autd.geometry().center() + Vector3::new(0., 0., 150.);
+
+        let frames = autd.capture(|autd| {
+            autd.send(&mut Sine::new(10))
+                .send(&mut Focus::new(center))
+                .unwrap();
+        });
+        assert!(frames.len() > 3);
+        frames.iter().for_each(|frame| {
+            let mut tx = TxDatagram::new(1);
+            tx.num_bodies =
+                (frame.len() - autd3_core::HEADER_SIZE) / std::mem::size_of::<autd3_core::Body>();
+            tx.data_mut()[..frame.len()].copy_from_slice(frame);
+            assert!(autd3_core::verify_checksum(&tx));
+        });
+
+        autd.close().unwrap();
+    }
+
+    /// Gain calculated for normal transducers, but created for `kind` transducers, as a gain given over FFI may be
+    struct Foreign(Focus<NormalTransducer>, TransducerKind);
+
+    impl IGain<NormalTransducer> for Foreign {
+        fn calc(&mut self, geometry: &Geometry<NormalTransducer>) -> Result<()> {
+            self.0.calc(geometry)
+        }
+    }
+
+    impl DatagramBody<NormalTransducer> for Foreign {
+        fn init(&mut self) -> Result<()> {
+            DatagramBody::<NormalTransducer>::init(&mut self.0)
+        }
+
+        fn pack(
+            &mut self,
+            geometry: &Geometry<NormalTransducer>,
+            tx: &mut TxDatagram,
+        ) -> Result<()> {
+            DatagramBody::<NormalTransducer>::pack(&mut self.0, geometry, tx)
+        }
+
+        fn is_finished(&self) -> bool {
+            DatagramBody::<NormalTransducer>::is_finished(&self.0)
+        }
+
+        fn target_kind(&self) -> Option<TransducerKind> {
+            Some(self.1)
+        }
+    }
+
+    impl Gain<NormalTransducer> for Foreign {
+        fn build(&mut self, geometry: &Geometry<NormalTransducer>) -> Result<()> {
+            self.0.build(geometry)
+        }
+
+        fn rebuild(&mut self, geometry: &Geometry<NormalTransducer>) -> Result<()> {
+            self.0.rebuild(geometry)
+        }
+
+        fn drives(&self) -> &[autd3_core::Drive] {
+            self.0.drives()
+        }
+
+        fn take_drives(self) -> Vec<autd3_core::Drive> {
+            self.0.take_drives()
+        }
+
+        fn built(&self) -> bool {
+            self.0.built()
+        }
+
+        fn transducer_kind(&self) -> TransducerKind {
+            self.1
+        }
+    }
+
+    #[test]
+    fn reject_mismatched_transducer_kind() {
+        let mut geometry = GeometryBuilder::new().build();
+        geometry.add_device(Vector3::zeros(), Vector3::zeros());
+        let mut autd = Controller::open(geometry, NullLink::new()).unwrap();
+        let center = autd.geometry().center() + Vector3::new(0., 0., 150.);
+
+        let frames = autd.capture(|autd| {
+            let mut g: Box<dyn Gain<NormalTransducer>> =
+                Box::new(Foreign(Focus::new(center), TransducerKind::Legacy));
+            let res = autd.send(&mut g).flush();
+            assert!(matches!(
+                res.unwrap_err().downcast_ref::<AUTDError>(),
+                Some(AUTDError::TransducerKindMismatch(
+                    TransducerKind::Legacy,
+                    TransducerKind::Normal
+                ))
+            ));
+
+            let mut g: Box<dyn Gain<NormalTransducer>> =
+                Box::new(Foreign(Focus::new(center), TransducerKind::Legacy));
+            let res = autd.send(&mut Sine::new(150)).send(&mut g);
+            assert!(res.is_err());
+        });
+        assert!(frames.is_empty());
+
+        let mut g: Box<dyn Gain<NormalTransducer>> =
+            Box::new(Foreign(Focus::new(center), TransducerKind::Normal));
+        assert!(autd.send(&mut g).flush().unwrap());
+
+        autd.close().unwrap();
+    }
+
+    #[test]
+    fn soft_start_ramp() {
+        const STEPS: usize = 4;
+
+        let mut geometry = GeometryBuilder::new().legacy_mode().build();
+        geometry.add_device(Vector3::zeros(), Vector3::zeros());
+        let ticks = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+        let ticks_ = ticks.clone();
+        let mut autd = Controller::open(geometry, NullLink::new())
+            .unwrap()
+            .with_soft_start(std::time::Duration::from_micros(
+                STEPS as u64 * autd3_core::EC_CYCLE_TIME_BASE_MICRO_SEC as u64,
+            ))
+            .with_soft_start_tick(move |interval| ticks_.lock().unwrap().push(interval));
+        let center = autd.geometry().center() + Vector3::new(0., 0., 150.);
+        let amp = |frame: &Vec<u8>| {
+            autd3_core::LegacyDrive {
+                phase: frame[autd3_core::HEADER_SIZE],
+                duty: frame[autd3_core::HEADER_SIZE + 1],
+            }
+            .to_phase_amp()
+            .1
+        };
+
+        // the soft start remains pending if the first gain is rejected
+        autd.power_limit = Some(0.);
+        let frames = autd.capture(|autd| {
+            assert!(autd.send(&mut Focus::new(center)).flush().is_err());
+        });
+        assert!(frames.is_empty());
+        assert!(ticks.lock().unwrap().is_empty());
+        autd.power_limit = None;
+
+        let mut g = Focus::new(center);
+        let frames = autd.capture(|autd| {
+            autd.send(&mut g).flush().unwrap();
+        });
+        assert_eq!(frames.len(), STEPS);
+        frames.iter().enumerate().for_each(|(i, frame)| {
+            assert!((amp(frame) - (i + 1) as f64 / STEPS as f64).abs() < 1e-2);
+        });
+        assert_eq!(
+            *ticks.lock().unwrap(),
+            vec![
+                std::time::Duration::from_micros(autd3_core::EC_CYCLE_TIME_BASE_MICRO_SEC as u64);
+                STEPS - 1
+            ]
+        );
+        assert!(g.drives().iter().all(|d| d.amp == 1.0));
+
+        // only the first gain is ramped
+        let frames = autd.capture(|autd| {
+            autd.send(&mut Focus::new(center)).flush().unwrap();
+        });
+        assert_eq!(frames.len(), 1);
+        assert_eq!(amp(&frames[0]), 1.0);
+
+        autd.close().unwrap();
+    }
+
+    #[test]
+    fn reject_power_over_limit() {
+        let mut geometry = GeometryBuilder::new().legacy_mode().build();
+        geometry.add_device(Vector3::zeros(), Vector3::zeros());
+        let mut autd = Controller::open(geometry, NullLink::new()).unwrap();
+        let center = autd.geometry().center() + Vector3::new(0., 0., 150.);
+        let full = NUM_TRANS_IN_UNIT as f64;
+
+        autd.power_limit = Some(full / 4.);
+        let frames = autd.capture(|autd| {
+            let err = autd.send(&mut Focus::new(center)).flush().unwrap_err();
+            match err.downcast_ref::<AUTDError>() {
+                Some(&AUTDError::PowerLimitExceeded(power, limit)) => {
+                    assert_eq!(power, full);
+                    assert_eq!(limit, full / 4.);
+                }
+                _ => panic!("{}", err),
+            }
+        });
+        assert!(frames.is_empty());
+
+        // the power of a half amplitude focus is a quarter of the full one
+        let frames = autd.capture(|autd| {
+            autd.send(&mut Focus::with_amp(center, 0.5))
+                .flush()
+                .unwrap();
+        });
+        assert_eq!(frames.len(), 1);
+
+        autd.close().unwrap();
+    }
+
+    #[test]
+    fn limit_power_by_scaling() {
+        const LIMIT: f64 = 100.;
+
+        let mut geometry = GeometryBuilder::new().legacy_mode().build();
+        geometry.add_device(Vector3::zeros(), Vector3::zeros());
+        let mut autd = Controller::open(geometry, NullLink::new()).unwrap();
+        autd.power_limit = Some(LIMIT);
+        autd.scales_power = true;
+        let center = autd.geometry().center() + Vector3::new(0., 0., 150.);
+        let power = |frame: &Vec<u8>| -> f64 {
+            frame[autd3_core::HEADER_SIZE..]
+                .chunks(2)
+                .map(|d| {
+                    autd3_core::LegacyDrive {
+                        phase: d[0],
+                        duty: d[1],
+                    }
+                    .to_phase_amp()
+                    .1
+                })
+                .map(|amp| amp * amp)
+                .sum()
+        };
+
+        let mut g = Focus::new(center);
+        let frames = autd.capture(|autd| {
+            autd.send(&mut g).flush().unwrap();
+        });
+        let full = NUM_TRANS_IN_UNIT as f64;
+        assert_eq!(frames.len(), 1);
+        assert!((power(&frames[0]) - LIMIT).abs() < LIMIT * 1e-2);
+        assert!((autd.power_scale() - (LIMIT / full).sqrt()).abs() < 1e-9);
+        // the gain itself is left as is
+        assert!(g.drives().iter().all(|d| d.amp == 1.0));
+
+        autd.power_limit = None;
+        let frames = autd.capture(|autd| {
+            autd.send(&mut g).flush().unwrap();
+        });
+        assert!((power(&frames[0]) - full).abs() < 1e-9);
+        assert_eq!(autd.power_scale(), 1.0);
+
+        autd.close().unwrap();
+    }
+
+    #[test]
+    fn capture_focus() {
+        let mut geometry = GeometryBuilder::new().build();
+        geometry.add_device(Vector3::zeros(), Vector3::zeros());
+        geometry.add_device(Vector3::new(192., 0., 0.), Vector3::zeros());
+        let mut autd = Controller::open(geometry, NullLink::new()).unwrap();
+        let center = autd.geometry().center() + Vector3::new(0., 0., 150.);
+
+        let frames = autd.capture(|autd| {
+            autd.send(&mut Focus::new(center)).flush().unwrap();
+        });
+
+        // phase and then duty
+        assert_eq!(frames.len(), 2);
+        frames
+            .iter()
+            .zip([false, true])
+            .for_each(|(frame, is_duty)| {
+                assert_eq!(
+                    frame.len(),
+                    autd3_core::HEADER_SIZE + 2 * std::mem::size_of::<autd3_core::Body>()
+                );
+                let fpga_flag = FPGAControlFlags::from_bits_truncate(frame[1]);
+                let cpu_flag = CPUControlFlags::from_bits_truncate(frame[2]);
+                assert!(!fpga_flag.contains(FPGAControlFlags::LEGACY_MODE));
+                assert!(!fpga_flag.contains(FPGAControlFlags::STM_MODE));
+                assert!(cpu_flag.contains(CPUControlFlags::WRITE_BODY));
+                assert_eq!(cpu_flag.contains(CPUControlFlags::IS_DUTY), is_duty);
+            });
+        assert_ne!(frames[0][0], frames[1][0]);
+
+        autd.close().unwrap();
+    }
+
+    #[test]
+    fn independent_mod_and_stm_freq_div() {
+        let mut geometry = GeometryBuilder::new().legacy_mode().build();
+        geometry.add_device(Vector3::zeros(), Vector3::zeros());
+        let mut autd = Controller::open(geometry, NullLink::new()).unwrap();
+        let center = autd.geometry().center() + Vector3::new(0., 0., 150.);
+
+        let mut m = Sine::new(10);
+        *m.sampling_frequency_division() = 40960;
+        let mut stm = PointSTM::new();
+        (0..10).for_each(|i| {
+            stm.add(center + Vector3::new(i as f64, 0., 0.), 0).unwrap();
+        });
+
+        for stm_div in [1612, 40960, 100000] {
+            stm.set_sampling_freq_div(stm_div);
+            let frames = autd.capture(|autd| {
+                autd.send(&mut m).send(&mut stm).unwrap();
+            });
+
+            let mut tx = TxDatagram::new(1);
+            tx.data_mut()[..frames[0].len()].copy_from_slice(&frames[0]);
+            assert_eq!(tx.header().mod_head().freq_div, 40960);
+            let d = tx.body()[0].point_stm_head().data();
+            assert_eq!(d[1] as u32 | (d[2] as u32) << 16, stm_div);
+        }
+
+        autd.close().unwrap();
+    }
+
+    /// Link to emulated devices, which keeps the sent datagrams and whether it is open after the controller is dropped
+    #[derive(Clone)]
+    struct EmulatorLink {
+        emulator: Arc<Mutex<Emulator>>,
+        sent: Arc<Mutex<Vec<Vec<u8>>>>,
+        is_open: Arc<AtomicBool>,
+    }
+
+    impl EmulatorLink {
+        fn new(num_devices: usize) -> Self {
+            let mut emulator = Emulator::new();
+            emulator.init(num_devices);
+            Self {
+                emulator: Arc::new(Mutex::new(emulator)),
+                sent: Arc::new(Mutex::new(vec![])),
+                is_open: Arc::new(AtomicBool::new(false)),
+            }
+        }
+    }
+
+    impl Link for EmulatorLink {
+        fn open<T: Transducer>(&mut self, _geometry: &Geometry<T>) -> Result<()> {
+            self.is_open.store(true, atomic::Ordering::SeqCst);
+            Ok(())
+        }
+
+        fn close(&mut self) -> Result<()> {
+            self.is_open.store(false, atomic::Ordering::SeqCst);
+            Ok(())
+        }
+
+        fn send(&mut self, tx: &TxDatagram) -> Result<bool> {
+            self.sent
+                .lock()
+                .unwrap()
+                .push(tx.data()[..tx.size()].to_vec());
+            self.emulator.lock().unwrap().send(tx);
+            Ok(true)
+        }
+
+        fn receive(&mut self, rx: &mut RxDatagram) -> Result<bool> {
+            self.emulator.lock().unwrap().read(rx);
+            Ok(true)
+        }
+
+        fn is_open(&self) -> bool {
+            self.is_open.load(atomic::Ordering::SeqCst)
+        }
+    }
+
+    #[test]
+    fn drop_clears_and_closes() {
+        let mut geometry = GeometryBuilder::new().build();
+        geometry.add_device(Vector3::zeros(), Vector3::zeros());
+        let link = EmulatorLink::new(1);
+        let mut autd = Controller::open(geometry, link.clone()).unwrap();
+        let center = autd.geometry().center() + Vector3::new(0., 0., 150.);
+
+        autd.send(&mut Sine::new(150))
+            .send(&mut Focus::new(center))
+            .unwrap();
+        assert!(link
+            .emulator
+            .lock()
+            .unwrap()
+            .fpga(0)
+            .modulation()
+            .0
+            .iter()
+            .any(|&m| m != 0));
+
+        drop(autd);
+
+        // the devices are cleared and the link is closed
+        assert_eq!(
+            link.sent.lock().unwrap().last().unwrap()[0],
+            autd3_core::MSG_CLEAR
+        );
+        assert!(!link.is_open());
+        assert!(link
+            .emulator
+            .lock()
+            .unwrap()
+            .fpga(0)
+            .modulation()
+            .0
+            .iter()
+            .all(|&m| m == 0));
+    }
+
+    /// Link which only writes to the devices
+    struct WriteOnlyLink(NullLink, Arc<AtomicUsize>);
+
+    impl Link for WriteOnlyLink {
+        fn open<T: Transducer>(&mut self, geometry: &Geometry<T>) -> Result<()> {
+            self.0.open(geometry)
+        }
+
+        fn close(&mut self) -> Result<()> {
+            self.0.close()
+        }
+
+        fn send(&mut self, tx: &TxDatagram) -> Result<bool> {
+            self.0.send(tx)
+        }
+
+        fn receive(&mut self, _rx: &mut RxDatagram) -> Result<bool> {
+            self.1.fetch_add(1, atomic::Ordering::SeqCst);
+            Ok(false)
+        }
+
+        fn is_open(&self) -> bool {
+            self.0.is_open()
+        }
+
+        fn supports_read(&self) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn skip_reading_on_write_only_link() {
+        let mut geometry = GeometryBuilder::new().build();
+        geometry.add_device(Vector3::zeros(), Vector3::zeros());
+        let receives = Arc::new(AtomicUsize::new(0));
+        let mut autd =
+            Controller::open(geometry, WriteOnlyLink(NullLink::new(), receives.clone())).unwrap();
+        let center = autd.geometry().center() + Vector3::new(0., 0., 150.);
+
+        autd.send(&mut Sine::new(150))
+            .send(&mut Focus::new(center))
+            .unwrap();
+        assert!(matches!(
+            autd.firmware_infos()
+                .err()
+                .unwrap()
+                .downcast_ref::<AUTDError>(),
+            Some(AUTDError::ReadNotSupported)
+        ));
+        autd.close().unwrap();
+
+        assert_eq!(receives.load(atomic::Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn poll_interval() {
+        const TRIALS: usize = 5;
+        const INTERVAL: std::time::Duration = std::time::Duration::from_millis(10);
+
+        let mut geometry = GeometryBuilder::new().legacy_mode().build();
+        geometry.add_device(Vector3::zeros(), Vector3::zeros());
+        let mut autd = Controller::open(geometry, NullLink::new()).unwrap();
+        autd.check_trials = TRIALS;
+        autd.poll_interval = Some(INTERVAL);
+
+        // the devices never acknowledge
+        autd.link_mut().set_rx(Some(vec![RxMessage::new()]));
+        let start = std::time::Instant::now();
+        assert!(!autd.send(&mut SilencerConfig::default()).flush().unwrap());
+        assert!(start.elapsed() >= INTERVAL * TRIALS as u32);
+
+        autd.link_mut().set_rx(None);
+        assert!(autd.send(&mut SilencerConfig::default()).flush().unwrap());
+        autd.close().unwrap();
+    }
+
+    #[test]
+    fn send_validated_legacy() {
+        send_all!(GeometryBuilder::new().legacy_mode().build());
+    }
+
+    #[test]
+    fn send_validated_normal() {
+        send_all!(GeometryBuilder::new().build());
+    }
+
+    #[test]
+    fn send_validated_normal_phase() {
+        send_all!(GeometryBuilder::new().normal_phase_mode().build());
+    }
+}
@@ -11,10 +11,35 @@
  *
  */
 
+use autd3_core::geometry::TransducerKind;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum AUTDError {
     #[error("Device id ({0}) is specified, but only {1} AUTDs are connected.")]
     GroupedOutOfRange(usize, usize),
+    #[error("The link does not support reading data from the devices")]
+    ReadNotSupported,
+    #[error("Steering angle ({0:.1} deg) exceeds the limit ({1:.1} deg)")]
+    SteeringAngleExceeded(f64, f64),
+    #[error("Failed to parse expression at {0}: {1}")]
+    ExprParse(usize, String),
+    #[error("The send was canceled because the controller has been stopped")]
+    SendCanceled,
+    #[error("Total power of the gain ({0:.1}) exceeds the limit ({1:.1})")]
+    PowerLimitExceeded(f64, f64),
+    #[error("The data for {0:?} transducers cannot be sent to the geometry of {1:?} transducers")]
+    TransducerKindMismatch(TransducerKind, TransducerKind),
+    #[error("Modulation buffer must not be empty")]
+    EmptyModulationBuffer,
+    #[error("Only monaural WAV files are supported, but the file has {0} channels")]
+    WavNotMonaural(u16),
+    #[error("{0}-bit {1} WAV files are not supported")]
+    WavUnsupportedFormat(u16, String),
+    #[error("The links of the bundle are assigned {0} devices in total, but the geometry has {1}")]
+    BundleDeviceMismatch(usize, usize),
+    #[error("Cutoff frequency must be positive, but got {0}")]
+    InvalidCutoffFrequency(f64),
+    #[error("The record of sent data has an invalid length ({0} bytes)")]
+    InvalidRecordLength(usize),
 }
@@ -0,0 +1,106 @@
+/*
+ * File: dither.rs
+ * Project: gain
+ * Created Date: 15/10/2022
+ * Author: Shun Suzuki
+ * -----
+ * Last Modified: 15/10/2022
+ * Modified By: Shun Suzuki (suzuki@hapis.k.u-tokyo.ac.jp)
+ * -----
+ * Copyright (c) 2022 Shun Suzuki. All rights reserved.
+ *
+ */
+
+use std::f64::consts::PI;
+
+use autd3_core::{
+    gain::{Gain, GainProps, IGain},
+    geometry::{Geometry, Transducer},
+};
+
+use autd3_traits::Gain;
+
+/// Gain to approximate the amplitude of the inner gain finer than the duty resolution
+///
+/// The duty is quantized with error diffusion over frames, so that the time-averaged duty approaches the requested one.
+/// Call `rebuild` before sending each frame to advance the diffusion.
+#[derive(Gain)]
+pub struct Dither<G: Gain<T>, T: Transducer> {
+    props: GainProps<T>,
+    gain: G,
+    error: Vec<f64>,
+}
+
+impl<G: Gain<T>, T: Transducer> Dither<G, T> {
+    /// constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `gain` - gain to be dithered
+    ///
+    pub fn new(gain: G) -> Self {
+        Self {
+            props: GainProps::new(),
+            gain,
+            error: vec![],
+        }
+    }
+}
+
+impl<G: Gain<T>, T: Transducer> IGain<T> for Dither<G, T> {
+    fn calc(&mut self, geometry: &Geometry<T>) -> anyhow::Result<()> {
+        self.gain.build(geometry)?;
+
+        self.error.resize(geometry.num_transducers(), 0.0);
+
+        geometry.transducers().for_each(|tr| {
+            let d = self.gain.drives()[tr.id()];
+            let scale = tr.duty_scale();
+            let target = scale * d.amp.clamp(0.0, 1.0).asin() / PI + self.error[tr.id()];
+            let duty = target.round().clamp(0.0, scale / 2.0);
+            self.error[tr.id()] = target - duty;
+            self.props.drives[tr.id()].amp = (duty * PI / scale).sin();
+            self.props.drives[tr.id()].phase = d.phase;
+        });
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use autd3_core::geometry::{GeometryBuilder, Vector3};
+
+    use super::*;
+    use crate::gain::Focus;
+
+    #[test]
+    fn averaged_duty_approaches_target() {
+        const FRAMES: usize = 100;
+        const TARGET_DUTY: f64 = 100.3;
+
+        let mut geometry = GeometryBuilder::new().legacy_mode().build();
+        geometry.add_device(Vector3::zeros(), Vector3::zeros());
+        let center = geometry.center() + Vector3::new(0., 0., 150.);
+        let scale = geometry.transducers().next().unwrap().duty_scale();
+
+        let amp = (TARGET_DUTY * PI / scale).sin();
+        let mut dither = Dither::new(Focus::with_amp(center, amp));
+        let mut sum = vec![0.0; geometry.num_transducers()];
+        (0..FRAMES).for_each(|_| {
+            dither.rebuild(&geometry).unwrap();
+            dither
+                .drives()
+                .iter()
+                .zip(sum.iter_mut())
+                .for_each(|(d, s)| {
+                    let duty = scale * d.amp.asin() / PI;
+                    // every frame is quantized to the duty resolution
+                    assert!((duty - duty.round()).abs() < 1e-6);
+                    *s += duty;
+                });
+        });
+        sum.iter()
+            .for_each(|s| assert!((s / FRAMES as f64 - TARGET_DUTY).abs() < 0.02));
+    }
+}
@@ -11,6 +11,8 @@
  *
  */
 
+use std::marker::PhantomData;
+
 use autd3_core::{
     gain::{Gain, GainProps, IGain},
     geometry::{Geometry, Transducer, Vector3},
@@ -18,12 +20,15 @@ use autd3_core::{
 
 use autd3_traits::Gain;
 
+use crate::error::AUTDError;
+
 /// Gain to produce single focal point
 #[derive(Gain)]
 pub struct Focus<T: Transducer> {
     props: GainProps<T>,
     amp: f64,
     pos: Vector3,
+    max_angle: Option<f64>,
 }
 
 impl<T: Transducer> Focus<T> {
@@ -49,12 +54,39 @@ impl<T: Transducer> Focus<T> {
             props: GainProps::new(),
             amp,
             pos,
+            max_angle: None,
+        }
+    }
+
+    /// Limit the steering angle
+    ///
+    /// # Arguments
+    ///
+    /// * `deg` - maximum angle in degree between the normal of each device and the direction from the center of the device to the focal point
+    ///
+    /// The normal is taken from the actual rotation of each device, so arrays facing downward can focus below themselves.
+    ///
+    pub fn with_max_angle(self, deg: f64) -> Self {
+        Self {
+            max_angle: Some(deg),
+            ..self
         }
     }
 }
 
 impl<T: Transducer> IGain<T> for Focus<T> {
     fn calc(&mut self, geometry: &Geometry<T>) -> anyhow::Result<()> {
+        if let Some(max_angle) = self.max_angle {
+            geometry.devices().iter().try_for_each(|dev| {
+                let normal = dev.z_direction();
+                let angle = normal.angle(&(self.pos - dev.center())).to_degrees();
+                if angle > max_angle {
+                    return Err(AUTDError::SteeringAngleExceeded(angle, max_angle));
+                }
+                Ok(())
+            })?;
+        }
+
         geometry.transducers().for_each(|tr| {
             let dist = (self.pos - tr.position()).norm();
             let phase = tr.align_phase_at(dist, geometry.sound_speed());
@@ -65,3 +97,168 @@ impl<T: Transducer> IGain<T> for Focus<T> {
         Ok(())
     }
 }
+
+/// Iterator yielding [Focus] at each point of a grid, e.g., for field scanning
+///
+/// The points are yielded in the order of x, y, and z, with their index.
+pub struct FocusGrid<T: Transducer> {
+    origin: Vector3,
+    step: Vector3,
+    num: [usize; 3],
+    idx: usize,
+    _t: PhantomData<T>,
+}
+
+impl<T: Transducer> FocusGrid<T> {
+    /// constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `origin` - position of the first grid point
+    /// * `step` - interval of the grid along each axis
+    /// * `num` - number of grid points along each axis
+    ///
+    pub fn new(origin: Vector3, step: Vector3, num: [usize; 3]) -> Self {
+        Self {
+            origin,
+            step,
+            num,
+            idx: 0,
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<T: Transducer> Iterator for FocusGrid<T> {
+    type Item = (usize, Vector3, Focus<T>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let [nx, ny, nz] = self.num;
+        if self.idx >= nx * ny * nz {
+            return None;
+        }
+        let idx = self.idx;
+        self.idx += 1;
+
+        let (ix, iy, iz) = (idx % nx, (idx / nx) % ny, idx / (nx * ny));
+        let pos = self.origin
+            + Vector3::new(
+                ix as f64 * self.step.x,
+                iy as f64 * self.step.y,
+                iz as f64 * self.step.z,
+            );
+        Some((idx, pos, Focus::new(pos)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.num.iter().product::<usize>() - self.idx;
+        (remaining, Some(remaining))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use autd3_core::geometry::{GeometryBuilder, LegacyTransducer};
+
+    use super::*;
+
+    fn geometry() -> Geometry<LegacyTransducer> {
+        let mut geometry = GeometryBuilder::new().legacy_mode().build();
+        geometry.add_device(Vector3::zeros(), Vector3::zeros());
+        geometry
+    }
+
+    #[test]
+    fn max_angle() {
+        let geometry = geometry();
+        let center = geometry.center();
+
+        let mut focus = Focus::new(center + Vector3::new(0., 0., 150.)).with_max_angle(30.);
+        assert!(focus.build(&geometry).is_ok());
+
+        let mut focus = Focus::new(center + Vector3::new(150., 0., 150.)).with_max_angle(30.);
+        match focus
+            .calc(&geometry)
+            .unwrap_err()
+            .downcast_ref::<AUTDError>()
+        {
+            Some(&AUTDError::SteeringAngleExceeded(angle, max)) => {
+                assert!((angle - 45.).abs() < 1e-6);
+                assert_eq!(max, 30.);
+            }
+            _ => panic!("steering angle must be rejected"),
+        }
+
+        let mut focus = Focus::new(center + Vector3::new(150., 0., 150.)).with_max_angle(50.);
+        assert!(focus.build(&geometry).is_ok());
+    }
+
+    #[test]
+    fn focus_grid() {
+        let origin = Vector3::new(10., 20., 150.);
+        let step = Vector3::new(1., 2., 3.);
+        let grid = FocusGrid::<LegacyTransducer>::new(origin, step, [3, 2, 2]);
+        assert_eq!(grid.size_hint(), (12, Some(12)));
+
+        let points = grid.map(|(i, pos, _)| (i, pos)).collect::<Vec<_>>();
+        assert_eq!(points.len(), 12);
+        points
+            .iter()
+            .enumerate()
+            .for_each(|(i, &(idx, _))| assert_eq!(i, idx));
+        assert_eq!(points[0].1, origin);
+        assert_eq!(points[1].1, origin + Vector3::new(1., 0., 0.));
+        assert_eq!(points[3].1, origin + Vector3::new(0., 2., 0.));
+        assert_eq!(points[6].1, origin + Vector3::new(0., 0., 3.));
+        assert_eq!(points[11].1, origin + Vector3::new(2., 2., 3.));
+
+        let mut grid = FocusGrid::<LegacyTransducer>::new(origin, step, [3, 2, 2]);
+        grid.next();
+        assert_eq!(grid.size_hint(), (11, Some(11)));
+        assert_eq!(
+            FocusGrid::<LegacyTransducer>::new(origin, step, [3, 0, 2]).count(),
+            0
+        );
+    }
+
+    #[test]
+    fn focus_grid_yields_focus_at_point() {
+        let geometry = geometry();
+        let origin = geometry.center() + Vector3::new(0., 0., 150.);
+        FocusGrid::new(origin, Vector3::new(10., 10., 10.), [2, 2, 1]).for_each(
+            |(_, pos, mut focus)| {
+                focus.build(&geometry).unwrap();
+                let mut expected = Focus::new(pos);
+                expected.build(&geometry).unwrap();
+                focus
+                    .drives()
+                    .iter()
+                    .zip(expected.drives().iter())
+                    .for_each(|(a, b)| assert_eq!(a.phase, b.phase));
+            },
+        );
+    }
+
+    #[test]
+    fn max_angle_of_downward_device() {
+        let mut geometry = GeometryBuilder::new().legacy_mode().build();
+        geometry.add_device(
+            Vector3::new(0., 0., 300.),
+            Vector3::new(0., std::f64::consts::PI, 0.),
+        );
+        let center = geometry.center();
+        assert!((geometry.devices()[0].z_direction() + Vector3::z()).norm() < 1e-9);
+
+        let mut focus = Focus::new(center - Vector3::new(0., 0., 150.)).with_max_angle(10.);
+        assert!(focus.build(&geometry).is_ok());
+
+        let mut focus = Focus::new(center + Vector3::new(0., 0., 150.)).with_max_angle(10.);
+        assert!(matches!(
+            focus
+                .build(&geometry)
+                .unwrap_err()
+                .downcast_ref::<AUTDError>(),
+            Some(AUTDError::SteeringAngleExceeded(_, _))
+        ));
+    }
+}
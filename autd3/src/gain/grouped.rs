@@ -41,6 +41,14 @@ impl<'a, T: Transducer> Grouped<'a, T> {
     pub fn add<G: 'a + Gain<T>>(&mut self, id: usize, gain: G) {
         self.gain_map.insert(id, Box::new(gain));
     }
+
+    /// Merged output of all groups
+    ///
+    /// This is valid only after the gain is built.
+    /// Devices not assigned to any group have zero amplitude.
+    pub fn combined_drives(&self) -> &GainProps<T> {
+        &self.props
+    }
 }
 
 impl<'a, T: Transducer> IGain<T> for Grouped<'a, T>
@@ -57,9 +65,9 @@ where
                 return Err(AUTDError::GroupedOutOfRange(*dev_id, geometry.num_devices()).into());
             }
 
-            self.props.drives[*dev_id..(*dev_id + autd3_core::NUM_TRANS_IN_UNIT)].copy_from_slice(
-                &gain.drives()[*dev_id..(*dev_id + autd3_core::NUM_TRANS_IN_UNIT)],
-            );
+            let start = dev_id * autd3_core::NUM_TRANS_IN_UNIT;
+            let end = start + autd3_core::NUM_TRANS_IN_UNIT;
+            self.props.drives[start..end].copy_from_slice(&gain.drives()[start..end]);
 
             Ok(())
         })
@@ -71,3 +79,69 @@ impl<'a, T: Transducer> Default for Grouped<'a, T> {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use autd3_core::{
+        geometry::{GeometryBuilder, LegacyTransducer, Vector3},
+        NUM_TRANS_IN_UNIT,
+    };
+
+    use super::*;
+    use crate::gain::Focus;
+
+    fn geometry() -> Geometry<LegacyTransducer> {
+        let mut geometry = GeometryBuilder::new().legacy_mode().build();
+        (0..3).for_each(|i| {
+            geometry.add_device(Vector3::new(192. * i as f64, 0., 0.), Vector3::zeros());
+        });
+        geometry
+    }
+
+    #[test]
+    fn combined_drives() {
+        let geometry = geometry();
+        let a = geometry.center() + Vector3::new(-50., 0., 150.);
+        let b = geometry.center() + Vector3::new(50., 0., 150.);
+
+        let mut grouped = Grouped::new();
+        grouped.add(0, Focus::new(a));
+        grouped.add(2, Focus::with_amp(b, 0.5));
+        grouped.build(&geometry).unwrap();
+
+        let mut focus_a = Focus::new(a);
+        focus_a.build(&geometry).unwrap();
+        let mut focus_b = Focus::with_amp(b, 0.5);
+        focus_b.build(&geometry).unwrap();
+
+        let drives = &grouped.combined_drives().drives;
+        drives
+            .chunks(NUM_TRANS_IN_UNIT)
+            .zip([Some(&focus_a), None, Some(&focus_b)])
+            .enumerate()
+            .for_each(|(dev, (drives, expected))| match expected {
+                Some(expected) => drives
+                    .iter()
+                    .zip(&expected.drives()[dev * NUM_TRANS_IN_UNIT..])
+                    .for_each(|(d, e)| {
+                        assert_eq!(d.phase, e.phase);
+                        assert_eq!(d.amp, e.amp);
+                    }),
+                None => drives.iter().for_each(|d| assert_eq!(d.amp, 0.)),
+            });
+    }
+
+    #[test]
+    fn out_of_range() {
+        let geometry = geometry();
+        let mut grouped = Grouped::new();
+        grouped.add(3, Focus::new(geometry.center()));
+        assert!(matches!(
+            grouped
+                .build(&geometry)
+                .unwrap_err()
+                .downcast_ref::<AUTDError>(),
+            Some(AUTDError::GroupedOutOfRange(3, 3))
+        ));
+    }
+}
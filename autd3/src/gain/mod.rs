@@ -12,15 +12,19 @@
  */
 
 pub mod bessel;
+pub mod dither;
 pub mod focus;
 pub mod grouped;
 pub mod null;
 pub mod plane;
+pub mod staggered;
 pub mod trans_test;
 
 pub use bessel::Bessel;
-pub use focus::Focus;
+pub use dither::Dither;
+pub use focus::{Focus, FocusGrid};
 pub use grouped::Grouped;
 pub use null::Null;
 pub use plane::Plane;
+pub use staggered::Staggered;
 pub use trans_test::TransducerTest;
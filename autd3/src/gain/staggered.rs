@@ -0,0 +1,248 @@
+/*
+ * File: staggered.rs
+ * Project: gain
+ * Created Date: 15/10/2022
+ * Author: Shun Suzuki
+ * -----
+ * Last Modified: 15/10/2022
+ * Modified By: Shun Suzuki (suzuki@hapis.k.u-tokyo.ac.jp)
+ * -----
+ * Copyright (c) 2022 Shun Suzuki. All rights reserved.
+ *
+ */
+
+use std::marker::PhantomData;
+
+use anyhow::Result;
+use autd3_core::{
+    gain::Gain,
+    geometry::{Geometry, Transducer, TransducerKind},
+    interface::{DatagramBody, Empty, Filled, Sendable},
+    Drive, TxDatagram, NUM_TRANS_IN_UNIT,
+};
+
+/// Send a gain to a subset of devices at a time, round-robin over multiple frames
+///
+/// Each frame changes the drives of only `devices_per_frame` devices, which limits the processing of new drives per frame.
+/// Note that this does not reduce the amount of data on the bus: the header, including the flag to write the bodies, is shared by all devices, so that every frame carries the bodies of all devices, and the devices not updated in the frame are sent the drives they already have.
+/// Hence, the update of the whole array takes `ceil(num_devices / devices_per_frame)` times as many frames as usual.
+/// During the update, the emitted field is a mixture of the old and new patterns, so that, e.g., a moving focus may look split or blurred.
+///
+/// The drives currently held by the devices must be given by [with_previous](Self::with_previous); otherwise, they are unknown, and all devices are updated in a single round.
+pub struct Staggered<G: Gain<T>, T: Transducer> {
+    gain: G,
+    devices_per_frame: usize,
+    previous: Option<Vec<Drive>>,
+    drives: Vec<Drive>,
+    group: usize,
+    num_groups: Option<usize>,
+    phase_sent: bool,
+    duty_sent: bool,
+    _t: PhantomData<T>,
+}
+
+impl<G: Gain<T>, T: Transducer> Staggered<G, T> {
+    /// constructor
+    ///
+    /// # Arguments
+    ///
+    /// * `gain` - gain to be sent
+    /// * `devices_per_frame` - number of devices updated in a frame
+    ///
+    pub fn new(gain: G, devices_per_frame: usize) -> Self {
+        Self {
+            gain,
+            devices_per_frame: devices_per_frame.max(1),
+            previous: None,
+            drives: vec![],
+            group: 0,
+            num_groups: None,
+            phase_sent: false,
+            duty_sent: false,
+            _t: PhantomData,
+        }
+    }
+
+    /// Set the drives currently held by the devices, e.g., the drives of the previously sent gain
+    pub fn with_previous(self, drives: Vec<Drive>) -> Self {
+        Self {
+            previous: Some(drives),
+            ..self
+        }
+    }
+}
+
+impl<G: Gain<T>, T: Transducer> DatagramBody<T> for Staggered<G, T> {
+    fn init(&mut self) -> Result<()> {
+        self.drives.clear();
+        self.group = 0;
+        self.num_groups = None;
+        self.phase_sent = false;
+        self.duty_sent = false;
+        Ok(())
+    }
+
+    fn pack(&mut self, geometry: &Geometry<T>, tx: &mut TxDatagram) -> Result<()> {
+        T::pack_head(tx);
+        if DatagramBody::<T>::is_finished(self) {
+            return Ok(());
+        }
+
+        if self.num_groups.is_none() {
+            self.gain.build(geometry)?;
+            let (drives, num_groups) = match &self.previous {
+                Some(previous) => (
+                    previous.clone(),
+                    geometry.num_devices().div_ceil(self.devices_per_frame),
+                ),
+                None => (self.gain.drives().to_vec(), 1),
+            };
+            self.drives = drives;
+            self.num_groups = Some(num_groups);
+        }
+
+        if !self.phase_sent && !self.duty_sent {
+            let (start, end) = if self.num_groups == Some(1) {
+                (0, self.drives.len())
+            } else {
+                (
+                    self.group * self.devices_per_frame * NUM_TRANS_IN_UNIT,
+                    ((self.group + 1) * self.devices_per_frame * NUM_TRANS_IN_UNIT)
+                        .min(self.drives.len()),
+                )
+            };
+            self.drives[start..end].copy_from_slice(&self.gain.drives()[start..end]);
+        }
+
+        if geometry.has_device_phase_offset() {
+            let mut drives = self.drives.clone();
+            geometry.apply_device_phase_offsets(&mut drives);
+            T::pack_body(&mut self.phase_sent, &mut self.duty_sent, &drives, tx)?;
+        } else {
+            T::pack_body(&mut self.phase_sent, &mut self.duty_sent, &self.drives, tx)?;
+        }
+
+        if self.phase_sent && self.duty_sent {
+            self.group += 1;
+            self.phase_sent = false;
+            self.duty_sent = false;
+        }
+
+        Ok(())
+    }
+
+    fn is_finished(&self) -> bool {
+        matches!(self.num_groups, Some(n) if self.group >= n)
+    }
+
+    fn total_power(&self) -> Option<f64> {
+        Some(self.drives.iter().map(|d| d.amp * d.amp).sum())
+    }
+
+    fn target_kind(&self) -> Option<TransducerKind> {
+        Some(self.gain.transducer_kind())
+    }
+}
+
+impl<G: Gain<T>, T: Transducer> Sendable<T> for Staggered<G, T> {
+    type H = Empty;
+    type B = Filled;
+
+    fn init(&mut self) -> Result<()> {
+        DatagramBody::<T>::init(self)
+    }
+
+    fn pack(&mut self, _msg_id: u8, geometry: &Geometry<T>, tx: &mut TxDatagram) -> Result<()> {
+        DatagramBody::<T>::pack(self, geometry, tx)
+    }
+
+    fn is_finished(&self) -> bool {
+        DatagramBody::<T>::is_finished(self)
+    }
+
+    fn total_power(&self) -> Option<f64> {
+        DatagramBody::<T>::total_power(self)
+    }
+
+    fn target_kind(&self) -> Option<TransducerKind> {
+        DatagramBody::<T>::target_kind(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use autd3_core::geometry::{GeometryBuilder, LegacyTransducer, Vector3};
+
+    use super::*;
+    use crate::gain::Focus;
+
+    const NUM_DEVICES: usize = 3;
+
+    fn geometry() -> Geometry<LegacyTransducer> {
+        let mut geometry = GeometryBuilder::new().legacy_mode().build();
+        (0..NUM_DEVICES).for_each(|i| {
+            geometry.add_device(Vector3::new(192. * i as f64, 0., 0.), Vector3::zeros());
+        });
+        geometry
+    }
+
+    fn send_frames(
+        gain: &mut Staggered<Focus<LegacyTransducer>, LegacyTransducer>,
+        geometry: &Geometry<LegacyTransducer>,
+    ) -> Vec<Vec<[u16; NUM_TRANS_IN_UNIT]>> {
+        DatagramBody::<LegacyTransducer>::init(gain).unwrap();
+        let mut frames = vec![];
+        while !DatagramBody::<LegacyTransducer>::is_finished(gain) {
+            let mut tx = TxDatagram::new(NUM_DEVICES);
+            DatagramBody::<LegacyTransducer>::pack(gain, geometry, &mut tx).unwrap();
+            frames.push(tx.body().iter().map(|b| b.data).collect());
+        }
+        frames
+    }
+
+    #[test]
+    fn every_device_updated_exactly_once() {
+        let geometry = geometry();
+        let center = geometry.center() + Vector3::new(0., 0., 150.);
+        let previous = vec![
+            Drive {
+                phase: 0.0,
+                amp: 0.0,
+                cycle: 4096,
+            };
+            geometry.num_transducers()
+        ];
+
+        let mut gain = Staggered::new(Focus::new(center), 1).with_previous(previous);
+        let frames = send_frames(&mut gain, &geometry);
+        assert_eq!(frames.len(), NUM_DEVICES);
+
+        let mut current = vec![[0u16; NUM_TRANS_IN_UNIT]; NUM_DEVICES];
+        let mut updates = vec![vec![]; NUM_DEVICES];
+        frames.iter().enumerate().for_each(|(frame, bodies)| {
+            bodies.iter().enumerate().for_each(|(dev, body)| {
+                if *body != current[dev] {
+                    updates[dev].push(frame);
+                    current[dev] = *body;
+                }
+            });
+        });
+        updates
+            .iter()
+            .enumerate()
+            .for_each(|(dev, frames)| assert_eq!(frames, &vec![dev]));
+    }
+
+    #[test]
+    fn without_previous_update_all_at_once() {
+        let geometry = geometry();
+        let center = geometry.center() + Vector3::new(0., 0., 150.);
+
+        let mut gain = Staggered::new(Focus::new(center), 1);
+        let frames = send_frames(&mut gain, &geometry);
+        assert_eq!(frames.len(), 1);
+        frames[0]
+            .iter()
+            .for_each(|body| assert!(body.iter().all(|&d| d & 0xFF00 != 0)));
+    }
+}
@@ -11,11 +11,16 @@
  *
  */
 
+pub mod calibration;
 mod controller;
 mod error;
 pub mod gain;
+pub mod link;
 pub mod modulation;
 pub mod prelude;
+mod shared_controller;
 
 pub use autd3_core;
 pub use controller::Controller;
+pub use error::AUTDError;
+pub use shared_controller::{SendHandle, SharedController};
@@ -0,0 +1,155 @@
+/*
+ * File: bundle.rs
+ * Project: link
+ * Created Date: 15/10/2022
+ * Author: Shun Suzuki
+ * -----
+ * Last Modified: 15/10/2022
+ * Modified By: Shun Suzuki (suzuki@hapis.k.u-tokyo.ac.jp)
+ * -----
+ * Copyright (c) 2022 Shun Suzuki. All rights reserved.
+ *
+ */
+
+use anyhow::Result;
+
+use autd3_core::{
+    geometry::{Geometry, Transducer},
+    link::Link,
+    RxDatagram, TxDatagram,
+};
+
+use crate::error::AUTDError;
+
+/// Link bundling multiple links, each of which is connected to a part of the devices, e.g., arrays on separate NICs
+///
+/// The devices are assigned to the links in the order of [with_link](Self::with_link), and each link is given the datagram and the geometry of its own devices.
+/// Note that the datagram is sent to the links one after another, so that the devices on different links are not updated at exactly the same time.
+pub struct BundleLink<L: Link> {
+    links: Vec<(L, usize)>,
+    tx: Vec<TxDatagram>,
+    rx: Vec<RxDatagram>,
+}
+
+impl<L: Link> BundleLink<L> {
+    pub fn new() -> Self {
+        Self {
+            links: vec![],
+            tx: vec![],
+            rx: vec![],
+        }
+    }
+
+    /// Add a link connected to the next `num_devices` devices
+    pub fn with_link(mut self, link: L, num_devices: usize) -> Self {
+        self.links.push((link, num_devices));
+        self
+    }
+
+    pub fn links(&self) -> impl Iterator<Item = &L> {
+        self.links.iter().map(|(link, _)| link)
+    }
+
+    pub fn links_mut(&mut self) -> impl Iterator<Item = &mut L> {
+        self.links.iter_mut().map(|(link, _)| link)
+    }
+
+    fn for_each_link<F: FnMut(&mut L, usize) -> Result<bool>>(&mut self, mut f: F) -> Result<bool> {
+        let mut res = Ok(true);
+        for (i, (link, _)) in self.links.iter_mut().enumerate() {
+            match f(link, i) {
+                Ok(r) => res = res.map(|a| a && r),
+                Err(e) => {
+                    if res.is_ok() {
+                        res = Err(e);
+                    }
+                }
+            }
+        }
+        res
+    }
+}
+
+impl<L: Link> Default for BundleLink<L> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<L: Link> Link for BundleLink<L> {
+    fn open<T: Transducer>(&mut self, geometry: &Geometry<T>) -> Result<()> {
+        let total = self.links.iter().map(|(_, n)| n).sum();
+        if total != geometry.num_devices() {
+            return Err(AUTDError::BundleDeviceMismatch(total, geometry.num_devices()).into());
+        }
+
+        let mut start = 0;
+        for i in 0..self.links.len() {
+            let num_devices = self.links[i].1;
+            let sub_geometry = geometry.sub_geometry(start..start + num_devices);
+            if let Err(e) = self.links[i].0.open(&sub_geometry) {
+                self.links[..i].iter_mut().for_each(|(link, _)| {
+                    let _ = link.close();
+                });
+                return Err(e);
+            }
+            start += num_devices;
+        }
+
+        self.tx = self
+            .links
+            .iter()
+            .map(|&(_, n)| TxDatagram::new(n))
+            .collect();
+        self.rx = self
+            .links
+            .iter()
+            .map(|&(_, n)| RxDatagram::new(n))
+            .collect();
+
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<()> {
+        self.for_each_link(|link, _| link.close().map(|_| true))
+            .map(|_| ())
+    }
+
+    fn send(&mut self, tx: &TxDatagram) -> Result<bool> {
+        let mut start = 0;
+        for ((_, n), sub) in self.links.iter().zip(self.tx.iter_mut()) {
+            *sub.header_mut() = *tx.header();
+            sub.body_mut().copy_from_slice(&tx.body()[start..start + n]);
+            sub.num_bodies = tx.num_bodies.saturating_sub(start).min(*n);
+            start += n;
+        }
+
+        let txs = std::mem::take(&mut self.tx);
+        let res = self.for_each_link(|link, i| link.send(&txs[i]));
+        self.tx = txs;
+        res
+    }
+
+    fn receive(&mut self, rx: &mut RxDatagram) -> Result<bool> {
+        let mut rxs = std::mem::take(&mut self.rx);
+        let res = self.for_each_link(|link, i| link.receive(&mut rxs[i]));
+
+        let mut start = 0;
+        for sub in &rxs {
+            let n = sub.messages().len();
+            rx.messages_mut()[start..start + n].copy_from_slice(sub.messages());
+            start += n;
+        }
+        self.rx = rxs;
+
+        res
+    }
+
+    fn is_open(&self) -> bool {
+        !self.links.is_empty() && self.links.iter().all(|(link, _)| link.is_open())
+    }
+
+    fn supports_read(&self) -> bool {
+        self.links.iter().all(|(link, _)| link.supports_read())
+    }
+}
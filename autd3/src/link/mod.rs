@@ -0,0 +1,20 @@
+/*
+ * File: mod.rs
+ * Project: link
+ * Created Date: 15/10/2022
+ * Author: Shun Suzuki
+ * -----
+ * Last Modified: 15/10/2022
+ * Modified By: Shun Suzuki (suzuki@hapis.k.u-tokyo.ac.jp)
+ * -----
+ * Copyright (c) 2022 Shun Suzuki. All rights reserved.
+ *
+ */
+
+pub mod bundle;
+pub mod null;
+pub mod record;
+
+pub use bundle::BundleLink;
+pub use null::NullLink;
+pub use record::{replay, RecordLink};
@@ -0,0 +1,121 @@
+/*
+ * File: null.rs
+ * Project: link
+ * Created Date: 15/10/2022
+ * Author: Shun Suzuki
+ * -----
+ * Last Modified: 15/10/2022
+ * Modified By: Shun Suzuki (suzuki@hapis.k.u-tokyo.ac.jp)
+ * -----
+ * Copyright (c) 2022 Shun Suzuki. All rights reserved.
+ *
+ */
+
+use anyhow::Result;
+
+use autd3_core::{
+    geometry::{Geometry, Transducer},
+    link::Link,
+    RxDatagram, RxMessage, TxDatagram,
+};
+
+/// Link which requires no hardware, e.g., for testing
+///
+/// All sent data are accepted, and the last one can be inspected by [last_tx](Self::last_tx).
+/// By default, the received data report that the last message has been processed by all devices.
+pub struct NullLink {
+    is_open: bool,
+    last_tx: Option<TxDatagram>,
+    rx: Option<Vec<RxMessage>>,
+    logs: bool,
+}
+
+impl NullLink {
+    pub fn new() -> Self {
+        Self {
+            is_open: false,
+            last_tx: None,
+            rx: None,
+            logs: false,
+        }
+    }
+
+    /// Return `messages` on receiving instead of the default ones
+    pub fn with_rx(self, messages: Vec<RxMessage>) -> Self {
+        Self {
+            rx: Some(messages),
+            ..self
+        }
+    }
+
+    /// Log each sent data at debug level
+    pub fn with_log(self, logs: bool) -> Self {
+        Self { logs, ..self }
+    }
+
+    /// Set the messages returned on receiving. If `None`, the default ones are returned.
+    pub fn set_rx(&mut self, messages: Option<Vec<RxMessage>>) {
+        self.rx = messages;
+    }
+
+    /// The last sent data
+    pub fn last_tx(&self) -> Option<&TxDatagram> {
+        self.last_tx.as_ref()
+    }
+}
+
+impl Link for NullLink {
+    fn open<T: Transducer>(&mut self, _geometry: &Geometry<T>) -> Result<()> {
+        self.is_open = true;
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<()> {
+        self.is_open = false;
+        Ok(())
+    }
+
+    fn send(&mut self, tx: &TxDatagram) -> Result<bool> {
+        if self.logs {
+            let header = tx.header();
+            log::debug!(
+                "Send data: msg_id = {}, CPU Flag = {:?}, FPGA Flag = {:?}, size = {}, num_bodies = {}",
+                header.msg_id,
+                header.cpu_flag,
+                header.fpga_flag,
+                header.size,
+                tx.num_bodies
+            );
+        }
+        self.last_tx = Some(tx.clone());
+        Ok(true)
+    }
+
+    fn receive(&mut self, rx: &mut RxDatagram) -> Result<bool> {
+        match &self.rx {
+            Some(messages) => rx
+                .messages_mut()
+                .iter_mut()
+                .zip(messages.iter())
+                .for_each(|(dst, src)| *dst = *src),
+            None => {
+                let msg_id = self.last_tx.as_ref().map_or(0, |tx| tx.header().msg_id);
+                rx.messages_mut().iter_mut().for_each(|msg| {
+                    msg.ack = 0;
+                    msg.msg_id = msg_id;
+                });
+            }
+        }
+        Ok(true)
+    }
+
+    fn is_open(&self) -> bool {
+        self.is_open
+    }
+}
+
+impl Default for NullLink {
+    fn default() -> Self {
+        Self::new()
+    }
+}
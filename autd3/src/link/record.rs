@@ -0,0 +1,270 @@
+/*
+ * File: record.rs
+ * Project: link
+ * Created Date: 15/10/2022
+ * Author: Shun Suzuki
+ * -----
+ * Last Modified: 15/10/2022
+ * Modified By: Shun Suzuki (suzuki@hapis.k.u-tokyo.ac.jp)
+ * -----
+ * Copyright (c) 2022 Shun Suzuki. All rights reserved.
+ *
+ */
+
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, ErrorKind, Read, Write},
+    path::Path,
+    time::Instant,
+};
+
+use anyhow::Result;
+
+use autd3_core::{
+    geometry::{Geometry, Transducer},
+    link::Link,
+    Body, GlobalHeader, RxDatagram, TxDatagram,
+};
+
+use crate::error::AUTDError;
+
+const RECORD_TX: u8 = 0;
+const RECORD_RX: u8 = 1;
+
+/// Link which records all sent and received data to a file
+///
+/// Each record consists of the kind (0: sent, 1: received), the elapsed time in nanoseconds as u64, the length of the data as u32, and the data.
+/// The data of a sent record is the number of bodies as u32 followed by the whole buffer of [TxDatagram].
+/// The data of a received record is the pairs of ack and msg_id of all devices.
+/// All values are in little endian. The recorded file can be fed into another link with [replay].
+pub struct RecordLink<L: Link> {
+    link: L,
+    writer: BufWriter<File>,
+    start: Instant,
+}
+
+impl<L: Link> RecordLink<L> {
+    pub fn new<P: AsRef<Path>>(link: L, path: P) -> Result<Self> {
+        Ok(Self {
+            link,
+            writer: BufWriter::new(File::create(path)?),
+            start: Instant::now(),
+        })
+    }
+
+    fn write_record(&mut self, kind: u8, data: &[u8]) -> Result<()> {
+        self.writer.write_all(&[kind])?;
+        self.writer
+            .write_all(&(self.start.elapsed().as_nanos() as u64).to_le_bytes())?;
+        self.writer.write_all(&(data.len() as u32).to_le_bytes())?;
+        self.writer.write_all(data)?;
+        Ok(())
+    }
+}
+
+impl<L: Link> Link for RecordLink<L> {
+    fn open<T: Transducer>(&mut self, geometry: &Geometry<T>) -> Result<()> {
+        self.link.open(geometry)
+    }
+
+    fn close(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        self.link.close()
+    }
+
+    fn send(&mut self, tx: &TxDatagram) -> Result<bool> {
+        let data = (tx.num_bodies as u32)
+            .to_le_bytes()
+            .iter()
+            .chain(tx.data().iter())
+            .copied()
+            .collect::<Vec<_>>();
+        self.write_record(RECORD_TX, &data)?;
+        self.link.send(tx)
+    }
+
+    fn receive(&mut self, rx: &mut RxDatagram) -> Result<bool> {
+        let res = self.link.receive(rx)?;
+        if res {
+            let data = rx
+                .messages()
+                .iter()
+                .flat_map(|msg| [msg.ack, msg.msg_id])
+                .collect::<Vec<_>>();
+            self.write_record(RECORD_RX, &data)?;
+        }
+        Ok(res)
+    }
+
+    fn is_open(&self) -> bool {
+        self.link.is_open()
+    }
+
+    fn supports_read(&self) -> bool {
+        self.link.supports_read()
+    }
+}
+
+/// Send the data recorded by [RecordLink] to `link` in order. The received data in the record are ignored.
+pub fn replay<P: AsRef<Path>, L: Link>(path: P, link: &mut L) -> Result<()> {
+    let mut reader = BufReader::new(File::open(path)?);
+    loop {
+        let mut kind = [0u8; 1];
+        match reader.read_exact(&mut kind) {
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(()),
+            r => r?,
+        }
+        let mut time = [0u8; 8];
+        reader.read_exact(&mut time)?;
+        let mut len = [0u8; 4];
+        reader.read_exact(&mut len)?;
+        let mut data = vec![0u8; u32::from_le_bytes(len) as usize];
+        reader.read_exact(&mut data)?;
+
+        if kind[0] != RECORD_TX {
+            continue;
+        }
+        let header_len = std::mem::size_of::<u32>() + std::mem::size_of::<GlobalHeader>();
+        if data.len() < header_len {
+            return Err(AUTDError::InvalidRecordLength(data.len()).into());
+        }
+        let size = (data.len() - header_len) / std::mem::size_of::<Body>();
+        if header_len + size * std::mem::size_of::<Body>() != data.len() {
+            return Err(AUTDError::InvalidRecordLength(data.len()).into());
+        }
+        let (num_bodies, data) = data.split_at(std::mem::size_of::<u32>());
+        let mut tx = TxDatagram::new(size);
+        tx.data_mut().copy_from_slice(data);
+        tx.num_bodies = u32::from_le_bytes(num_bodies.try_into()?) as usize;
+        if tx.num_bodies > size {
+            return Err(AUTDError::InvalidRecordLength(data.len()).into());
+        }
+        link.send(&tx)?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use autd3_core::geometry::{GeometryBuilder, Vector3};
+    use autd3_firmware_emulator::Emulator;
+
+    use super::*;
+    use crate::{gain::Focus, modulation::Sine, Controller};
+
+    #[derive(Clone)]
+    struct EmulatorLink {
+        emulator: Arc<Mutex<Emulator>>,
+        is_open: bool,
+    }
+
+    impl EmulatorLink {
+        fn new(num_devices: usize) -> Self {
+            let mut emulator = Emulator::new();
+            emulator.init(num_devices);
+            Self {
+                emulator: Arc::new(Mutex::new(emulator)),
+                is_open: false,
+            }
+        }
+    }
+
+    impl Link for EmulatorLink {
+        fn open<T: Transducer>(&mut self, _geometry: &Geometry<T>) -> Result<()> {
+            self.is_open = true;
+            Ok(())
+        }
+
+        fn close(&mut self) -> Result<()> {
+            self.is_open = false;
+            Ok(())
+        }
+
+        fn send(&mut self, tx: &TxDatagram) -> Result<bool> {
+            self.emulator.lock().unwrap().send(tx);
+            Ok(true)
+        }
+
+        fn receive(&mut self, rx: &mut RxDatagram) -> Result<bool> {
+            self.emulator.lock().unwrap().read(rx);
+            Ok(true)
+        }
+
+        fn is_open(&self) -> bool {
+            self.is_open
+        }
+    }
+
+    fn assert_same_state(a: &Emulator, b: &Emulator, num_devices: usize) {
+        (0..num_devices).for_each(|i| {
+            let (fa, fb) = (a.fpga(i), b.fpga(i));
+            assert_eq!(fa.modulation(), fb.modulation());
+            assert_eq!(fa.modulation_cycle(), fb.modulation_cycle());
+            assert_eq!(fa.silencer_step(), fb.silencer_step());
+            assert_eq!(fa.cycles(), fb.cycles());
+            fa.drives()
+                .iter()
+                .zip(fb.drives().iter())
+                .for_each(|((da, pa), (db, pb))| {
+                    da.iter()
+                        .zip(db.iter())
+                        .for_each(|(x, y)| assert_eq!(x.duty, y.duty));
+                    pa.iter()
+                        .zip(pb.iter())
+                        .for_each(|(x, y)| assert_eq!(x.phase, y.phase));
+                });
+        });
+    }
+
+    #[test]
+    fn replay_into_emulator() {
+        const NUM_DEVICES: usize = 2;
+        let path = std::env::temp_dir().join("autd3_record_replay_test.dat");
+
+        let mut geometry = GeometryBuilder::new().legacy_mode().build();
+        geometry.add_device(Vector3::zeros(), Vector3::zeros());
+        geometry.add_device(Vector3::new(192., 0., 0.), Vector3::zeros());
+        let center = geometry.center() + Vector3::new(0., 0., 150.);
+
+        let recorded = EmulatorLink::new(NUM_DEVICES);
+        let link = RecordLink::new(recorded.clone(), &path).unwrap();
+        let mut autd = Controller::open(geometry, link).unwrap();
+        autd.send(&mut Sine::new(150))
+            .send(&mut Focus::new(center))
+            .unwrap();
+        // close only the link to keep the output of the devices
+        autd.link_mut().close().unwrap();
+        drop(autd);
+
+        let mut replayed = EmulatorLink::new(NUM_DEVICES);
+        replay(&path, &mut replayed).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let recorded = recorded.emulator.lock().unwrap();
+        let replayed = replayed.emulator.lock().unwrap();
+        assert_same_state(&recorded, &replayed, replayed.cpus().len());
+        assert!(recorded.fpga(0).modulation().0.iter().any(|&m| m != 0));
+        assert!(recorded.fpga(0).drives()[0].0.iter().any(|d| d.duty != 0));
+    }
+
+    #[test]
+    fn replay_truncated_record() {
+        let path = std::env::temp_dir().join("autd3_record_truncated_test.dat");
+        {
+            let mut writer = BufWriter::new(File::create(&path).unwrap());
+            writer.write_all(&[RECORD_TX]).unwrap();
+            writer.write_all(&0u64.to_le_bytes()).unwrap();
+            writer.write_all(&2u32.to_le_bytes()).unwrap();
+            writer.write_all(&[0, 0]).unwrap();
+        }
+
+        let mut link = EmulatorLink::new(1);
+        let res = replay(&path, &mut link);
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            res.unwrap_err().downcast_ref::<AUTDError>(),
+            Some(AUTDError::InvalidRecordLength(2))
+        ));
+    }
+}
@@ -0,0 +1,59 @@
+/*
+ * File: custom.rs
+ * Project: modulation
+ * Created Date: 15/10/2022
+ * Author: Shun Suzuki
+ * -----
+ * Last Modified: 15/10/2022
+ * Modified By: Shun Suzuki (suzuki@hapis.k.u-tokyo.ac.jp)
+ * -----
+ * Copyright (c) 2022 Shun Suzuki. All rights reserved.
+ *
+ */
+
+use anyhow::Result;
+use autd3_core::{
+    modulation::{ModProps, Modulation},
+    FPGAError, MOD_BUF_SIZE_MAX, MOD_SAMPLING_FREQ_DIV_MIN,
+};
+use autd3_traits::Modulation;
+
+use crate::error::AUTDError;
+
+/// Modulation playing back a user-defined buffer of duty ratios
+#[derive(Modulation)]
+pub struct Custom {
+    props: ModProps,
+    buffer: Vec<u8>,
+}
+
+impl Custom {
+    /// constructor.
+    ///
+    /// # Arguments
+    ///
+    /// * `buffer` - Duty ratios of the modulation
+    /// * `freq_div` - Sampling frequency division
+    ///
+    pub fn new(buffer: Vec<u8>, freq_div: u32) -> Result<Self> {
+        if buffer.is_empty() {
+            return Err(AUTDError::EmptyModulationBuffer.into());
+        }
+        if buffer.len() > MOD_BUF_SIZE_MAX {
+            return Err(FPGAError::ModulationOutOfBuffer(buffer.len()).into());
+        }
+        if freq_div < MOD_SAMPLING_FREQ_DIV_MIN {
+            return Err(FPGAError::ModFreqDivOutOfRange(freq_div).into());
+        }
+        let mut props = ModProps::new();
+        props.freq_div = freq_div;
+        Ok(Self { props, buffer })
+    }
+
+    #[allow(clippy::unnecessary_wraps)]
+    fn calc(&mut self) -> Result<()> {
+        self.props.buffer.clone_from(&self.buffer);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,325 @@
+/*
+ * File: expr.rs
+ * Project: modulation
+ * Created Date: 15/10/2022
+ * Author: Shun Suzuki
+ * -----
+ * Last Modified: 15/10/2022
+ * Modified By: Shun Suzuki (suzuki@hapis.k.u-tokyo.ac.jp)
+ * -----
+ * Copyright (c) 2022 Shun Suzuki. All rights reserved.
+ *
+ */
+
+use std::{f64::consts::PI, iter::Peekable, str::Chars};
+
+use anyhow::Result;
+use autd3_core::modulation::{ModProps, Modulation};
+use autd3_traits::Modulation;
+
+use crate::error::AUTDError;
+
+enum Node {
+    Num(f64),
+    Time,
+    Neg(Box<Node>),
+    Add(Box<Node>, Box<Node>),
+    Sub(Box<Node>, Box<Node>),
+    Mul(Box<Node>, Box<Node>),
+    Div(Box<Node>, Box<Node>),
+    Pow(Box<Node>, Box<Node>),
+    Func(fn(f64) -> f64, Box<Node>),
+}
+
+impl Node {
+    fn eval(&self, t: f64) -> f64 {
+        match self {
+            Node::Num(v) => *v,
+            Node::Time => t,
+            Node::Neg(a) => -a.eval(t),
+            Node::Add(a, b) => a.eval(t) + b.eval(t),
+            Node::Sub(a, b) => a.eval(t) - b.eval(t),
+            Node::Mul(a, b) => a.eval(t) * b.eval(t),
+            Node::Div(a, b) => a.eval(t) / b.eval(t),
+            Node::Pow(a, b) => a.eval(t).powf(b.eval(t)),
+            Node::Func(f, a) => f(a.eval(t)),
+        }
+    }
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn parse(src: &'a str) -> Result<Node, AUTDError> {
+        let mut parser = Self {
+            chars: src.chars().peekable(),
+            pos: 0,
+        };
+        let node = parser.expr()?;
+        parser.skip_whitespace();
+        if let Some(c) = parser.chars.peek().copied() {
+            return Err(parser.error(&format!("unexpected '{}'", c)));
+        }
+        Ok(node)
+    }
+
+    fn error(&self, msg: &str) -> AUTDError {
+        AUTDError::ExprParse(self.pos, msg.to_owned())
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        self.pos += 1;
+        self.chars.next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.peek().is_some_and(|c| c.is_whitespace()) {
+            self.bump();
+        }
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.chars.peek() == Some(&c) {
+            self.bump();
+            true
+        } else {
+            false
+        }
+    }
+
+    // expr := term (('+' | '-') term)*
+    fn expr(&mut self) -> Result<Node, AUTDError> {
+        let mut lhs = self.term()?;
+        loop {
+            if self.eat('+') {
+                lhs = Node::Add(Box::new(lhs), Box::new(self.term()?));
+            } else if self.eat('-') {
+                lhs = Node::Sub(Box::new(lhs), Box::new(self.term()?));
+            } else {
+                return Ok(lhs);
+            }
+        }
+    }
+
+    // term := unary (('*' | '/') unary)*
+    fn term(&mut self) -> Result<Node, AUTDError> {
+        let mut lhs = self.unary()?;
+        loop {
+            if self.eat('*') {
+                lhs = Node::Mul(Box::new(lhs), Box::new(self.unary()?));
+            } else if self.eat('/') {
+                lhs = Node::Div(Box::new(lhs), Box::new(self.unary()?));
+            } else {
+                return Ok(lhs);
+            }
+        }
+    }
+
+    // unary := '-' unary | power
+    fn unary(&mut self) -> Result<Node, AUTDError> {
+        if self.eat('-') {
+            return Ok(Node::Neg(Box::new(self.unary()?)));
+        }
+        self.power()
+    }
+
+    // power := primary ('^' unary)?
+    fn power(&mut self) -> Result<Node, AUTDError> {
+        let base = self.primary()?;
+        if self.eat('^') {
+            return Ok(Node::Pow(Box::new(base), Box::new(self.unary()?)));
+        }
+        Ok(base)
+    }
+
+    // primary := number | ident | ident '(' expr ')' | '(' expr ')'
+    fn primary(&mut self) -> Result<Node, AUTDError> {
+        self.skip_whitespace();
+        match self.chars.peek().copied() {
+            Some('(') => {
+                self.bump();
+                let node = self.expr()?;
+                if !self.eat(')') {
+                    return Err(self.error("expected ')'"));
+                }
+                Ok(node)
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                let mut s = String::new();
+                while let Some(&c) = self.chars.peek() {
+                    if !(c.is_ascii_digit() || c == '.') {
+                        break;
+                    }
+                    s.push(c);
+                    self.bump();
+                }
+                s.parse()
+                    .map(Node::Num)
+                    .map_err(|_| self.error(&format!("invalid number '{}'", s)))
+            }
+            Some(c) if c.is_ascii_alphabetic() => {
+                let mut s = String::new();
+                while let Some(&c) = self.chars.peek() {
+                    if !c.is_ascii_alphanumeric() {
+                        break;
+                    }
+                    s.push(c);
+                    self.bump();
+                }
+                let f: fn(f64) -> f64 = match s.as_str() {
+                    "t" => return Ok(Node::Time),
+                    "pi" => return Ok(Node::Num(PI)),
+                    "sin" => f64::sin,
+                    "cos" => f64::cos,
+                    "abs" => f64::abs,
+                    _ => return Err(self.error(&format!("unknown identifier '{}'", s))),
+                };
+                if !self.eat('(') {
+                    return Err(self.error(&format!("expected '(' after '{}'", s)));
+                }
+                let arg = self.expr()?;
+                if !self.eat(')') {
+                    return Err(self.error("expected ')'"));
+                }
+                Ok(Node::Func(f, Box::new(arg)))
+            }
+            Some(c) => Err(self.error(&format!("unexpected '{}'", c))),
+            None => Err(self.error("unexpected end of expression")),
+        }
+    }
+}
+
+/// Modulation sampled from a mathematical expression of time
+///
+/// The expression is a function of `t`, the time in seconds, and its value is taken as the normalized amplitude.
+/// It supports numbers, `pi`, `+`, `-`, `*`, `/`, `^`, parentheses, and `sin`, `cos` and `abs`.
+/// The value is clamped to [0, 1].
+#[derive(Modulation)]
+pub struct Expr {
+    props: ModProps,
+    node: Node,
+    duration: f64,
+}
+
+impl Expr {
+    /// constructor.
+    ///
+    /// # Arguments
+    ///
+    /// * `expr` - Expression, e.g., `0.5 + 0.5*sin(2*pi*200*t)`
+    ///
+    pub fn new(expr: &str) -> Result<Self> {
+        Ok(Self {
+            props: ModProps::new(),
+            node: Parser::parse(expr)?,
+            duration: 1.0,
+        })
+    }
+
+    /// Set the length of the modulation buffer in seconds. Default is 1 second.
+    ///
+    /// The expression should be periodic with this duration, since the buffer is played repeatedly.
+    pub fn with_duration(self, duration: f64) -> Self {
+        Self { duration, ..self }
+    }
+
+    #[allow(clippy::unnecessary_wraps)]
+    fn calc(&mut self) -> Result<()> {
+        let sf = self.sampling_freq();
+
+        let n = ((self.duration * sf).round() as usize).max(1);
+
+        self.props.buffer.resize(n, 0);
+
+        let node = &self.node;
+        self.props.buffer.iter_mut().enumerate().for_each(|(i, m)| {
+            let amp = node.eval(i as f64 / sf);
+            let amp = if amp.is_nan() {
+                0.0
+            } else {
+                amp.clamp(0.0, 1.0)
+            };
+            let duty = amp.asin() * 2.0 / PI * 255.0;
+            *m = duty as u8
+        });
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(src: &str, t: f64) -> f64 {
+        Parser::parse(src).unwrap().eval(t)
+    }
+
+    fn parse_err(src: &str) -> (usize, String) {
+        match Parser::parse(src) {
+            Err(AUTDError::ExprParse(pos, msg)) => (pos, msg),
+            _ => panic!("'{}' must not be parsed", src),
+        }
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(eval("1 + 2 * 3", 0.), 7.);
+        assert_eq!(eval("(1 + 2) * 3", 0.), 9.);
+        assert_eq!(eval("10 / 4 / 5", 0.), 0.5);
+        assert_eq!(eval("8 - 2 - 1", 0.), 5.);
+        assert_eq!(eval("2 ^ 3 ^ 2", 0.), 512.);
+        assert_eq!(eval("-2 ^ 2", 0.), -4.);
+        assert_eq!(eval("2 ^ -1", 0.), 0.5);
+        assert_eq!(eval("--1", 0.), 1.);
+    }
+
+    #[test]
+    fn functions_and_time() {
+        assert!((eval("sin(pi / 2)", 0.) - 1.).abs() < 1e-12);
+        assert!((eval("cos(pi)", 0.) + 1.).abs() < 1e-12);
+        assert_eq!(eval("abs(-t)", 3.), 3.);
+        assert_eq!(eval("0.5 * t + .25", 2.), 1.25);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(parse_err("1 2"), (2, "unexpected '2'".to_owned()));
+        assert_eq!(
+            parse_err("1 +"),
+            (3, "unexpected end of expression".to_owned())
+        );
+        assert_eq!(parse_err("(1"), (2, "expected ')'".to_owned()));
+        assert_eq!(
+            parse_err("tan(t)"),
+            (3, "unknown identifier 'tan'".to_owned())
+        );
+        assert_eq!(
+            parse_err("sin t"),
+            (4, "expected '(' after 'sin'".to_owned())
+        );
+        assert_eq!(parse_err("1..2"), (4, "invalid number '1..2'".to_owned()));
+    }
+
+    #[test]
+    fn buffer() {
+        let mut m = Expr::new("0.5 + 0.5 * sin(2 * pi * 200 * t)")
+            .unwrap()
+            .with_duration(0.005);
+        m.build().unwrap();
+        let n = (0.005 * m.sampling_freq()).round() as usize;
+        assert_eq!(m.buffer().len(), n);
+        assert_eq!(m.buffer()[0], 85);
+        assert_eq!(m.buffer()[n / 4], 255);
+        assert_eq!(m.buffer()[3 * n / 4], 0);
+
+        let mut m = Expr::new("2 - t * 1000").unwrap().with_duration(0.005);
+        m.build().unwrap();
+        assert_eq!(m.buffer()[0], 255);
+        assert_eq!(*m.buffer().last().unwrap(), 0);
+    }
+}
@@ -0,0 +1,150 @@
+/*
+ * File: fourier.rs
+ * Project: modulation
+ * Created Date: 15/10/2022
+ * Author: Shun Suzuki
+ * -----
+ * Last Modified: 15/10/2022
+ * Modified By: Shun Suzuki (suzuki@hapis.k.u-tokyo.ac.jp)
+ * -----
+ * Copyright (c) 2022 Shun Suzuki. All rights reserved.
+ *
+ */
+
+use std::f64::consts::PI;
+
+use anyhow::Result;
+use autd3_core::modulation::{ModProps, Modulation};
+use autd3_traits::Modulation;
+
+use num::integer::lcm;
+
+use super::sine::sampling_period;
+
+/// Modulation of the sum of sine waves in ultrasound amplitude, e.g., for complex tactile textures
+///
+/// Each component oscillates around the common offset 0.5 in the same way as [Sine](super::Sine).
+/// If the sum would exceed [0, 1], the components are scaled down uniformly to fit, and a warning is logged.
+#[derive(Modulation)]
+pub struct Fourier {
+    props: ModProps,
+    components: Vec<(usize, f64, f64)>,
+}
+
+impl Fourier {
+    /// constructor.
+    ///
+    /// # Arguments
+    ///
+    /// * `components` - Frequency, peek to peek amplitude, and phase in radian of each sine wave
+    ///
+    pub fn new(components: Vec<(usize, f64, f64)>) -> Self {
+        Self {
+            props: ModProps::new(),
+            components,
+        }
+    }
+
+    #[allow(clippy::unnecessary_wraps)]
+    fn calc(&mut self) -> Result<()> {
+        let sf = self.sampling_freq() as usize;
+
+        let periods = self
+            .components
+            .iter()
+            .map(|&(freq, _, _)| sampling_period(sf, freq))
+            .collect::<Vec<_>>();
+        let n = periods.iter().fold(1, |acc, &(n, _)| lcm(acc, n));
+
+        let wave = (0..n)
+            .map(|i| {
+                self.components
+                    .iter()
+                    .zip(periods.iter())
+                    .map(|(&(_, amp, phase), &(n, rep))| {
+                        amp / 2.0 * (2.0 * PI * (rep * i % n) as f64 / n as f64 + phase).sin()
+                    })
+                    .sum::<f64>()
+            })
+            .collect::<Vec<_>>();
+
+        let peak = wave.iter().fold(0.0, |acc: f64, x| acc.max(x.abs()));
+        let scale = if peak > 0.5 {
+            log::warn!(
+                "The sum of the components deviates from the offset by {:.3}, which is scaled down to 0.5 to fit in [0, 1]",
+                peak
+            );
+            0.5 / peak
+        } else {
+            1.0
+        };
+
+        self.props.buffer = wave
+            .iter()
+            .map(|x| {
+                let amp = (x * scale + 0.5).clamp(0.0, 1.0);
+                (amp.asin() * 2.0 / PI * 255.0) as u8
+            })
+            .collect();
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modulation::Sine;
+
+    fn amp(d: u8) -> f64 {
+        (d as f64 / 255.0 * PI / 2.0).sin()
+    }
+
+    #[test]
+    fn single_component_same_as_sine() {
+        [(150, 1.0), (200, 0.5), (7, 0.2)]
+            .into_iter()
+            .for_each(|(freq, a)| {
+                let mut m = Fourier::new(vec![(freq, a, 0.0)]);
+                m.build().unwrap();
+                let mut sine = Sine::with_params(freq, a, 0.5);
+                sine.build().unwrap();
+                assert_eq!(m.buffer(), sine.buffer());
+            });
+    }
+
+    #[test]
+    fn sum_of_components() {
+        let mut m = Fourier::new(vec![(100, 0.4, 0.0), (150, 0.2, PI / 2.0)]);
+        m.build().unwrap();
+        let sf = m.sampling_freq();
+        let n = m.buffer().len();
+        // shortest buffer containing whole periods of both, i.e., 1 / 50 s
+        assert_eq!(n, (sf / 50.0) as usize);
+        m.buffer().iter().enumerate().for_each(|(i, &d)| {
+            let t = i as f64 / sf;
+            let expected = 0.5
+                + 0.2 * (2.0 * PI * 100.0 * t).sin()
+                + 0.1 * (2.0 * PI * 150.0 * t + PI / 2.0).sin();
+            assert!((amp(d) - expected).abs() < 0.01);
+        });
+    }
+
+    #[test]
+    fn scaled_down_to_fit() {
+        let components = vec![(100, 1.0, 0.0), (200, 1.0, 0.0)];
+        let mut m = Fourier::new(components);
+        m.build().unwrap();
+
+        // only the peaks reach the limits, i.e., the wave is scaled instead of clipped
+        let (min, max) = m
+            .buffer()
+            .iter()
+            .fold((u8::MAX, u8::MIN), |(min, max), &d| {
+                (min.min(d), max.max(d))
+            });
+        assert!(max >= 254);
+        assert_eq!(min, 0);
+        assert_eq!(m.buffer().iter().filter(|&&d| d >= 254).count(), 1);
+    }
+}
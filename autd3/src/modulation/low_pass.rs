@@ -0,0 +1,128 @@
+/*
+ * File: low_pass.rs
+ * Project: modulation
+ * Created Date: 15/10/2022
+ * Author: Shun Suzuki
+ * -----
+ * Last Modified: 15/10/2022
+ * Modified By: Shun Suzuki (suzuki@hapis.k.u-tokyo.ac.jp)
+ * -----
+ * Copyright (c) 2022 Shun Suzuki. All rights reserved.
+ *
+ */
+
+use std::f64::consts::PI;
+
+use anyhow::Result;
+use autd3_core::modulation::{ModProps, Modulation};
+use autd3_traits::Modulation;
+
+use crate::error::AUTDError;
+
+/// Modulation applying a first-order low-pass filter to another modulation in amplitude, e.g., to remove the clicks of hard transitions
+///
+/// The step response of the filter rises monotonically without overshoot.
+/// Since the modulation loops, the filter starts from its periodic steady state, i.e., as if the buffer had been played back infinitely, instead of zero.
+#[derive(Modulation)]
+pub struct LowPass<M: Modulation> {
+    props: ModProps,
+    m: M,
+    cutoff: f64,
+}
+
+impl<M: Modulation> LowPass<M> {
+    /// constructor.
+    ///
+    /// # Arguments
+    ///
+    /// * `m` - Modulation to be filtered. The sampling frequency division is inherited.
+    /// * `cutoff` - Cutoff frequency in Hz
+    ///
+    pub fn new(mut m: M, cutoff: f64) -> Self {
+        let mut props = ModProps::new();
+        props.freq_div = *m.sampling_frequency_division();
+        Self { props, m, cutoff }
+    }
+
+    fn calc(&mut self) -> Result<()> {
+        if self.cutoff.is_nan() || self.cutoff <= 0.0 {
+            return Err(AUTDError::InvalidCutoffFrequency(self.cutoff).into());
+        }
+
+        *self.m.sampling_frequency_division() = self.props.freq_div;
+        self.m.rebuild()?;
+
+        let amps = self
+            .m
+            .buffer()
+            .iter()
+            .map(|&d| (d as f64 / 255.0 * PI / 2.0).sin())
+            .collect::<Vec<_>>();
+        let len = amps.len();
+        if len == 0 {
+            self.props.buffer.clear();
+            return Ok(());
+        }
+
+        let alpha = 1.0 - (-2.0 * PI * self.cutoff / self.sampling_freq()).exp();
+        let decay = (1.0 - alpha).powi(len as i32);
+        let filter = |mut y: f64| {
+            amps.iter()
+                .map(|&x| {
+                    y += alpha * (x - y);
+                    y
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // the response to the initial value y0 decays by `decay` over the buffer, so the periodic steady state satisfies y0 = decay * y0 + (response from 0)
+        let y0 = if decay < 1.0 {
+            filter(0.0)[len - 1] / (1.0 - decay)
+        } else {
+            amps.iter().sum::<f64>() / len as f64
+        };
+
+        self.props.buffer = filter(y0)
+            .into_iter()
+            .map(|amp| (amp.clamp(0.0, 1.0).asin() * 2.0 / PI * 255.0).round() as u8)
+            .collect();
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modulation::Square;
+
+    #[test]
+    fn step_is_smoothed_monotonically() {
+        let mut m = LowPass::new(Square::new(100), 200.);
+        m.build().unwrap();
+        let buf = m.buffer();
+        let n = buf.len();
+        assert_eq!(n, (m.sampling_freq() / 100.0).round() as usize);
+
+        // rises while the input is high, and falls while low, without overshoot
+        assert!(buf[..n / 2].windows(2).all(|w| w[0] <= w[1]));
+        assert!(buf[n / 2..].windows(2).all(|w| w[0] >= w[1]));
+        assert!(buf[0] < buf[n / 2 - 1]);
+        assert!(buf[n / 2] > buf[n - 1]);
+        // the hard edges are rounded off
+        assert!(buf[0] > u8::MIN && buf[n / 2 - 1] < u8::MAX);
+        // starting from the periodic steady state, the loop continues smoothly
+        assert!(buf[n - 1] <= buf[0]);
+    }
+
+    #[test]
+    fn invalid_cutoff() {
+        [0., -1., f64::NAN].into_iter().for_each(|cutoff| {
+            let mut m = LowPass::new(Square::new(100), cutoff);
+            assert!(matches!(
+                m.build().unwrap_err().downcast_ref::<AUTDError>(),
+                Some(AUTDError::InvalidCutoffFrequency(_))
+            ));
+        });
+    }
+}
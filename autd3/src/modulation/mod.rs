@@ -11,12 +11,26 @@
  *
  */
 
+pub mod custom;
+pub mod expr;
+pub mod fourier;
+pub mod low_pass;
 pub mod sine;
 pub mod sine_legacy;
 pub mod sine_pressure;
+pub mod square;
 pub mod r#static;
+#[cfg(feature = "wav")]
+pub mod wav;
 
+pub use custom::Custom;
+pub use expr::Expr;
+pub use fourier::Fourier;
+pub use low_pass::LowPass;
 pub use r#static::Static;
 pub use sine::Sine;
 pub use sine_legacy::SineLegacy;
 pub use sine_pressure::SinePressure;
+pub use square::Square;
+#[cfg(feature = "wav")]
+pub use wav::{EnvelopeMode, Wav};
@@ -19,6 +19,15 @@ use autd3_traits::Modulation;
 
 use num::integer::gcd;
 
+/// Number of samples `n` in the shortest buffer containing whole periods of a sine wave of `freq` sampled at `sf`, and the number of the periods `rep`
+///
+/// `freq` is clamped to [1, `sf`/2].
+pub(crate) fn sampling_period(sf: usize, freq: usize) -> (usize, usize) {
+    let freq = freq.clamp(1, sf / 2);
+    let d = gcd(sf, freq);
+    (sf / d, freq / d)
+}
+
 /// Sine wave modulation in ultrasound amplitude
 #[derive(Modulation)]
 pub struct Sine {
@@ -59,14 +68,7 @@ impl Sine {
 
     #[allow(clippy::unnecessary_wraps)]
     fn calc(&mut self) -> Result<()> {
-        let sf = self.sampling_freq() as usize;
-
-        let freq = self.freq.clamp(1, sf / 2);
-
-        let d = gcd(sf, freq);
-
-        let n = sf / d;
-        let rep = freq / d;
+        let (n, rep) = sampling_period(self.sampling_freq() as usize, self.freq);
 
         self.props.buffer.resize(n, 0);
 
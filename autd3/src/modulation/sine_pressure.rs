@@ -19,7 +19,10 @@ use autd3_traits::Modulation;
 
 use num::integer::gcd;
 
-/// Sine wave modulation in ultrasound amplitude
+/// Sine wave modulation in radiation pressure
+///
+/// Since the radiation pressure is proportional to the square of the ultrasound amplitude, the ultrasound amplitude is modulated by the square root of the sine wave.
+/// The parts of the wave out of [0, 1] are clipped before taking the square root.
 #[derive(Modulation)]
 pub struct SinePressure {
     props: ModProps,
@@ -57,6 +60,13 @@ impl SinePressure {
         }
     }
 
+    /// Set the peak to peak amplitude of the wave, i.e., the depth of the modulation, keeping the offset
+    ///
+    /// For example, `SinePressure::new(150).with_amp(0.5)` oscillates from 0.25 to 0.75 in radiation pressure.
+    pub fn with_amp(self, amp: f64) -> Self {
+        Self { amp, ..self }
+    }
+
     #[allow(clippy::unnecessary_wraps)]
     fn calc(&mut self) -> Result<()> {
         let sf = self.sampling_freq() as usize;
@@ -72,7 +82,7 @@ impl SinePressure {
 
         self.props.buffer.iter_mut().enumerate().for_each(|(i, m)| {
             let amp = self.amp / 2.0 * (2.0 * PI * (rep * i) as f64 / n as f64).sin() + self.offset;
-            let amp = amp.sqrt().clamp(0.0, 1.0);
+            let amp = amp.clamp(0.0, 1.0).sqrt();
             let duty = amp.asin() * 2.0 / PI * 255.0;
             *m = duty as u8
         });
@@ -80,3 +90,87 @@ impl SinePressure {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a duty step of the buffer changes the pressure by at most 2 * (PI / 2 / 255)
+    const EPS: f64 = 0.0125;
+
+    fn pressure(m: &mut SinePressure) -> Vec<f64> {
+        m.build().unwrap();
+        m.buffer()
+            .iter()
+            .map(|&d| (d as f64 / 255.0 * PI / 2.0).sin().powi(2))
+            .collect()
+    }
+
+    fn expected(freq: usize, amp: f64, offset: f64, n: usize, sampling_freq: f64) -> Vec<f64> {
+        (0..n)
+            .map(|i| {
+                let t = i as f64 / sampling_freq;
+                (amp / 2.0 * (2.0 * PI * freq as f64 * t).sin() + offset).clamp(0.0, 1.0)
+            })
+            .collect()
+    }
+
+    fn assert_near(actual: &[f64], expected: &[f64]) {
+        actual.iter().zip(expected.iter()).for_each(|(a, e)| {
+            assert!((a - e).abs() < EPS, "{} != {}", a, e);
+        });
+    }
+
+    // amplitude of the DFT component of `rep` cycles per buffer
+    fn component(p: &[f64], rep: usize) -> f64 {
+        let n = p.len() as f64;
+        let (re, im) = p.iter().enumerate().fold((0.0, 0.0), |(re, im), (i, &v)| {
+            let theta = 2.0 * PI * (rep * i) as f64 / n;
+            (re + v * theta.cos(), im + v * theta.sin())
+        });
+        2.0 * (re * re + im * im).sqrt() / n
+    }
+
+    #[test]
+    fn squared_buffer_is_sinusoid() {
+        let mut m = SinePressure::new(150).with_amp(0.5);
+        let p = pressure(&mut m);
+        let sf = m.sampling_freq();
+        assert_near(&p, &expected(150, 0.5, 0.5, p.len(), sf));
+
+        let rep = (150.0 * p.len() as f64 / sf).round() as usize;
+        assert!((component(&p, rep) - 0.25).abs() < EPS);
+        (1..p.len() / 2)
+            .filter(|&k| k != rep)
+            .for_each(|k| assert!(component(&p, k) < EPS, "harmonic {}", k));
+    }
+
+    #[test]
+    fn squared_buffer_at_full_depth() {
+        let mut m = SinePressure::new(150);
+        let p = pressure(&mut m);
+        assert_near(&p, &expected(150, 1.0, 0.5, p.len(), m.sampling_freq()));
+        assert_eq!(*m.buffer().iter().max().unwrap(), 255);
+        assert_eq!(*m.buffer().iter().min().unwrap(), 0);
+    }
+
+    #[test]
+    fn squared_buffer_clipped() {
+        [(1.0, 0.75), (1.0, 0.25), (2.0, 0.5)]
+            .into_iter()
+            .for_each(|(amp, offset)| {
+                let mut m = SinePressure::with_params(150, amp, offset);
+                let p = pressure(&mut m);
+                let e = expected(150, amp, offset, p.len(), m.sampling_freq());
+                assert_near(&p, &e);
+                p.iter().zip(e.iter()).for_each(|(&p, &e)| {
+                    if e == 1.0 {
+                        assert_eq!(p, 1.0);
+                    }
+                    if e == 0.0 {
+                        assert_eq!(p, 0.0);
+                    }
+                });
+            });
+    }
+}
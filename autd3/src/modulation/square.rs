@@ -0,0 +1,170 @@
+/*
+ * File: square.rs
+ * Project: modulation
+ * Created Date: 15/10/2022
+ * Author: Shun Suzuki
+ * -----
+ * Last Modified: 15/10/2022
+ * Modified By: Shun Suzuki (suzuki@hapis.k.u-tokyo.ac.jp)
+ * -----
+ * Copyright (c) 2022 Shun Suzuki. All rights reserved.
+ *
+ */
+
+use anyhow::Result;
+use autd3_core::modulation::{ModProps, Modulation};
+use autd3_traits::Modulation;
+
+/// Square wave modulation
+#[derive(Modulation)]
+pub struct Square {
+    props: ModProps,
+    freq: usize,
+    low: u8,
+    high: u8,
+    duty: f64,
+    high_samples: Option<usize>,
+}
+
+impl Square {
+    /// constructor.
+    ///
+    /// # Arguments
+    ///
+    /// * `freq` - Frequency of the square wave
+    ///
+    pub fn new(freq: usize) -> Self {
+        Self::with_params(freq, u8::MIN, u8::MAX, 0.5)
+    }
+
+    /// constructor.
+    ///
+    /// # Arguments
+    ///
+    /// * `freq` - Frequency of the square wave
+    /// * `duty` - Ratio of high level in one period (0.0 to 1.0)
+    ///
+    pub fn with_duty(freq: usize, duty: f64) -> Self {
+        Self::with_params(freq, u8::MIN, u8::MAX, duty)
+    }
+
+    /// constructor.
+    ///
+    /// # Arguments
+    ///
+    /// * `freq` - Frequency of the square wave
+    /// * `low` - Duty ratio of low level
+    /// * `high` - Duty ratio of high level
+    /// * `duty` - Ratio of high level in one period (0.0 to 1.0)
+    ///
+    pub fn with_params(freq: usize, low: u8, high: u8, duty: f64) -> Self {
+        Self {
+            props: ModProps::new(),
+            freq,
+            low,
+            high,
+            duty: duty.clamp(0.0, 1.0),
+            high_samples: None,
+        }
+    }
+
+    /// constructor.
+    /// High level lasts for `high_samples` samples in each period.
+    /// If `high_samples` is 0 or not less than the number of samples in one period, the output becomes constant.
+    ///
+    /// # Arguments
+    ///
+    /// * `freq` - Frequency of the pulse train
+    /// * `high_samples` - The number of samples of high level in one period
+    ///
+    pub fn pulse(freq: usize, high_samples: usize) -> Self {
+        Self {
+            high_samples: Some(high_samples),
+            ..Self::new(freq)
+        }
+    }
+
+    #[allow(clippy::unnecessary_wraps)]
+    fn calc(&mut self) -> Result<()> {
+        let sf = self.sampling_freq() as usize;
+
+        let freq = self.freq.clamp(1, sf / 2);
+
+        let n = (sf as f64 / freq as f64).round() as usize;
+        let high_samples = self
+            .high_samples
+            .unwrap_or_else(|| (n as f64 * self.duty).round() as usize)
+            .min(n);
+
+        self.props.buffer.clear();
+        self.props.buffer.resize(high_samples, self.high);
+        self.props.buffer.resize(n, self.low);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use autd3_core::modulation::ModulationOptions;
+
+    use super::*;
+
+    fn high_samples(m: &mut Square) -> usize {
+        m.build().unwrap();
+        m.buffer().iter().take_while(|&&d| d == u8::MAX).count()
+    }
+
+    #[test]
+    fn duty() {
+        let mut m = Square::with_duty(200, 0.25);
+        let n = (m.sampling_freq() / 200.0).round() as usize;
+        assert_eq!(high_samples(&mut m), n / 4);
+        assert_eq!(m.buffer().len(), n);
+        assert!(m.buffer()[n / 4..].iter().all(|&d| d == u8::MIN));
+
+        let mut m = Square::new(200);
+        assert_eq!(high_samples(&mut m), n / 2);
+
+        let mut m = Square::with_duty(200, 2.0);
+        assert_eq!(high_samples(&mut m), n);
+        let mut m = Square::with_duty(200, -1.0);
+        assert_eq!(high_samples(&mut m), 0);
+    }
+
+    #[test]
+    fn pulse() {
+        let mut m = Square::pulse(200, 3);
+        let n = (m.sampling_freq() / 200.0).round() as usize;
+        assert_eq!(high_samples(&mut m), 3);
+        assert_eq!(m.buffer().len(), n);
+
+        let mut m = Square::pulse(200, n + 1);
+        assert_eq!(high_samples(&mut m), n);
+        assert_eq!(m.buffer().len(), n);
+    }
+
+    #[test]
+    fn phase_offset() {
+        let n = (Square::new(200).sampling_freq() / 200.0).round() as usize;
+        let high_range = |m: &mut Square| {
+            m.build().unwrap();
+            let start = m.buffer().iter().position(|&d| d == u8::MAX).unwrap();
+            let len = m.buffer()[start..]
+                .iter()
+                .take_while(|&&d| d == u8::MAX)
+                .count();
+            (start, len)
+        };
+
+        let mut m = Square::with_duty(200, 0.25).with_phase_offset(0.5);
+        assert_eq!(high_range(&mut m), (n / 2, n / 4));
+        assert_eq!(m.buffer().len(), n);
+
+        let mut m = Square::with_duty(200, 0.25).with_phase_offset(-0.25);
+        assert_eq!(high_range(&mut m), (n / 4, n / 4));
+
+        let mut m = Square::with_duty(200, 0.25).with_phase_offset(1.0);
+        assert_eq!(high_range(&mut m), (0, n / 4));
+    }
+}
@@ -0,0 +1,272 @@
+/*
+ * File: wav.rs
+ * Project: modulation
+ * Created Date: 15/10/2022
+ * Author: Shun Suzuki
+ * -----
+ * Last Modified: 15/10/2022
+ * Modified By: Shun Suzuki (suzuki@hapis.k.u-tokyo.ac.jp)
+ * -----
+ * Copyright (c) 2022 Shun Suzuki. All rights reserved.
+ *
+ */
+
+use std::{f64::consts::PI, path::Path};
+
+use anyhow::Result;
+use autd3_core::{
+    modulation::{ModProps, Modulation},
+    FPGAError, MOD_BUF_SIZE_MAX,
+};
+use autd3_traits::Modulation;
+use hound::SampleFormat;
+use rustfft::{num_complex::Complex, FftPlanner};
+
+use crate::error::AUTDError;
+
+/// How to convert the audio samples into the amplitude of the modulation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EnvelopeMode {
+    /// Use the samples as they are, mapping the full scale of the file to the amplitude from 0 to 1
+    Raw,
+    /// Root mean square of the samples in the circular moving window of `window` samples at the sampling rate of the file
+    Rms { window: usize },
+    /// Magnitude of the analytic signal computed by FFT, i.e., the instantaneous amplitude
+    ///
+    /// Unlike [Rms](Self::Rms), this follows the envelope of an amplitude-modulated tone without rectification, e.g., a tone modulated at 200 Hz yields a smooth 200 Hz envelope.
+    Hilbert,
+}
+
+/// Modulation playing back a monaural WAV file
+///
+/// The amplitude is computed from the samples as specified by [EnvelopeMode], which is [Raw](EnvelopeMode::Raw) by default.
+/// It is then resampled to the sampling frequency of the modulation by linear interpolation, and converted into the duty ratio in the same way as [Sine](crate::modulation::Sine).
+#[derive(Modulation)]
+pub struct Wav {
+    props: ModProps,
+    samples: Vec<f64>,
+    sample_rate: u32,
+    envelope: EnvelopeMode,
+}
+
+impl Wav {
+    /// constructor.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the WAV file. Only monaural 8, 16, 24, or 32-bit integer and 32-bit float PCM are supported.
+    ///
+    pub fn new(path: impl AsRef<Path>) -> Result<Self> {
+        let mut reader = hound::WavReader::open(path)?;
+        let spec = reader.spec();
+        if spec.channels != 1 {
+            return Err(AUTDError::WavNotMonaural(spec.channels).into());
+        }
+
+        // normalize to [-1, 1]
+        let samples = match (spec.sample_format, spec.bits_per_sample) {
+            (SampleFormat::Int, bits @ (8 | 16 | 24 | 32)) => {
+                let full_scale = (1u64 << (bits - 1)) as f64;
+                reader
+                    .samples::<i32>()
+                    .map(|s| s.map(|s| s as f64 / full_scale))
+                    .collect::<Result<Vec<_>, _>>()?
+            }
+            (SampleFormat::Float, 32) => reader
+                .samples::<f32>()
+                .map(|s| s.map(|s| s as f64))
+                .collect::<Result<Vec<_>, _>>()?,
+            (SampleFormat::Int, bits) => {
+                return Err(AUTDError::WavUnsupportedFormat(bits, "integer".to_owned()).into())
+            }
+            (SampleFormat::Float, bits) => {
+                return Err(AUTDError::WavUnsupportedFormat(bits, "float".to_owned()).into())
+            }
+        };
+        if samples.is_empty() {
+            return Err(AUTDError::EmptyModulationBuffer.into());
+        }
+
+        Ok(Self {
+            props: ModProps::new(),
+            samples,
+            sample_rate: spec.sample_rate,
+            envelope: EnvelopeMode::Raw,
+        })
+    }
+
+    /// Set how to convert the samples into the amplitude
+    pub fn with_envelope(self, envelope: EnvelopeMode) -> Self {
+        Self { envelope, ..self }
+    }
+
+    /// Amplitude from 0 to 1 at the sampling rate of the file
+    fn envelope(&self) -> Vec<f64> {
+        let len = self.samples.len();
+        match self.envelope {
+            EnvelopeMode::Raw => self.samples.iter().map(|s| (s + 1.0) / 2.0).collect(),
+            EnvelopeMode::Rms { window } => {
+                let window = window.clamp(1, len);
+                let start = window / 2;
+                (0..len)
+                    .map(|i| {
+                        ((0..window)
+                            .map(|k| self.samples[(i + len + k - start) % len].powi(2))
+                            .sum::<f64>()
+                            / window as f64)
+                            .sqrt()
+                    })
+                    .collect()
+            }
+            EnvelopeMode::Hilbert => {
+                let mut planner = FftPlanner::new();
+                let mut spectrum = self
+                    .samples
+                    .iter()
+                    .map(|&s| Complex::new(s, 0.0))
+                    .collect::<Vec<_>>();
+                planner.plan_fft_forward(len).process(&mut spectrum);
+                // keep DC and Nyquist, double the positive frequencies, and remove the negative ones
+                spectrum.iter_mut().enumerate().skip(1).for_each(|(k, c)| {
+                    if 2 * k < len {
+                        *c *= 2.0;
+                    } else if 2 * k > len {
+                        *c = Complex::new(0.0, 0.0);
+                    }
+                });
+                planner.plan_fft_inverse(len).process(&mut spectrum);
+                spectrum.iter().map(|c| c.norm() / len as f64).collect()
+            }
+        }
+    }
+
+    fn calc(&mut self) -> Result<()> {
+        let ratio = self.sampling_freq() / self.sample_rate as f64;
+        let n = ((self.samples.len() as f64 * ratio).round() as usize).max(1);
+        if n > MOD_BUF_SIZE_MAX {
+            return Err(FPGAError::ModulationOutOfBuffer(n).into());
+        }
+
+        let envelope = self.envelope();
+        let last = envelope.len() - 1;
+        self.props.buffer = (0..n)
+            .map(|i| {
+                let t = i as f64 / ratio;
+                let idx = (t.floor() as usize).min(last);
+                let frac = t - idx as f64;
+                let next = envelope[(idx + 1).min(last)];
+                let v = envelope[idx] * (1.0 - frac) + next * frac;
+                (v.clamp(0.0, 1.0).asin() * 2.0 / PI * 255.0).round() as u8
+            })
+            .collect();
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_wav(name: &str, channels: u16, samples: &[i16]) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(name);
+        let spec = hound::WavSpec {
+            channels,
+            sample_rate: 4000,
+            bits_per_sample: 16,
+            sample_format: SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        samples
+            .iter()
+            .for_each(|&s| writer.write_sample(s).unwrap());
+        writer.finalize().unwrap();
+        path
+    }
+
+    #[test]
+    fn raw_amplitude_to_duty() {
+        let samples = [i16::MIN, -16384, 0, 16384, i16::MAX];
+        let path = write_wav("autd3_wav_raw_test.wav", 1, &samples);
+        let mut m = Wav::new(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(m.sampling_freq(), 4000.);
+
+        m.build().unwrap();
+        let expect = samples
+            .iter()
+            .map(|&s| {
+                let amp = (s as f64 / 32768. + 1.0) / 2.0;
+                (amp.asin() * 2.0 / PI * 255.0).round() as u8
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(m.buffer(), &expect);
+        assert_eq!(m.buffer()[0], 0);
+        assert_eq!(m.buffer()[2], 85);
+    }
+
+    #[test]
+    fn reject_stereo() {
+        let path = write_wav("autd3_wav_stereo_test.wav", 2, &[0, 0]);
+        let res = Wav::new(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            res.err().unwrap().downcast_ref::<AUTDError>(),
+            Some(AUTDError::WavNotMonaural(2))
+        ));
+    }
+
+    fn am_signal(fs: f64, n: usize) -> (Vec<f64>, Vec<f64>) {
+        (0..n)
+            .map(|i| {
+                let t = i as f64 / fs;
+                let env = 0.5 * (1.0 + 0.8 * (2.0 * PI * 200.0 * t).cos());
+                (env * (2.0 * PI * 2000.0 * t).sin(), env)
+            })
+            .unzip()
+    }
+
+    fn wav_from_samples(samples: Vec<f64>, sample_rate: u32, envelope: EnvelopeMode) -> Wav {
+        Wav {
+            props: ModProps::new(),
+            samples,
+            sample_rate,
+            envelope,
+        }
+    }
+
+    #[test]
+    fn hilbert_envelope_of_am_signal() {
+        let (samples, expect) = am_signal(16000., 1600);
+        let m = wav_from_samples(samples, 16000, EnvelopeMode::Hilbert);
+        m.envelope()
+            .iter()
+            .zip(expect.iter())
+            .for_each(|(a, b)| assert!((a - b).abs() < 1e-9));
+    }
+
+    #[test]
+    fn rms_envelope_of_am_signal() {
+        let (samples, expect) = am_signal(16000., 1600);
+        // the window is one period of the carrier
+        let m = wav_from_samples(samples, 16000, EnvelopeMode::Rms { window: 8 });
+        m.envelope()
+            .iter()
+            .zip(expect.iter())
+            .for_each(|(a, b)| assert!((a - b / 2f64.sqrt()).abs() < 0.03));
+    }
+
+    #[test]
+    fn hilbert_envelope_to_duty() {
+        let (samples, expect) = am_signal(16000., 1600);
+        let mut m = wav_from_samples(samples, 16000, EnvelopeMode::Hilbert);
+        m.build().unwrap();
+        assert_eq!(m.buffer().len(), 400);
+        m.buffer()
+            .iter()
+            .zip(expect.iter().step_by(4))
+            .for_each(|(&duty, &amp)| {
+                assert_eq!(duty, (amp.asin() * 2.0 / PI * 255.0).round() as u8)
+            });
+    }
+}
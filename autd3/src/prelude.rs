@@ -15,11 +15,13 @@ pub use crate::{controller::Controller, gain::*, modulation::*};
 
 pub use autd3_core::{
     delay::ModDelay,
+    gain::{DutyMap, GainOptions},
     geometry::{
         Amplitudes, Geometry, GeometryBuilder, LegacyTransducer, NormalPhaseTransducer,
         NormalTransducer, Transducer, Vector3,
     },
     link::Link,
+    modulation::ModulationOptions,
     silencer_config::SilencerConfig,
     stm::{GainSTM, PointSTM, STM},
     Mode, DEVICE_HEIGHT, DEVICE_WIDTH, NUM_TRANS_IN_UNIT, NUM_TRANS_X, NUM_TRANS_Y,
@@ -0,0 +1,252 @@
+/*
+ * File: shared_controller.rs
+ * Project: src
+ * Created Date: 15/10/2022
+ * Author: Shun Suzuki
+ * -----
+ * Last Modified: 15/10/2022
+ * Modified By: Shun Suzuki (suzuki@hapis.k.u-tokyo.ac.jp)
+ * -----
+ * Copyright (c) 2022 Shun Suzuki. All rights reserved.
+ *
+ */
+
+use std::{
+    sync::mpsc::{channel, Receiver, Sender, TryRecvError},
+    thread::{self, JoinHandle},
+};
+
+use anyhow::Result;
+
+use autd3_core::{
+    gain::Gain,
+    geometry::Transducer,
+    interface::{Empty, Filled, Sendable},
+    link::Link,
+    modulation::Modulation,
+};
+
+use crate::{controller::Controller, error::AUTDError};
+
+type Command<L, T> = Box<dyn FnOnce(&mut Controller<L, T>) + Send>;
+
+/// Controller which can be shared among threads
+///
+/// The commands submitted from each thread are queued and executed in order on a dedicated thread.
+pub struct SharedController<L: Link + 'static, T: Transducer + Send + 'static> {
+    sender: Option<Sender<Command<L, T>>>,
+    th: Option<JoinHandle<Controller<L, T>>>,
+}
+
+impl<L: Link + 'static, T: Transducer + Send + 'static> SharedController<L, T> {
+    pub fn new(cnt: Controller<L, T>) -> Self {
+        let (sender, receiver) = channel::<Command<L, T>>();
+        let th = thread::spawn(move || {
+            let mut cnt = cnt;
+            for cmd in receiver {
+                cmd(&mut cnt);
+            }
+            cnt
+        });
+        Self {
+            sender: Some(sender),
+            th: Some(th),
+        }
+    }
+
+    /// Submit a command. The returned receiver gets the result after the command is executed.
+    pub fn execute<F>(&self, f: F) -> Receiver<Result<bool>>
+    where
+        F: FnOnce(&mut Controller<L, T>) -> Result<bool> + Send + 'static,
+    {
+        let (tx, rx) = channel();
+        let cmd: Command<L, T> = Box::new(move |cnt| {
+            let _ = tx.send(f(cnt));
+        });
+        if let Some(sender) = &self.sender {
+            let _ = sender.send(cmd);
+        }
+        rx
+    }
+
+    pub fn send_gain<G>(&self, gain: G) -> Receiver<Result<bool>>
+    where
+        G: Gain<T> + Sendable<T, H = Empty, B = Filled> + Send + 'static,
+    {
+        let mut gain = gain;
+        self.execute(move |cnt| cnt.send(&mut gain).flush())
+    }
+
+    pub fn send_modulation<M>(&self, modulation: M) -> Receiver<Result<bool>>
+    where
+        M: Modulation + Sendable<T, H = Filled, B = Empty> + Send + 'static,
+    {
+        let mut modulation = modulation;
+        self.execute(move |cnt| cnt.send(&mut modulation).flush())
+    }
+
+    /// Queue sending the gain and return a handle to poll its completion
+    ///
+    /// This allows to compute the next frame while the current one is being sent.
+    pub fn send_async_handle<G>(&self, gain: G) -> SendHandle
+    where
+        G: Gain<T> + Sendable<T, H = Empty, B = Filled> + Send + 'static,
+    {
+        SendHandle::new(self.send_gain(gain))
+    }
+
+    /// Wait for all submitted commands and return the inner controller
+    pub fn into_inner(mut self) -> Controller<L, T> {
+        self.sender.take();
+        self.th.take().unwrap().join().unwrap()
+    }
+}
+
+impl<L: Link + 'static, T: Transducer + Send + 'static> Drop for SharedController<L, T> {
+    fn drop(&mut self) {
+        self.sender.take();
+        if let Some(th) = self.th.take() {
+            let _ = th.join();
+        }
+    }
+}
+
+/// Handle of a queued send
+pub struct SendHandle {
+    rx: Receiver<Result<bool>>,
+    result: Option<Result<bool>>,
+}
+
+impl SendHandle {
+    fn new(rx: Receiver<Result<bool>>) -> Self {
+        Self { rx, result: None }
+    }
+
+    /// Return whether the send has been completed without blocking
+    pub fn is_done(&mut self) -> bool {
+        if self.result.is_some() {
+            return true;
+        }
+        match self.rx.try_recv() {
+            Ok(res) => {
+                self.result = Some(res);
+                true
+            }
+            Err(TryRecvError::Empty) => false,
+            Err(TryRecvError::Disconnected) => {
+                self.result = Some(Err(AUTDError::SendCanceled.into()));
+                true
+            }
+        }
+    }
+
+    /// Block until the send is completed and return its result
+    pub fn wait(self) -> Result<bool> {
+        match self.result {
+            Some(res) => res,
+            None => self
+                .rx
+                .recv()
+                .unwrap_or_else(|_| Err(AUTDError::SendCanceled.into())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use autd3_core::geometry::{GeometryBuilder, Vector3};
+
+    use super::*;
+    use crate::{gain::Focus, link::NullLink, modulation::Sine};
+
+    #[test]
+    fn serialize_commands_from_threads() {
+        const NUM_THREADS: usize = 4;
+        const NUM_COMMANDS: usize = 10;
+
+        let mut geometry = GeometryBuilder::new().legacy_mode().build();
+        geometry.add_device(Vector3::zeros(), Vector3::zeros());
+        let center = geometry.center() + Vector3::new(0., 0., 150.);
+        let autd = SharedController::new(Controller::open(geometry, NullLink::new()).unwrap());
+
+        let log = Arc::new(Mutex::new(vec![]));
+        thread::scope(|s| {
+            (0..NUM_THREADS).for_each(|th| {
+                let autd = &autd;
+                let log = log.clone();
+                s.spawn(move || {
+                    let results = (0..NUM_COMMANDS)
+                        .map(|i| {
+                            let log = log.clone();
+                            autd.execute(move |_| {
+                                log.lock().unwrap().push((th, i));
+                                Ok(true)
+                            })
+                        })
+                        .collect::<Vec<_>>();
+                    results
+                        .into_iter()
+                        .for_each(|rx| assert!(rx.recv().unwrap().unwrap()));
+                });
+            });
+        });
+
+        let log = log.lock().unwrap();
+        assert_eq!(log.len(), NUM_THREADS * NUM_COMMANDS);
+        (0..NUM_THREADS).for_each(|th| {
+            let order = log
+                .iter()
+                .filter(|&&(t, _)| t == th)
+                .map(|&(_, i)| i)
+                .collect::<Vec<_>>();
+            assert_eq!(order, (0..NUM_COMMANDS).collect::<Vec<_>>());
+        });
+
+        assert!(autd
+            .send_modulation(Sine::new(150))
+            .recv()
+            .unwrap()
+            .unwrap());
+        let mut handle = autd.send_async_handle(Focus::new(center));
+        while !handle.is_done() {}
+        assert!(handle.wait().unwrap());
+
+        autd.into_inner().close().unwrap();
+    }
+
+    #[test]
+    fn send_handle() {
+        let mut geometry = GeometryBuilder::new().legacy_mode().build();
+        geometry.add_device(Vector3::zeros(), Vector3::zeros());
+        let center = geometry.center() + Vector3::new(0., 0., 150.);
+        let autd = SharedController::new(Controller::open(geometry, NullLink::new()).unwrap());
+
+        // block the queue until the gate is opened
+        let (gate, blocked) = channel::<()>();
+        let _ = autd.execute(move |_| {
+            blocked.recv().unwrap();
+            Ok(true)
+        });
+        let mut handle = autd.send_async_handle(Focus::new(center));
+        assert!(!handle.is_done());
+
+        gate.send(()).unwrap();
+        assert!(handle.wait().unwrap());
+
+        autd.into_inner().close().unwrap();
+    }
+
+    #[test]
+    fn send_handle_canceled() {
+        let (tx, rx) = channel::<Result<bool>>();
+        drop(tx);
+        let mut handle = SendHandle::new(rx);
+        assert!(handle.is_done());
+        assert!(matches!(
+            handle.wait().unwrap_err().downcast_ref::<AUTDError>(),
+            Some(AUTDError::SendCanceled)
+        ));
+    }
+}